@@ -0,0 +1,370 @@
+use serde::Deserialize;
+
+use crate::{BYTES_PER_PIXEL, Transfer};
+
+/// Window radius (in pixels) above which `Auto` switches to
+/// the separable approximation.
+const EXACT_MAX_RADIUS: usize = 5;
+
+/// Bilateral filter parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BilateralParams {
+    /// Standard deviation of the spatial Gaussian in pixels.
+    /// The filter window extends `ceil(2 * sigma_spatial)`
+    /// pixels in each direction.
+    pub sigma_spatial: f64,
+    /// Standard deviation of the range (color) Gaussian in
+    /// 8-bit channel units.
+    pub sigma_range: f64,
+    /// Evaluation strategy.
+    pub method: BilateralMethod,
+}
+
+impl Default for BilateralParams {
+    fn default() -> Self {
+        Self {
+            sigma_spatial: 3.0,
+            sigma_range: 25.0,
+            method: BilateralMethod::default(),
+        }
+    }
+}
+
+impl BilateralParams {
+    /// Returns `true` if both sigmas are positive and finite.
+    pub fn is_valid(&self) -> bool {
+        self.sigma_spatial.is_finite()
+            && self.sigma_spatial > 0.0
+            && self.sigma_range.is_finite()
+            && self.sigma_range > 0.0
+    }
+
+    /// Filter window radius in pixels.
+    pub fn radius(&self) -> usize {
+        // sigma_spatial is validated positive and finite; the
+        // window is capped to keep the weight table bounded.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let radius = (2.0 * self.sigma_spatial).ceil().min(1024.0) as usize;
+        radius
+    }
+}
+
+/// How the bilateral filter is evaluated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BilateralMethod {
+    /// Exact for small windows, separable for large ones.
+    #[default]
+    Auto,
+    /// Full 2D window — `O(radius²)` per pixel.
+    Exact,
+    /// Horizontal then vertical 1D pass — `O(radius)` per
+    /// pixel. Approximates the exact filter closely except
+    /// along diagonal edges.
+    Separable,
+}
+
+/// Applies an edge-preserving bilateral filter.
+///
+/// Each pixel becomes an average of its neighbors weighted by
+/// spatial distance and by color similarity, so pixels across
+/// a strong edge contribute almost nothing. Color differences
+/// are measured on stored values; averaging happens in the
+/// space selected by `transfer`.
+pub fn bilateral_filter(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    params: &BilateralParams,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    let radius = params.radius();
+    let range = range_weights(params.sigma_range);
+    let spatial = &gaussian(radius, params.sigma_spatial);
+    let separable = match params.method {
+        BilateralMethod::Auto => radius > EXACT_MAX_RADIUS,
+        BilateralMethod::Exact => false,
+        BilateralMethod::Separable => true,
+    };
+
+    let mut temp = vec![0u8; data.len()];
+
+    for _ in 0..iterations {
+        if separable {
+            filter_pass(
+                data,
+                &mut temp,
+                width,
+                height,
+                &range,
+                transfer,
+                |x, y| {
+                    window(x, width, radius)
+                        .map(move |nx| ((nx, y), spatial[x.abs_diff(nx)]))
+                },
+            );
+            filter_pass(
+                &temp,
+                data,
+                width,
+                height,
+                &range,
+                transfer,
+                |x, y| {
+                    window(y, height, radius)
+                        .map(move |ny| ((x, ny), spatial[y.abs_diff(ny)]))
+                },
+            );
+        } else {
+            filter_pass(
+                data,
+                &mut temp,
+                width,
+                height,
+                &range,
+                transfer,
+                |x, y| {
+                    window(y, height, radius).flat_map(move |ny| {
+                        window(x, width, radius).map(move |nx| {
+                            let (dx, dy) = (x.abs_diff(nx), y.abs_diff(ny));
+                            ((nx, ny), spatial[dx] * spatial[dy])
+                        })
+                    })
+                },
+            );
+            data.copy_from_slice(&temp);
+        }
+    }
+}
+
+/// Runs one bilateral pass from `src` into `dst`.
+///
+/// `neighbors(x, y)` yields every `((nx, ny), spatial_weight)`
+/// contributing to pixel `(x, y)`.
+fn filter_pass<I>(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    range: &[f64; 256],
+    transfer: Transfer,
+    neighbors: impl Fn(usize, usize) -> I,
+) where
+    I: Iterator<Item = ((usize, usize), f64)>,
+{
+    for y in 0..height {
+        for x in 0..width {
+            let center = (y * width + x) * BYTES_PER_PIXEL;
+            let mut sum = [0.0_f64; BYTES_PER_PIXEL];
+            let mut total_weight = 0.0_f64;
+
+            for ((nx, ny), spatial) in neighbors(x, y) {
+                let n = (ny * width + nx) * BYTES_PER_PIXEL;
+                let weight = spatial
+                    * range[usize::from(src[center].abs_diff(src[n]))]
+                    * range[usize::from(src[center + 1].abs_diff(src[n + 1]))]
+                    * range[usize::from(src[center + 2].abs_diff(src[n + 2]))];
+
+                for (c, s) in sum.iter_mut().enumerate() {
+                    let t = if c < 3 { transfer } else { Transfer::Encoded };
+                    *s = t.decode(src[n + c]).mul_add(weight, *s);
+                }
+                total_weight += weight;
+            }
+
+            // The center pixel always contributes weight 1.0,
+            // so total_weight is never zero.
+            dst[center] = transfer.encode(sum[0] / total_weight);
+            dst[center + 1] = transfer.encode(sum[1] / total_weight);
+            dst[center + 2] = transfer.encode(sum[2] / total_weight);
+            dst[center + 3] = Transfer::Encoded.encode(sum[3] / total_weight);
+        }
+    }
+}
+
+/// Indices within `radius` of `center`, clipped to `[0, len)`.
+fn window(center: usize, len: usize, radius: usize) -> std::ops::Range<usize> {
+    center.saturating_sub(radius)..(center + radius + 1).min(len)
+}
+
+/// 1D Gaussian weights for offsets `0..=radius`.
+fn gaussian(radius: usize, sigma: f64) -> Vec<f64> {
+    let denom = 2.0 * sigma * sigma;
+    (0..=radius)
+        .map(|d| {
+            #[allow(clippy::cast_precision_loss)]
+            let d = d as f64;
+            (-(d * d) / denom).exp()
+        })
+        .collect()
+}
+
+/// Per-channel range weights for absolute differences `0..=255`.
+///
+/// The product over R, G and B equals the Gaussian of the
+/// Euclidean color distance.
+fn range_weights(sigma: f64) -> [f64; 256] {
+    let denom = 2.0 * sigma * sigma;
+    std::array::from_fn(|d| {
+        #[allow(clippy::cast_precision_loss)]
+        let d = d as f64;
+        (-(d * d) / denom).exp()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an opaque image whose left half is `left` and
+    /// right half is `right` (gray levels).
+    fn step_edge(width: usize, height: usize, left: u8, right: u8) -> Vec<u8> {
+        let mut data = vec![255u8; width * height * BYTES_PER_PIXEL];
+        for y in 0..height {
+            for x in 0..width {
+                let v = if x < width / 2 { left } else { right };
+                let i = (y * width + x) * BYTES_PER_PIXEL;
+                data[i..i + 3].fill(v);
+            }
+        }
+        data
+    }
+
+    /// Sum of squared deviations of the red channel from `mean`.
+    fn red_variance(data: &[u8], mean: f64) -> f64 {
+        data.iter()
+            .step_by(BYTES_PER_PIXEL)
+            .map(|&v| (f64::from(v) - mean).powi(2))
+            .sum()
+    }
+
+    fn params(method: BilateralMethod) -> BilateralParams {
+        BilateralParams {
+            sigma_spatial: 2.0,
+            sigma_range: 20.0,
+            method,
+        }
+    }
+
+    #[test]
+    fn strong_edge_is_preserved() {
+        for method in [BilateralMethod::Exact, BilateralMethod::Separable] {
+            let mut data = step_edge(12, 6, 10, 240);
+            let original = data.clone();
+            bilateral_filter(
+                &mut data,
+                12,
+                6,
+                &params(method),
+                1,
+                Transfer::Encoded,
+            );
+            assert_eq!(data, original, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn small_noise_is_smoothed() {
+        for method in [BilateralMethod::Exact, BilateralMethod::Separable] {
+            let mut data = step_edge(16, 16, 128, 128);
+            for (i, pixel) in data.chunks_mut(BYTES_PER_PIXEL).enumerate() {
+                let noise = if i % 3 == 0 { 8 } else { 0 };
+                pixel[..3].iter_mut().for_each(|v| *v += noise);
+            }
+            let before = red_variance(&data, 130.67);
+            bilateral_filter(
+                &mut data,
+                16,
+                16,
+                &params(method),
+                1,
+                Transfer::Encoded,
+            );
+            let after = red_variance(&data, 130.67);
+            assert!(after < before / 4.0, "{method:?}: {before} -> {after}");
+        }
+    }
+
+    #[test]
+    fn separable_is_close_to_exact() {
+        let mut exact = step_edge(10, 10, 60, 90);
+        let mut separable = exact.clone();
+        bilateral_filter(
+            &mut exact,
+            10,
+            10,
+            &params(BilateralMethod::Exact),
+            1,
+            Transfer::Encoded,
+        );
+        bilateral_filter(
+            &mut separable,
+            10,
+            10,
+            &params(BilateralMethod::Separable),
+            1,
+            Transfer::Encoded,
+        );
+        for (a, b) in exact.iter().zip(&separable) {
+            assert!(a.abs_diff(*b) <= 3, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn invalid_sigmas_are_rejected() {
+        let zero = BilateralParams {
+            sigma_range: 0.0,
+            ..BilateralParams::default()
+        };
+        assert!(!zero.is_valid());
+        let nan = BilateralParams {
+            sigma_spatial: f64::NAN,
+            ..BilateralParams::default()
+        };
+        assert!(!nan.is_valid());
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn uniform_image_unchanged(
+                w in 1..=12usize,
+                h in 1..=12usize,
+                pixel in any::<[u8; 4]>(),
+                sigma_spatial in 0.5..4.0f64,
+                separable in any::<bool>(),
+            ) {
+                let mut data: Vec<u8> = pixel
+                    .iter()
+                    .copied()
+                    .cycle()
+                    .take(w * h * BYTES_PER_PIXEL)
+                    .collect();
+                let original = data.clone();
+                let params = BilateralParams {
+                    sigma_spatial,
+                    sigma_range: 25.0,
+                    method: if separable {
+                        BilateralMethod::Separable
+                    } else {
+                        BilateralMethod::Exact
+                    },
+                };
+                bilateral_filter(
+                    &mut data,
+                    w,
+                    h,
+                    &params,
+                    1,
+                    Transfer::Encoded,
+                );
+                prop_assert_eq!(data, original);
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Deserialize;
+
+use crate::{BYTES_PER_PIXEL, Transfer};
+
+/// Explicit convolution kernel parameters.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KernelParams {
+    /// Kernel rows, top to bottom. Width and height must be
+    /// odd so the kernel has a center cell.
+    pub matrix: Vec<Vec<f64>>,
+    /// Value the weighted sum is divided by. Defaults to the
+    /// sum of the weights, or `1` if they sum to zero.
+    #[serde(default)]
+    pub divisor: Option<f64>,
+    /// Offset (in 8-bit channel units) added after division.
+    #[serde(default)]
+    pub bias: f64,
+}
+
+/// Reasons a kernel is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelError {
+    /// The matrix has no rows or no columns.
+    Empty,
+    /// Rows have different lengths.
+    Ragged,
+    /// Width or height is even.
+    EvenSize,
+    /// A weight, the divisor or the bias is NaN or infinite.
+    NonFinite,
+    /// The divisor is zero.
+    ZeroDivisor,
+}
+
+impl KernelParams {
+    /// Checks that the kernel is a non-empty rectangular matrix
+    /// with odd dimensions and finite coefficients.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`KernelError`] found.
+    pub fn validate(&self) -> Result<(), KernelError> {
+        let width = self.matrix.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err(KernelError::Empty);
+        }
+        if self.matrix.iter().any(|row| row.len() != width) {
+            return Err(KernelError::Ragged);
+        }
+        if width.is_multiple_of(2) || self.matrix.len().is_multiple_of(2) {
+            return Err(KernelError::EvenSize);
+        }
+        let coefficients_finite = self
+            .matrix
+            .iter()
+            .flatten()
+            .chain(self.divisor.as_ref())
+            .chain([&self.bias])
+            .all(|v| v.is_finite());
+        if !coefficients_finite {
+            return Err(KernelError::NonFinite);
+        }
+        if self.divisor == Some(0.0) {
+            return Err(KernelError::ZeroDivisor);
+        }
+        Ok(())
+    }
+
+    /// Largest distance from the center cell to an edge of the
+    /// kernel, i.e. how far one pass reads from each pixel.
+    pub fn reach(&self) -> usize {
+        let width = self.matrix.first().map_or(0, Vec::len);
+        width.max(self.matrix.len()) / 2
+    }
+
+    /// Effective divisor: explicit, or the weight sum if it is
+    /// non-zero, or `1`.
+    fn effective_divisor(&self) -> f64 {
+        self.divisor.unwrap_or_else(|| {
+            let sum: f64 = self.matrix.iter().flatten().sum();
+            if sum == 0.0 { 1.0 } else { sum }
+        })
+    }
+}
+
+/// Convolves the color channels of an RGBA buffer with a
+/// validated kernel.
+///
+/// The kernel is applied as a correlation (not flipped),
+/// centered on each pixel; samples outside the image repeat the
+/// nearest edge pixel. Results are clamped to the channel
+/// range. Alpha is left unchanged.
+pub fn convolve(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    params: &KernelParams,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    let kernel_height = params.matrix.len();
+    let kernel_width = params.matrix[0].len();
+    let (half_w, half_h) = (kernel_width / 2, kernel_height / 2);
+    let divisor = params.effective_divisor();
+    let max = transfer.decode(u8::MAX);
+    let bias = params.bias / 255.0 * max;
+
+    let mut temp = data.to_vec();
+
+    for _ in 0..iterations {
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0_f64; 3];
+
+                for (ky, row) in params.matrix.iter().enumerate() {
+                    let ny = (y + ky).saturating_sub(half_h).min(height - 1);
+                    for (kx, &weight) in row.iter().enumerate() {
+                        let nx = (x + kx).saturating_sub(half_w).min(width - 1);
+                        let src = (ny * width + nx) * BYTES_PER_PIXEL;
+                        for (c, s) in sum.iter_mut().enumerate() {
+                            *s = transfer
+                                .decode(data[src + c])
+                                .mul_add(weight, *s);
+                        }
+                    }
+                }
+
+                let dst = (y * width + x) * BYTES_PER_PIXEL;
+                for (c, s) in sum.into_iter().enumerate() {
+                    temp[dst + c] =
+                        transfer.encode((s / divisor + bias).clamp(0.0, max));
+                }
+            }
+        }
+        data.copy_from_slice(&temp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kernel(matrix: Vec<Vec<f64>>) -> KernelParams {
+        KernelParams {
+            matrix,
+            divisor: None,
+            bias: 0.0,
+        }
+    }
+
+    /// Creates a one-row opaque gray image from levels.
+    fn row(levels: &[u8]) -> Vec<u8> {
+        levels.iter().flat_map(|&v| [v, v, v, 255]).collect()
+    }
+
+    #[test]
+    fn identity_kernel_keeps_image() {
+        let mut data = row(&[3, 50, 200, 255]);
+        let original = data.clone();
+        let params = kernel(vec![
+            vec![0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.0, 0.0, 0.0],
+        ]);
+        convolve(&mut data, 4, 1, &params, 1, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn box_kernel_is_normalized_by_default() {
+        let mut data = row(&[0, 90, 0]);
+        let params = kernel(vec![vec![1.0, 1.0, 1.0]]);
+        convolve(&mut data, 3, 1, &params, 1, Transfer::Encoded);
+        assert_eq!(data, row(&[30, 30, 30]));
+    }
+
+    #[test]
+    fn edge_detect_with_bias_and_clamping() {
+        // Zero-sum kernel: divisor falls back to 1.
+        let mut data = row(&[10, 10, 200, 200]);
+        let params = KernelParams {
+            bias: 128.0,
+            ..kernel(vec![vec![-1.0, 1.0, 0.0]])
+        };
+        convolve(&mut data, 4, 1, &params, 1, Transfer::Encoded);
+        // Flat areas map to the bias, the rising edge clamps.
+        assert_eq!(data, row(&[128, 128, 255, 128]));
+    }
+
+    #[test]
+    fn explicit_divisor_is_used() {
+        let mut data = row(&[100]);
+        let params = KernelParams {
+            divisor: Some(4.0),
+            ..kernel(vec![vec![1.0]])
+        };
+        convolve(&mut data, 1, 1, &params, 1, Transfer::Encoded);
+        assert_eq!(data, row(&[25]));
+    }
+
+    #[test]
+    fn invalid_kernels_are_rejected() {
+        assert_eq!(kernel(vec![]).validate(), Err(KernelError::Empty));
+        assert_eq!(kernel(vec![vec![]]).validate(), Err(KernelError::Empty));
+        assert_eq!(
+            kernel(vec![vec![1.0, 1.0, 1.0], vec![1.0]]).validate(),
+            Err(KernelError::Ragged)
+        );
+        assert_eq!(
+            kernel(vec![vec![1.0, 1.0]]).validate(),
+            Err(KernelError::EvenSize)
+        );
+        assert_eq!(
+            kernel(vec![vec![f64::NAN]]).validate(),
+            Err(KernelError::NonFinite)
+        );
+        let zero = KernelParams {
+            divisor: Some(0.0),
+            ..kernel(vec![vec![1.0]])
+        };
+        assert_eq!(zero.validate(), Err(KernelError::ZeroDivisor));
+        assert_eq!(kernel(vec![vec![1.0; 5]; 3]).validate(), Ok(()));
+    }
+
+    #[test]
+    fn reach_is_half_the_larger_side() {
+        assert_eq!(kernel(vec![vec![1.0]]).reach(), 0);
+        assert_eq!(kernel(vec![vec![1.0; 5]; 3]).reach(), 2);
+        assert_eq!(kernel(vec![vec![1.0; 3]; 7]).reach(), 3);
+    }
+}
@@ -0,0 +1,1010 @@
+//! Blur, denoise and sharpen plugin, built on `plugin_sdk`.
+//!
+//! The `blur_plugin` crate exports it as a dynamic library; the
+//! host can also link [`BlurPlugin`] in directly.
+
+use plugin_sdk::{
+    AuxInputs, BYTES_PER_PIXEL, ImageViewMut, Plugin, PluginError,
+};
+use serde::Deserialize;
+
+use crate::bilateral::BilateralParams;
+use crate::kernel::KernelParams;
+use crate::mask::{MaskSpec, Rect};
+use crate::motion::{MotionParams, RadialParams};
+use crate::rank::RankParams;
+use crate::sharpen::SharpenParams;
+
+mod bilateral;
+mod kernel;
+mod mask;
+mod motion;
+mod rank;
+mod sharpen;
+mod srgb;
+
+/// Plugin version, reported by the plugin built into the host
+/// and by the dynamic library.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Error code reported for a malformed `kernel` mode matrix.
+const INVALID_KERNEL: std::ffi::c_int = 6;
+
+/// Blur plugin parameters.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BlurParams {
+    /// Blur radius in pixels.
+    radius: u32,
+    /// Number of blur iterations.
+    iterations: u32,
+    /// Blur in linear light instead of averaging
+    /// sRGB-encoded values directly.
+    linear_light: bool,
+    /// Rectangles to blur; the whole image if empty.
+    regions: Vec<Rect>,
+    /// Auxiliary mask image restricting the blur.
+    mask: Option<MaskSpec>,
+    /// Width in pixels of the soft edge around regions
+    /// and mask boundaries.
+    feather: u32,
+    /// Blur algorithm.
+    mode: BlurMode,
+}
+
+/// Blur algorithm selected by the `mode` parameter, tagged by
+/// its `type` field.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlurMode {
+    /// Inverse-distance weighted average over a square
+    /// neighborhood of `radius`.
+    #[default]
+    Weighted,
+    /// Directional motion blur.
+    Motion(MotionParams),
+    /// Radial (zoom) blur around a center point.
+    Radial(RadialParams),
+    /// Edge-preserving bilateral filter.
+    Bilateral(BilateralParams),
+    /// Unsharp mask sharpening using a weighted blur of
+    /// `radius` as reference.
+    Sharpen(SharpenParams),
+    /// Median filter over the `radius` neighborhood.
+    Median,
+    /// Minimum filter over the `radius` neighborhood.
+    Min,
+    /// Maximum filter over the `radius` neighborhood.
+    Max,
+    /// Percentile filter over the `radius` neighborhood.
+    Rank(RankParams),
+    /// Convolution with a user-supplied kernel matrix.
+    Kernel(KernelParams),
+}
+
+impl BlurMode {
+    /// Checks the mode-specific parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PluginError::Custom(INVALID_KERNEL)` for a malformed kernel
+    /// and `PluginError::InvalidParams` for any other invalid
+    /// value.
+    fn validate(&self) -> Result<(), PluginError> {
+        let valid = match self {
+            Self::Weighted | Self::Median | Self::Min | Self::Max => true,
+            Self::Motion(params) => params.is_valid(),
+            Self::Radial(params) => params.is_valid(),
+            Self::Bilateral(params) => params.is_valid(),
+            Self::Sharpen(params) => params.is_valid(),
+            Self::Rank(params) => params.is_valid(),
+            Self::Kernel(params) => {
+                return params
+                    .validate()
+                    .map_err(|_| PluginError::Custom(INVALID_KERNEL));
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(PluginError::InvalidParams)
+        }
+    }
+
+    /// How far (in pixels) one iteration reads around each
+    /// pixel, or `None` if the result also depends on the
+    /// pixel's absolute position.
+    ///
+    /// Motion and radial blur sample at sub-pixel positions
+    /// computed from absolute coordinates (and, for radial, the
+    /// image size), so a tile would not reproduce them exactly.
+    fn reach(&self, radius: u32) -> Option<u32> {
+        match self {
+            Self::Weighted
+            | Self::Sharpen(_)
+            | Self::Median
+            | Self::Min
+            | Self::Max
+            | Self::Rank(_) => Some(radius),
+            Self::Bilateral(params) => u32::try_from(params.radius()).ok(),
+            Self::Kernel(params) => u32::try_from(params.reach()).ok(),
+            Self::Motion(_) | Self::Radial(_) => None,
+        }
+    }
+}
+
+impl Default for BlurParams {
+    fn default() -> Self {
+        Self {
+            radius: 1,
+            iterations: 1,
+            linear_light: false,
+            regions: Vec::new(),
+            mask: None,
+            feather: 0,
+            mode: BlurMode::default(),
+        }
+    }
+}
+
+/// Transfer function applied to color channels around
+/// averaging. Alpha is always averaged as stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    /// Average the stored sRGB-encoded byte values.
+    Encoded,
+    /// Decode to linear light, average, then re-encode.
+    LinearLight,
+}
+
+impl Transfer {
+    /// Converts a stored channel value into the averaging space.
+    fn decode(self, value: u8) -> f64 {
+        match self {
+            Self::Encoded => f64::from(value),
+            Self::LinearLight => srgb::to_linear(value),
+        }
+    }
+
+    /// Converts an averaged value back into a stored byte.
+    fn encode(self, value: f64) -> u8 {
+        match self {
+            // Averages of byte values stay within [0, 255].
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Self::Encoded => value.round() as u8,
+            Self::LinearLight => srgb::from_linear(value),
+        }
+    }
+}
+
+/// Blur, denoise and sharpen filters.
+#[derive(Default)]
+pub struct BlurPlugin;
+
+impl Plugin for BlurPlugin {
+    type Params = BlurParams;
+
+    fn process(
+        &self,
+        image: ImageViewMut<'_>,
+        params: BlurParams,
+    ) -> Result<(), PluginError> {
+        self.process_with_aux(image, params, &AuxInputs::new())
+    }
+
+    fn process_with_aux(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: BlurParams,
+        aux: &AuxInputs<'_>,
+    ) -> Result<(), PluginError> {
+        params.mode.validate()?;
+
+        let (w, h) = (image.width(), image.height());
+
+        let mask = match &params.mask {
+            Some(spec) => {
+                let mask = aux
+                    .get(&spec.input)
+                    .filter(|m| m.width() == w && m.height() == h)
+                    .ok_or(PluginError::AuxInput)?;
+                Some((spec, mask.data()))
+            }
+            None => None,
+        };
+
+        // Every mode reads neighbouring rows, so strided views
+        // are blurred in a packed copy.
+        image.with_packed(|data| blur_image(data, w, h, &params, mask));
+
+        Ok(())
+    }
+
+    fn tile_halo(
+        &self,
+        params: &BlurParams,
+    ) -> Result<Option<u32>, PluginError> {
+        params.mode.validate()?;
+
+        // Regions use absolute coordinates and feathering sums
+        // coverage along whole rows, so both need the full image.
+        if !params.regions.is_empty()
+            || (params.mask.is_some() && params.feather > 0)
+        {
+            return Ok(None);
+        }
+
+        Ok(params
+            .mode
+            .reach(params.radius)
+            .and_then(|reach| reach.checked_mul(params.iterations)))
+    }
+}
+
+/// Blurs `data` with the configured mode, restricted to the
+/// configured regions and mask.
+fn blur_image(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    params: &BlurParams,
+    mask: Option<(&MaskSpec, &[u8])>,
+) {
+    let transfer = if params.linear_light {
+        Transfer::LinearLight
+    } else {
+        Transfer::Encoded
+    };
+    let radius = usize::try_from(params.radius).unwrap_or(0);
+
+    let coverage = mask::coverage(
+        width,
+        height,
+        &params.regions,
+        mask,
+        usize::try_from(params.feather).unwrap_or(usize::MAX),
+    );
+    let original = coverage.as_ref().map(|_| data.to_vec());
+
+    match &params.mode {
+        BlurMode::Weighted => weighted_blur(
+            data,
+            width,
+            height,
+            radius,
+            params.iterations,
+            transfer,
+        ),
+        BlurMode::Motion(motion) => motion::motion_blur(
+            data,
+            width,
+            height,
+            motion,
+            params.iterations,
+            transfer,
+        ),
+        BlurMode::Radial(radial) => motion::radial_blur(
+            data,
+            width,
+            height,
+            radial,
+            params.iterations,
+            transfer,
+        ),
+        BlurMode::Bilateral(bilateral) => bilateral::bilateral_filter(
+            data,
+            width,
+            height,
+            bilateral,
+            params.iterations,
+            transfer,
+        ),
+        BlurMode::Sharpen(sharpen) => sharpen::unsharp_mask(
+            data,
+            width,
+            height,
+            radius,
+            sharpen,
+            params.iterations,
+            transfer,
+        ),
+        // Rank selection is order-preserving, so the transfer
+        // function does not affect the result.
+        BlurMode::Median => rank::rank_filter(
+            data,
+            width,
+            height,
+            radius,
+            RankParams::MEDIAN,
+            params.iterations,
+        ),
+        BlurMode::Min => rank::rank_filter(
+            data,
+            width,
+            height,
+            radius,
+            RankParams::MIN,
+            params.iterations,
+        ),
+        BlurMode::Max => rank::rank_filter(
+            data,
+            width,
+            height,
+            radius,
+            RankParams::MAX,
+            params.iterations,
+        ),
+        BlurMode::Rank(rank) => rank::rank_filter(
+            data,
+            width,
+            height,
+            radius,
+            *rank,
+            params.iterations,
+        ),
+        BlurMode::Kernel(kernel) => kernel::convolve(
+            data,
+            width,
+            height,
+            kernel,
+            params.iterations,
+            transfer,
+        ),
+    }
+
+    if let (Some(coverage), Some(original)) = (coverage, original) {
+        mask::blend(data, &original, &coverage, transfer);
+    }
+}
+
+/// Applies weighted blur to an RGBA buffer.
+///
+/// For each pixel, computes a weighted average of all pixels
+/// within a square of side `2 * radius + 1`.
+/// Weight = `1.0 / max(1.0, distance)`, so the center pixel
+/// has weight 1.0.
+///
+/// Color channels are averaged in the space selected by
+/// `transfer`.
+///
+/// Uses a temporary buffer to avoid reading already-modified
+/// data.
+fn weighted_blur(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    radius: usize,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    let mut temp = vec![0u8; data.len()];
+
+    for _ in 0..iterations {
+        for y in 0..height {
+            for x in 0..width {
+                let (sr, sg, sb, sa, tw) = accumulate_neighborhood(
+                    data, width, height, x, y, radius, transfer,
+                );
+
+                let dst = (y * width + x) * BYTES_PER_PIXEL;
+
+                // Values are guaranteed non-negative (sums of non-negative products),
+                // and division by total_weight keeps them within the channel range.
+                temp[dst] = transfer.encode(sr / tw);
+                temp[dst + 1] = transfer.encode(sg / tw);
+                temp[dst + 2] = transfer.encode(sb / tw);
+                temp[dst + 3] = Transfer::Encoded.encode(sa / tw);
+            }
+        }
+        data.copy_from_slice(&temp);
+    }
+}
+
+/// Accumulates weighted channel values of all neighboring
+/// pixels within radius `r` of pixel `(center_x, center_y)`.
+/// Color channels are decoded with `transfer` first.
+///
+/// Returns `(sum_r, sum_g, sum_b, sum_a, total_weight)`.
+fn accumulate_neighborhood(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    center_x: usize,
+    center_y: usize,
+    radius: usize,
+    transfer: Transfer,
+) -> (f64, f64, f64, f64, f64) {
+    let mut sum_r = 0.0_f64;
+    let mut sum_g = 0.0_f64;
+    let mut sum_b = 0.0_f64;
+    let mut sum_a = 0.0_f64;
+    let mut total_weight = 0.0_f64;
+
+    let y_start = center_y.saturating_sub(radius);
+    let y_end = (center_y + radius + 1).min(height);
+    let x_start = center_x.saturating_sub(radius);
+    let x_end = (center_x + radius + 1).min(width);
+
+    for ny in y_start..y_end {
+        for nx in x_start..x_end {
+            let dx = center_x.abs_diff(nx);
+            let dy = center_y.abs_diff(ny);
+
+            #[allow(clippy::cast_precision_loss)]
+            let distance = ((dx * dx + dy * dy) as f64).sqrt();
+            let weight = 1.0 / distance.max(1.0);
+
+            let src = (ny * width + nx) * BYTES_PER_PIXEL;
+
+            sum_r = transfer.decode(data[src]).mul_add(weight, sum_r);
+            sum_g = transfer.decode(data[src + 1]).mul_add(weight, sum_g);
+            sum_b = transfer.decode(data[src + 2]).mul_add(weight, sum_b);
+            sum_a = f64::from(data[src + 3]).mul_add(weight, sum_a);
+            total_weight += weight;
+        }
+    }
+
+    (sum_r, sum_g, sum_b, sum_a, total_weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_radius_zero_is_identity() {
+        let mut data = vec![
+            255, 0, 0, 255, // red
+            0, 255, 0, 255, // green
+            0, 0, 255, 255, // blue
+            255, 255, 0, 255, // yellow
+        ];
+        let original = data.clone();
+        weighted_blur(&mut data, 2, 2, 0, 1, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn blur_single_pixel() {
+        let mut data = vec![100, 150, 200, 255];
+        let original = data.clone();
+        weighted_blur(&mut data, 1, 1, 5, 3, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn blur_uniform_image_unchanged() {
+        let pixel = [128u8, 128, 128, 255];
+        let mut data: Vec<u8> =
+            pixel.iter().copied().cycle().take(9 * 4).collect();
+        let original = data.clone();
+        weighted_blur(&mut data, 3, 3, 1, 1, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn blur_reduces_contrast() {
+        // 3x3 image: center is white, rest are black
+        let mut data = vec![0u8; 3 * 3 * BYTES_PER_PIXEL];
+        for i in 0..9 {
+            data[i * BYTES_PER_PIXEL + 3] = 255;
+        }
+        // Center pixel (1,1) = white
+        let center = 4 * BYTES_PER_PIXEL;
+        data[center] = 255;
+        data[center + 1] = 255;
+        data[center + 2] = 255;
+
+        weighted_blur(&mut data, 3, 3, 1, 1, Transfer::Encoded);
+
+        // Center pixel should darken (< 255)
+        let center_r = data[center];
+        assert!(
+            center_r < 255,
+            "Center pixel should darken after blur, \
+             but R={center_r}"
+        );
+
+        // Neighbor pixel (0,1) should brighten (> 0)
+        let neighbor = BYTES_PER_PIXEL;
+        assert!(data[neighbor] > 0, "Neighbor pixel should brighten");
+    }
+
+    #[test]
+    fn blur_multiple_iterations() {
+        let make_data = || {
+            let mut d = vec![0u8; 5 * 5 * BYTES_PER_PIXEL];
+            for i in 0..25 {
+                d[i * BYTES_PER_PIXEL + 3] = 255;
+            }
+            // Center pixel (2,2) = white
+            let c = 12 * BYTES_PER_PIXEL;
+            d[c] = 255;
+            d[c + 1] = 255;
+            d[c + 2] = 255;
+            d
+        };
+
+        let mut data1 = make_data();
+        weighted_blur(&mut data1, 5, 5, 1, 1, Transfer::Encoded);
+        let center1 = data1[12 * BYTES_PER_PIXEL];
+
+        let mut data2 = make_data();
+        weighted_blur(&mut data2, 5, 5, 1, 3, Transfer::Encoded);
+        let center2 = data2[12 * BYTES_PER_PIXEL];
+
+        assert!(
+            center2 < center1,
+            "More iterations should produce stronger blur: \
+             1 iter R={center1}, 3 iter R={center2}"
+        );
+    }
+
+    /// Runs the plugin through the C ABI glue, like the exported
+    /// `process_image_with_aux` of `blur_plugin`.
+    unsafe fn process_image_with_aux(
+        width: u32,
+        height: u32,
+        rgba_data: *mut u8,
+        params: *const std::ffi::c_char,
+        aux: *const plugin_sdk::ffi::AuxInput,
+        aux_count: usize,
+    ) -> std::ffi::c_int {
+        // SAFETY: forwarded caller guarantees.
+        unsafe {
+            plugin_sdk::ffi::process_image(
+                BlurPlugin::default,
+                width,
+                height,
+                rgba_data,
+                params,
+                aux,
+                aux_count,
+            )
+        }
+    }
+
+    /// Runs the plugin through the C ABI glue, like the exported
+    /// `process_image` of `blur_plugin`.
+    unsafe fn process_image(
+        width: u32,
+        height: u32,
+        rgba_data: *mut u8,
+        params: *const std::ffi::c_char,
+    ) -> std::ffi::c_int {
+        // SAFETY: forwarded caller guarantees; no auxiliary inputs.
+        unsafe {
+            process_image_with_aux(
+                width,
+                height,
+                rgba_data,
+                params,
+                std::ptr::null(),
+                0,
+            )
+        }
+    }
+
+    /// Creates an opaque black/white checkerboard.
+    fn make_checkerboard(width: usize, height: usize) -> Vec<u8> {
+        let mut data = vec![255u8; width * height * BYTES_PER_PIXEL];
+        for y in 0..height {
+            for x in 0..width {
+                if (x + y) % 2 == 1 {
+                    let i = (y * width + x) * BYTES_PER_PIXEL;
+                    data[i..i + 3].fill(0);
+                }
+            }
+        }
+        data
+    }
+
+    /// Asserts that every interior pixel's color channels
+    /// are within `tolerance` of `expected`.
+    fn assert_interior_near(
+        data: &[u8],
+        width: usize,
+        height: usize,
+        expected: u8,
+        tolerance: u8,
+    ) {
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let i = (y * width + x) * BYTES_PER_PIXEL;
+                for &value in &data[i..i + 3] {
+                    assert!(
+                        value.abs_diff(expected) <= tolerance,
+                        "pixel ({x},{y}) = {value}, expected ≈{expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checkerboard_encoded_blur_is_dark_gray() {
+        let mut data = make_checkerboard(8, 8);
+        weighted_blur(&mut data, 8, 8, 1, 1, Transfer::Encoded);
+        assert_interior_near(&data, 8, 8, 128, 4);
+    }
+
+    #[test]
+    fn checkerboard_linear_light_blur_is_perceptual_mid_gray() {
+        let mut data = make_checkerboard(8, 8);
+        weighted_blur(&mut data, 8, 8, 1, 1, Transfer::LinearLight);
+        assert_interior_near(&data, 8, 8, 188, 4);
+    }
+
+    #[test]
+    fn linear_light_keeps_alpha_linear() {
+        let mut data = vec![
+            0, 0, 0, 0, //
+            255, 255, 255, 255, //
+        ];
+        weighted_blur(&mut data, 2, 1, 1, 1, Transfer::LinearLight);
+        assert_eq!(data[3], 128);
+        assert_eq!(data[7], 128);
+    }
+
+    #[test]
+    fn region_blur_leaves_outside_untouched() {
+        let mut data = make_checkerboard(8, 8);
+        let original = data.clone();
+        let params = BlurParams {
+            regions: vec![Rect {
+                x: 0,
+                y: 0,
+                width: 4,
+                height: 8,
+            }],
+            ..BlurParams::default()
+        };
+        blur_image(&mut data, 8, 8, &params, None);
+
+        let row_bytes = 8 * BYTES_PER_PIXEL;
+        for y in 0..8 {
+            let row = y * row_bytes;
+            assert_ne!(data[row..row + 16], original[row..row + 16]);
+            assert_eq!(
+                data[row + 16..row + row_bytes],
+                original[row + 16..row + row_bytes]
+            );
+        }
+    }
+
+    #[test]
+    fn mask_from_aux_input_limits_blur() {
+        let mut data = make_checkerboard(4, 1);
+        let original = data.clone();
+        // Only the last pixel is masked in.
+        let mut mask = [0u8; 4 * BYTES_PER_PIXEL];
+        mask[12..16].fill(255);
+        let name = c"mask";
+        let aux = [plugin_sdk::ffi::AuxInput {
+            name: name.as_ptr(),
+            width: 4,
+            height: 1,
+            rgba_data: mask.as_ptr(),
+        }];
+        let params = cr#"{"mask": {}}"#;
+
+        // SAFETY: all pointers reference live buffers of the
+        // declared sizes.
+        let code = unsafe {
+            process_image_with_aux(
+                4,
+                1,
+                data.as_mut_ptr(),
+                params.as_ptr(),
+                aux.as_ptr(),
+                aux.len(),
+            )
+        };
+
+        assert_eq!(code, 0);
+        assert_eq!(data[..12], original[..12]);
+        assert_ne!(data[12..], original[12..]);
+    }
+
+    #[test]
+    fn missing_mask_input_is_reported() {
+        let mut data = make_checkerboard(2, 2);
+        let params = cr#"{"mask": {"input": "absent"}}"#;
+
+        // SAFETY: data holds 2x2 RGBA pixels and params is a
+        // valid C string.
+        let code =
+            unsafe { process_image(2, 2, data.as_mut_ptr(), params.as_ptr()) };
+
+        assert_eq!(code, 5);
+    }
+
+    #[test]
+    fn mode_params_are_parsed() {
+        let params: BlurParams = serde_json::from_str(
+            r#"{"mode": {"type": "motion", "angle": 45, "length": 3}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            params.mode,
+            BlurMode::Motion(MotionParams {
+                angle: 45.0,
+                length: 3.0,
+            })
+        );
+
+        let params: BlurParams =
+            serde_json::from_str(r#"{"mode": {"type": "radial"}}"#).unwrap();
+        assert_eq!(params.mode, BlurMode::Radial(RadialParams::default()));
+
+        let params: BlurParams = serde_json::from_str(
+            r#"{"mode": {"type": "bilateral", "sigma_range": 10,
+                         "method": "separable"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            params.mode,
+            BlurMode::Bilateral(BilateralParams {
+                sigma_range: 10.0,
+                method: bilateral::BilateralMethod::Separable,
+                ..BilateralParams::default()
+            })
+        );
+
+        let params: BlurParams =
+            serde_json::from_str(r#"{"mode": {"type": "median"}}"#).unwrap();
+        assert_eq!(params.mode, BlurMode::Median);
+
+        let params: BlurParams = serde_json::from_str(
+            r#"{"mode": {"type": "rank", "percentile": 25}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            params.mode,
+            BlurMode::Rank(RankParams { percentile: 25.0 })
+        );
+
+        let params: BlurParams = serde_json::from_str("{}").unwrap();
+        assert_eq!(params.mode, BlurMode::Weighted);
+    }
+
+    #[test]
+    fn invalid_mode_params_are_rejected() {
+        let mut data = make_checkerboard(2, 2);
+        let params = cr#"{"mode": {"type": "motion", "length": -2}}"#;
+
+        // SAFETY: data holds 2x2 RGBA pixels and params is a
+        // valid C string.
+        let code =
+            unsafe { process_image(2, 2, data.as_mut_ptr(), params.as_ptr()) };
+
+        assert_eq!(code, 4);
+    }
+
+    #[test]
+    fn malformed_kernel_has_dedicated_code() {
+        let mut data = make_checkerboard(2, 2);
+        let params = cr#"{"mode": {"type": "kernel", "matrix": [[1, 1]]}}"#;
+
+        // SAFETY: data holds 2x2 RGBA pixels and params is a
+        // valid C string.
+        let code =
+            unsafe { process_image(2, 2, data.as_mut_ptr(), params.as_ptr()) };
+
+        assert_eq!(code, 6);
+    }
+
+    #[test]
+    fn kernel_mode_runs_through_entry_point() {
+        let mut data = make_checkerboard(3, 3);
+        let original = data.clone();
+        let params = cr#"{"mode": {"type": "kernel",
+                           "matrix": [[0, 0, 0], [0, 1, 0], [0, 0, 0]]}}"#;
+
+        // SAFETY: data holds 3x3 RGBA pixels and params is a
+        // valid C string.
+        let code =
+            unsafe { process_image(3, 3, data.as_mut_ptr(), params.as_ptr()) };
+
+        assert_eq!(code, 0);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn tile_halo_is_reach_times_iterations() {
+        let halo = |json: &str| {
+            let params: BlurParams = serde_json::from_str(json).unwrap();
+            BlurPlugin.tile_halo(&params)
+        };
+
+        assert_eq!(halo(r#"{"radius": 3, "iterations": 2}"#), Ok(Some(6)));
+        assert_eq!(
+            halo(r#"{"mode": {"type": "bilateral", "sigma_spatial": 1.5}}"#),
+            Ok(Some(3))
+        );
+        assert_eq!(
+            halo(
+                r#"{"iterations": 3, "mode": {"type": "kernel",
+                    "matrix": [[1, 1, 1, 1, 1]]}}"#
+            ),
+            Ok(Some(6))
+        );
+        assert_eq!(
+            halo(r#"{"mask": {"input": "mask"}, "radius": 2}"#),
+            Ok(Some(2))
+        );
+    }
+
+    #[test]
+    fn position_dependent_blurs_are_not_tile_safe() {
+        for json in [
+            r#"{"mode": {"type": "motion"}}"#,
+            r#"{"mode": {"type": "radial"}}"#,
+            r#"{"regions": [{"x": 0, "y": 0, "width": 1, "height": 1}]}"#,
+            r#"{"mask": {"input": "mask"}, "feather": 2}"#,
+        ] {
+            let params: BlurParams = serde_json::from_str(json).unwrap();
+            assert_eq!(BlurPlugin.tile_halo(&params), Ok(None), "{json}");
+        }
+
+        let params: BlurParams = serde_json::from_str(
+            r#"{"mode": {"type": "kernel", "matrix": [[1, 1]]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            BlurPlugin.tile_halo(&params),
+            Err(PluginError::Custom(INVALID_KERNEL))
+        );
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Copies the `w x h` rectangle at `(x, y)` out of an
+        /// image `width` pixels wide.
+        fn crop(
+            data: &[u8],
+            width: usize,
+            (x, y, w, h): (usize, usize, usize, usize),
+        ) -> Vec<u8> {
+            (y..y + h)
+                .flat_map(|row| {
+                    let start = (row * width + x) * BYTES_PER_PIXEL;
+                    &data[start..start + w * BYTES_PER_PIXEL]
+                })
+                .copied()
+                .collect()
+        }
+
+        /// Tile-safe modes with small parameters.
+        fn tile_safe_mode() -> impl Strategy<Value = BlurMode> {
+            prop_oneof![
+                Just(BlurMode::Weighted),
+                Just(BlurMode::Median),
+                Just(BlurMode::Max),
+                (0.5..2.0f64).prop_map(|sigma_spatial| {
+                    BlurMode::Bilateral(BilateralParams {
+                        sigma_spatial,
+                        ..BilateralParams::default()
+                    })
+                }),
+                Just(BlurMode::Sharpen(SharpenParams::default())),
+                Just(BlurMode::Kernel(KernelParams {
+                    matrix: vec![vec![1.0, 2.0, 1.0]; 3],
+                    divisor: None,
+                    bias: 0.0,
+                })),
+            ]
+        }
+
+        /// Generates a random RGBA image with dimensions
+        /// in range [1, 32] and random pixel data.
+        fn arbitrary_image() -> impl Strategy<Value = (usize, usize, Vec<u8>)> {
+            (1..=32usize, 1..=32usize).prop_flat_map(|(w, h)| {
+                let len = w * h * BYTES_PER_PIXEL;
+                (Just(w), Just(h), proptest::collection::vec(any::<u8>(), len))
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn radius_zero_is_identity(
+                (w, h, mut data) in arbitrary_image()
+            ) {
+                let original = data.clone();
+                weighted_blur(&mut data, w, h, 0, 1, Transfer::Encoded);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn uniform_image_unchanged(
+                w in 1..=16usize,
+                h in 1..=16usize,
+                pixel in any::<[u8; 4]>(),
+                radius in 1..=5usize,
+            ) {
+                let mut data: Vec<u8> = pixel
+                    .iter()
+                    .copied()
+                    .cycle()
+                    .take(w * h * BYTES_PER_PIXEL)
+                    .collect();
+                let original = data.clone();
+                weighted_blur(&mut data, w, h, radius, 1, Transfer::Encoded);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn linear_light_radius_zero_is_identity(
+                (w, h, mut data) in arbitrary_image()
+            ) {
+                let original = data.clone();
+                weighted_blur(&mut data, w, h, 0, 1, Transfer::LinearLight);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn linear_light_uniform_image_unchanged(
+                w in 1..=16usize,
+                h in 1..=16usize,
+                pixel in any::<[u8; 4]>(),
+                radius in 1..=5usize,
+            ) {
+                let mut data: Vec<u8> = pixel
+                    .iter()
+                    .copied()
+                    .cycle()
+                    .take(w * h * BYTES_PER_PIXEL)
+                    .collect();
+                let original = data.clone();
+                weighted_blur(&mut data, w, h, radius, 1, Transfer::LinearLight);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn tile_with_halo_matches_whole_image(
+                (w, h, data) in arbitrary_image(),
+                mode in tile_safe_mode(),
+                radius in 0..=2u32,
+                iterations in 1..=2u32,
+                linear_light in any::<bool>(),
+                tile in (0.0..1.0f64, 0.0..1.0f64, 0.0..1.0f64, 0.0..1.0f64),
+            ) {
+                let params = BlurParams {
+                    radius,
+                    iterations,
+                    linear_light,
+                    mode,
+                    ..BlurParams::default()
+                };
+                let halo = BlurPlugin.tile_halo(&params).unwrap().unwrap();
+                let halo = usize::try_from(halo).unwrap();
+
+                // Pick a tile inside the image from the fractions.
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss
+                )]
+                let pick = |f: f64, len: usize| (f * len as f64) as usize;
+                let (x, y) = (pick(tile.0, w), pick(tile.1, h));
+                let (tw, th) = (pick(tile.2, w - x) + 1, pick(tile.3, h - y) + 1);
+
+                let mut whole = data.clone();
+                blur_image(&mut whole, w, h, &params, None);
+
+                let (ox, oy) = (x.saturating_sub(halo), y.saturating_sub(halo));
+                let ow = (x + tw + halo).min(w) - ox;
+                let oh = (y + th + halo).min(h) - oy;
+                let mut outer = crop(&data, w, (ox, oy, ow, oh));
+                blur_image(&mut outer, ow, oh, &params, None);
+
+                prop_assert_eq!(
+                    crop(&outer, ow, (x - ox, y - oy, tw, th)),
+                    crop(&whole, w, (x, y, tw, th))
+                );
+            }
+        }
+    }
+}
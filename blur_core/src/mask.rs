@@ -0,0 +1,305 @@
+use serde::Deserialize;
+
+use crate::{BYTES_PER_PIXEL, Transfer};
+
+/// Axis-aligned rectangle in pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Mask image channel that provides blur coverage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskChannel {
+    /// Rec. 709 luma of the color channels.
+    #[default]
+    Luma,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+/// Reference to an auxiliary mask image supplied by the host.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MaskSpec {
+    /// Name of the auxiliary input holding the mask.
+    pub input: String,
+    /// Channel read from the mask image.
+    pub channel: MaskChannel,
+    /// Blur where the mask is zero instead of non-zero.
+    pub invert: bool,
+}
+
+impl Default for MaskSpec {
+    fn default() -> Self {
+        Self {
+            input: "mask".to_owned(),
+            channel: MaskChannel::default(),
+            invert: false,
+        }
+    }
+}
+
+/// Builds per-pixel blur coverage in `[0.0, 1.0]`.
+///
+/// Coverage is the union of `regions` intersected with the
+/// mask (if any), softened over `feather` pixels. Returns
+/// `None` when neither regions nor a mask restrict the blur.
+///
+/// `mask` must be an RGBA buffer of `width * height` pixels.
+pub fn coverage(
+    width: usize,
+    height: usize,
+    regions: &[Rect],
+    mask: Option<(&MaskSpec, &[u8])>,
+    feather: usize,
+) -> Option<Vec<f32>> {
+    if regions.is_empty() && mask.is_none() {
+        return None;
+    }
+
+    let mut cov = if regions.is_empty() {
+        vec![1.0_f32; width * height]
+    } else {
+        region_coverage(width, height, regions)
+    };
+
+    if let Some((spec, data)) = mask {
+        let (pixels, _) = data.as_chunks::<BYTES_PER_PIXEL>();
+        for (c, pixel) in cov.iter_mut().zip(pixels) {
+            let value = mask_value(spec.channel, *pixel) / 255.0;
+            *c *= if spec.invert { 1.0 - value } else { value };
+        }
+    }
+
+    if feather > 0 {
+        // Two box passes approximate a tent filter, which gives
+        // a smoother ramp than a single box.
+        for _ in 0..2 {
+            box_blur_rows(&mut cov, width, height, feather);
+            box_blur_columns(&mut cov, width, height, feather);
+        }
+    }
+
+    Some(cov)
+}
+
+/// Blends `blurred` (in place) with `original` according to
+/// per-pixel `coverage`. Color channels are mixed in the
+/// space selected by `transfer`.
+pub fn blend(
+    blurred: &mut [u8],
+    original: &[u8],
+    coverage: &[f32],
+    transfer: Transfer,
+) {
+    let (original, _) = original.as_chunks::<BYTES_PER_PIXEL>();
+    let pixels = blurred
+        .as_chunks_mut::<BYTES_PER_PIXEL>()
+        .0
+        .iter_mut()
+        .zip(original);
+
+    for ((dst, src), &c) in pixels.zip(coverage) {
+        if c <= 0.0 {
+            dst.copy_from_slice(src);
+        } else if c < 1.0 {
+            let c = f64::from(c);
+            for (i, (d, &s)) in dst.iter_mut().zip(src).enumerate() {
+                let t = if i < 3 { transfer } else { Transfer::Encoded };
+                let (from, to) = (t.decode(s), t.decode(*d));
+                *d = t.encode((to - from).mul_add(c, from));
+            }
+        }
+    }
+}
+
+/// Rasterizes the union of `regions`, clipped to the image.
+fn region_coverage(width: usize, height: usize, regions: &[Rect]) -> Vec<f32> {
+    let mut cov = vec![0.0_f32; width * height];
+
+    for rect in regions {
+        let x0 = usize::try_from(rect.x).unwrap_or(usize::MAX).min(width);
+        let y0 = usize::try_from(rect.y).unwrap_or(usize::MAX).min(height);
+        let x1 = x0
+            .saturating_add(usize::try_from(rect.width).unwrap_or(usize::MAX))
+            .min(width);
+        let y1 = y0
+            .saturating_add(usize::try_from(rect.height).unwrap_or(usize::MAX))
+            .min(height);
+
+        for y in y0..y1 {
+            cov[y * width + x0..y * width + x1].fill(1.0);
+        }
+    }
+
+    cov
+}
+
+/// Reads the selected mask channel of one RGBA pixel.
+fn mask_value(channel: MaskChannel, pixel: [u8; BYTES_PER_PIXEL]) -> f32 {
+    let [r, g, b, a] = pixel.map(f32::from);
+    match channel {
+        MaskChannel::Luma => {
+            0.0722f32.mul_add(b, 0.2126f32.mul_add(r, 0.7152 * g))
+        }
+        MaskChannel::Red => r,
+        MaskChannel::Green => g,
+        MaskChannel::Blue => b,
+        MaskChannel::Alpha => a,
+    }
+}
+
+/// Box-filters each row of a single-channel map in place,
+/// clamping the window at the image borders.
+fn box_blur_rows(map: &mut [f32], width: usize, height: usize, radius: usize) {
+    let mut line = vec![0.0_f32; width];
+    for y in 0..height {
+        line.copy_from_slice(&map[y * width..(y + 1) * width]);
+        box_blur_line(&line, &mut map[y * width..(y + 1) * width], radius);
+    }
+}
+
+/// Box-filters each column of a single-channel map in place.
+fn box_blur_columns(
+    map: &mut [f32],
+    width: usize,
+    height: usize,
+    radius: usize,
+) {
+    let mut line = vec![0.0_f32; height];
+    let mut out = vec![0.0_f32; height];
+    for x in 0..width {
+        for (y, v) in line.iter_mut().enumerate() {
+            *v = map[y * width + x];
+        }
+        box_blur_line(&line, &mut out, radius);
+        for (y, &v) in out.iter().enumerate() {
+            map[y * width + x] = v;
+        }
+    }
+}
+
+/// Averages `src` over a window of `2 * radius + 1` samples
+/// using prefix sums; the window is clipped at both ends.
+fn box_blur_line(src: &[f32], dst: &mut [f32], radius: usize) {
+    let mut prefix = Vec::with_capacity(src.len() + 1);
+    prefix.push(0.0_f64);
+    for &v in src {
+        prefix.push(prefix[prefix.len() - 1] + f64::from(v));
+    }
+
+    for (i, d) in dst.iter_mut().enumerate() {
+        let lo = i.saturating_sub(radius);
+        let hi = (i + radius + 1).min(src.len());
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        {
+            *d = ((prefix[hi] - prefix[lo]) / (hi - lo) as f64) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_restrictions_means_full_coverage() {
+        assert!(coverage(4, 4, &[], None, 3).is_none());
+    }
+
+    #[test]
+    fn regions_are_clipped_and_merged() {
+        let regions = [
+            Rect {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+            },
+            Rect {
+                x: 2,
+                y: 1,
+                width: 10,
+                height: 10,
+            },
+        ];
+        let cov = coverage(3, 2, &regions, None, 0).unwrap();
+        assert_eq!(cov, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn mask_channel_and_invert() {
+        let mask = [
+            255, 0, 0, 0, //
+            0, 0, 0, 255, //
+        ];
+        let spec = MaskSpec {
+            channel: MaskChannel::Alpha,
+            ..MaskSpec::default()
+        };
+        assert_eq!(
+            coverage(2, 1, &[], Some((&spec, &mask)), 0).unwrap(),
+            vec![0.0, 1.0]
+        );
+
+        let spec = MaskSpec {
+            channel: MaskChannel::Red,
+            invert: true,
+            ..MaskSpec::default()
+        };
+        assert_eq!(
+            coverage(2, 1, &[], Some((&spec, &mask)), 0).unwrap(),
+            vec![0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn mask_is_intersected_with_regions() {
+        let mask = [255u8; 2 * BYTES_PER_PIXEL];
+        let regions = [Rect {
+            x: 1,
+            y: 0,
+            width: 1,
+            height: 1,
+        }];
+        let cov =
+            coverage(2, 1, &regions, Some((&MaskSpec::default(), &mask)), 0)
+                .unwrap();
+        assert_eq!(cov, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn feather_softens_edges() {
+        let regions = [Rect {
+            x: 0,
+            y: 0,
+            width: 5,
+            height: 10,
+        }];
+        let cov = coverage(10, 10, &regions, None, 2).unwrap();
+        let row = &cov[5 * 10..6 * 10];
+        assert!(row[0] > 0.99);
+        assert!(row[4] > 0.0 && row[4] < 1.0);
+        assert!(row[5] > 0.0 && row[5] < 1.0);
+        assert!(row[9] < 0.01);
+        assert!(row.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn blend_mixes_by_coverage() {
+        let original = [0u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut blurred = [200u8; 12];
+        blend(&mut blurred, &original, &[0.0, 0.5, 1.0], Transfer::Encoded);
+        assert_eq!(
+            blurred,
+            [0, 0, 0, 0, 100, 100, 100, 100, 200, 200, 200, 200]
+        );
+    }
+}
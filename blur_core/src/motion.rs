@@ -0,0 +1,348 @@
+use serde::Deserialize;
+
+use crate::{BYTES_PER_PIXEL, Transfer};
+
+/// Upper bound on the number of samples taken along one path.
+const MAX_PATH_SAMPLES: f64 = 4096.0;
+
+/// Directional motion blur parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MotionParams {
+    /// Direction of motion in degrees, counter-clockwise
+    /// from the positive x axis.
+    pub angle: f64,
+    /// Length of the blur path in pixels.
+    pub length: f64,
+}
+
+impl Default for MotionParams {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            length: 10.0,
+        }
+    }
+}
+
+impl MotionParams {
+    /// Returns `true` if the parameters describe a valid path.
+    pub fn is_valid(&self) -> bool {
+        self.angle.is_finite() && self.length.is_finite() && self.length >= 0.0
+    }
+}
+
+/// Radial (zoom) blur parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RadialParams {
+    /// Zoom center as a fraction of the image width.
+    pub center_x: f64,
+    /// Zoom center as a fraction of the image height.
+    pub center_y: f64,
+    /// Fraction of the distance to the center covered by the
+    /// blur path, in `[0.0, 1.0]`.
+    pub amount: f64,
+}
+
+impl Default for RadialParams {
+    fn default() -> Self {
+        Self {
+            center_x: 0.5,
+            center_y: 0.5,
+            amount: 0.1,
+        }
+    }
+}
+
+impl RadialParams {
+    /// Returns `true` if the parameters describe a valid zoom.
+    pub fn is_valid(&self) -> bool {
+        self.center_x.is_finite()
+            && self.center_y.is_finite()
+            && (0.0..=1.0).contains(&self.amount)
+    }
+}
+
+/// Applies directional motion blur: each pixel becomes the
+/// average of samples along a segment of `length` pixels
+/// centered on it.
+pub fn motion_blur(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    params: &MotionParams,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    let (sin, cos) = params.angle.to_radians().sin_cos();
+    // Image rows grow downwards, so positive angles point up.
+    let (dx, dy) = (cos * params.length / 2.0, -sin * params.length / 2.0);
+
+    path_blur(data, width, height, iterations, transfer, |x, y| {
+        ((x - dx, y - dy), (x + dx, y + dy))
+    });
+}
+
+/// Applies radial (zoom) blur: each pixel becomes the average
+/// of samples along the line towards the zoom center.
+pub fn radial_blur(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    params: &RadialParams,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    #[allow(clippy::cast_precision_loss)]
+    let (cx, cy) = (
+        params.center_x * (width - 1) as f64,
+        params.center_y * (height - 1) as f64,
+    );
+    let amount = params.amount;
+
+    path_blur(data, width, height, iterations, transfer, |x, y| {
+        ((x, y), ((cx - x).mul_add(amount, x), (cy - y).mul_add(amount, y)))
+    });
+}
+
+/// Replaces each pixel by the average of bilinear samples
+/// along the segment returned by `segment(x, y)`.
+///
+/// One sample is taken per pixel of segment length (plus one),
+/// so a zero-length segment reproduces the pixel exactly.
+fn path_blur(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    iterations: u32,
+    transfer: Transfer,
+    segment: impl Fn(f64, f64) -> ((f64, f64), (f64, f64)),
+) {
+    let mut temp = vec![0u8; data.len()];
+
+    for _ in 0..iterations {
+        for y in 0..height {
+            for x in 0..width {
+                #[allow(clippy::cast_precision_loss)]
+                let ((x0, y0), (x1, y1)) = segment(x as f64, y as f64);
+                let length = (x1 - x0).hypot(y1 - y0);
+
+                // Length is finite and non-negative (validated params);
+                // capping keeps huge motion lengths from overflowing.
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss
+                )]
+                let samples = length.ceil().min(MAX_PATH_SAMPLES) as usize + 1;
+
+                let mut sum = [0.0_f64; BYTES_PER_PIXEL];
+                for i in 0..samples {
+                    #[allow(clippy::cast_precision_loss)]
+                    let t = if samples > 1 {
+                        i as f64 / (samples - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    let sample = sample_bilinear(
+                        data,
+                        width,
+                        height,
+                        (x1 - x0).mul_add(t, x0),
+                        (y1 - y0).mul_add(t, y0),
+                        transfer,
+                    );
+                    for (s, v) in sum.iter_mut().zip(sample) {
+                        *s += v;
+                    }
+                }
+
+                let dst = (y * width + x) * BYTES_PER_PIXEL;
+                #[allow(clippy::cast_precision_loss)]
+                let n = samples as f64;
+                temp[dst] = transfer.encode(sum[0] / n);
+                temp[dst + 1] = transfer.encode(sum[1] / n);
+                temp[dst + 2] = transfer.encode(sum[2] / n);
+                temp[dst + 3] = Transfer::Encoded.encode(sum[3] / n);
+            }
+        }
+        data.copy_from_slice(&temp);
+    }
+}
+
+/// Samples the image at a sub-pixel position using bilinear
+/// interpolation. Coordinates outside the image are clamped
+/// to the nearest edge.
+///
+/// Returns channel values decoded with `transfer` (alpha as
+/// stored).
+fn sample_bilinear(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    x: f64,
+    y: f64,
+    transfer: Transfer,
+) -> [f64; BYTES_PER_PIXEL] {
+    #[allow(clippy::cast_precision_loss)]
+    let (x, y) =
+        (x.clamp(0.0, (width - 1) as f64), y.clamp(0.0, (height - 1) as f64));
+    let (fx, fy) = (x.fract(), y.fract());
+
+    // Clamped to [0, width - 1] / [0, height - 1] above.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+
+    let corners = [
+        ((x0, y0), (1.0 - fx) * (1.0 - fy)),
+        ((x1, y0), fx * (1.0 - fy)),
+        ((x0, y1), (1.0 - fx) * fy),
+        ((x1, y1), fx * fy),
+    ];
+
+    let mut out = [0.0_f64; BYTES_PER_PIXEL];
+    for ((cx, cy), weight) in corners {
+        if weight == 0.0 {
+            continue;
+        }
+        let src = (cy * width + cx) * BYTES_PER_PIXEL;
+        for (c, o) in out.iter_mut().enumerate() {
+            let t = if c < 3 { transfer } else { Transfer::Encoded };
+            *o = t.decode(data[src + c]).mul_add(weight, *o);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an opaque image with vertical black/white stripes.
+    fn vertical_stripes(width: usize, height: usize) -> Vec<u8> {
+        let mut data = vec![255u8; width * height * BYTES_PER_PIXEL];
+        for y in 0..height {
+            for x in (0..width).step_by(2) {
+                let i = (y * width + x) * BYTES_PER_PIXEL;
+                data[i..i + 3].fill(0);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn horizontal_motion_mixes_vertical_stripes() {
+        let mut data = vertical_stripes(8, 4);
+        let params = MotionParams {
+            angle: 0.0,
+            length: 4.0,
+        };
+        motion_blur(&mut data, 8, 4, &params, 1, Transfer::Encoded);
+
+        let i = (2 * 8 + 4) * BYTES_PER_PIXEL;
+        assert!(data[i] > 64 && data[i] < 192, "R={}", data[i]);
+    }
+
+    #[test]
+    fn vertical_motion_keeps_vertical_stripes() {
+        let mut data = vertical_stripes(8, 4);
+        let original = data.clone();
+        let params = MotionParams {
+            angle: 90.0,
+            length: 4.0,
+        };
+        motion_blur(&mut data, 8, 4, &params, 1, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn radial_center_pixel_is_unchanged() {
+        let mut data = vertical_stripes(5, 5);
+        let original = data.clone();
+        let params = RadialParams {
+            amount: 0.8,
+            ..RadialParams::default()
+        };
+        radial_blur(&mut data, 5, 5, &params, 1, Transfer::Encoded);
+
+        let center = (2 * 5 + 2) * BYTES_PER_PIXEL;
+        assert_eq!(data[center..center + 4], original[center..center + 4]);
+        assert_ne!(data, original);
+    }
+
+    #[test]
+    fn bilinear_sample_interpolates_between_pixels() {
+        let data = [0, 0, 0, 255, 200, 200, 200, 255];
+        let sample = sample_bilinear(&data, 2, 1, 0.25, 0.0, Transfer::Encoded);
+        assert!((sample[0] - 50.0).abs() < 1e-9);
+        assert!((sample[3] - 255.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        assert!(
+            !MotionParams {
+                angle: 0.0,
+                length: -1.0,
+            }
+            .is_valid()
+        );
+        assert!(
+            !RadialParams {
+                amount: 1.5,
+                ..RadialParams::default()
+            }
+            .is_valid()
+        );
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Generates a random RGBA image with dimensions
+        /// in range [1, 16] and random pixel data.
+        fn arbitrary_image() -> impl Strategy<Value = (usize, usize, Vec<u8>)> {
+            (1..=16usize, 1..=16usize).prop_flat_map(|(w, h)| {
+                let len = w * h * BYTES_PER_PIXEL;
+                (Just(w), Just(h), proptest::collection::vec(any::<u8>(), len))
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn zero_length_motion_is_identity(
+                (w, h, mut data) in arbitrary_image(),
+                angle in -360.0..360.0f64,
+                linear in any::<bool>(),
+            ) {
+                let original = data.clone();
+                let transfer = if linear {
+                    Transfer::LinearLight
+                } else {
+                    Transfer::Encoded
+                };
+                let params = MotionParams { angle, length: 0.0 };
+                motion_blur(&mut data, w, h, &params, 2, transfer);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn zero_amount_radial_is_identity(
+                (w, h, mut data) in arbitrary_image(),
+                center_x in -1.0..2.0f64,
+                center_y in -1.0..2.0f64,
+            ) {
+                let original = data.clone();
+                let params = RadialParams {
+                    center_x,
+                    center_y,
+                    amount: 0.0,
+                };
+                radial_blur(&mut data, w, h, &params, 1, Transfer::Encoded);
+                prop_assert_eq!(data, original);
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use serde::Deserialize;
+
+use crate::BYTES_PER_PIXEL;
+
+/// Rank-order filter parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RankParams {
+    /// Rank to select, as a percentile of the sorted window:
+    /// `0` is the minimum, `50` the median, `100` the maximum.
+    pub percentile: f64,
+}
+
+impl Default for RankParams {
+    fn default() -> Self {
+        Self { percentile: 50.0 }
+    }
+}
+
+impl RankParams {
+    /// Median filter.
+    pub const MEDIAN: Self = Self { percentile: 50.0 };
+    /// Minimum (erosion) filter.
+    pub const MIN: Self = Self { percentile: 0.0 };
+    /// Maximum (dilation) filter.
+    pub const MAX: Self = Self { percentile: 100.0 };
+
+    /// Returns `true` if the percentile is within `[0, 100]`.
+    pub fn is_valid(self) -> bool {
+        (0.0..=100.0).contains(&self.percentile)
+    }
+
+    /// Zero-based index of the selected value in a sorted
+    /// window of `count` samples.
+    fn rank(self, count: usize) -> usize {
+        #[allow(clippy::cast_precision_loss)]
+        let position = self.percentile / 100.0 * (count - 1) as f64;
+        // percentile is within [0, 100], so position is within
+        // [0, count - 1].
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let rank = position.round() as usize;
+        rank
+    }
+}
+
+/// Applies a rank-order filter to every channel of an RGBA
+/// buffer: each value is replaced by the value at the given
+/// percentile of its `(2 * radius + 1)²` neighborhood (clipped
+/// at the image borders).
+///
+/// Uses per-channel histograms that slide along each row, so
+/// the cost per pixel is `O(radius)` for the update plus a
+/// constant 256-bin scan, rather than sorting the window.
+pub fn rank_filter(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    radius: usize,
+    params: RankParams,
+    iterations: u32,
+) {
+    let mut temp = vec![0u8; data.len()];
+
+    for _ in 0..iterations {
+        for y in 0..height {
+            let y_start = y.saturating_sub(radius);
+            let y_end = (y + radius + 1).min(height);
+            let rows = y_end - y_start;
+
+            let mut histograms = [[0u32; 256]; BYTES_PER_PIXEL];
+            let mut columns = 0;
+
+            // Initial window for x = 0 covers columns [0, radius].
+            for nx in 0..(radius + 1).min(width) {
+                update_column(
+                    data,
+                    width,
+                    &mut histograms,
+                    nx,
+                    y_start..y_end,
+                    1,
+                );
+                columns += 1;
+            }
+
+            for x in 0..width {
+                let rank = params.rank(columns * rows);
+                let dst = (y * width + x) * BYTES_PER_PIXEL;
+                for (c, histogram) in histograms.iter().enumerate() {
+                    temp[dst + c] = select(histogram, rank);
+                }
+
+                // Slide the window one column to the right.
+                if x >= radius {
+                    update_column(
+                        data,
+                        width,
+                        &mut histograms,
+                        x - radius,
+                        y_start..y_end,
+                        -1,
+                    );
+                    columns -= 1;
+                }
+                if x + radius + 1 < width {
+                    update_column(
+                        data,
+                        width,
+                        &mut histograms,
+                        x + radius + 1,
+                        y_start..y_end,
+                        1,
+                    );
+                    columns += 1;
+                }
+            }
+        }
+        data.copy_from_slice(&temp);
+    }
+}
+
+/// Adds (`delta = 1`) or removes (`delta = -1`) the pixels of
+/// column `x` within `rows` to/from the channel histograms.
+fn update_column(
+    data: &[u8],
+    width: usize,
+    histograms: &mut [[u32; 256]; BYTES_PER_PIXEL],
+    x: usize,
+    rows: std::ops::Range<usize>,
+    delta: i32,
+) {
+    for ny in rows {
+        let src = (ny * width + x) * BYTES_PER_PIXEL;
+        for (c, histogram) in histograms.iter_mut().enumerate() {
+            let bin = &mut histogram[usize::from(data[src + c])];
+            *bin = bin.saturating_add_signed(delta);
+        }
+    }
+}
+
+/// Returns the value at zero-based `rank` in the sorted
+/// multiset described by `histogram`.
+fn select(histogram: &[u32; 256], rank: usize) -> u8 {
+    let mut seen = 0usize;
+    for (value, &count) in histogram.iter().enumerate() {
+        seen += usize::try_from(count).unwrap_or(usize::MAX);
+        if seen > rank {
+            return u8::try_from(value).unwrap_or(u8::MAX);
+        }
+    }
+    u8::MAX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an opaque uniform gray image.
+    fn gray(width: usize, height: usize, level: u8) -> Vec<u8> {
+        [level, level, level, 255]
+            .iter()
+            .copied()
+            .cycle()
+            .take(width * height * BYTES_PER_PIXEL)
+            .collect()
+    }
+
+    fn set_gray(data: &mut [u8], width: usize, x: usize, y: usize, v: u8) {
+        let i = (y * width + x) * BYTES_PER_PIXEL;
+        data[i..i + 3].fill(v);
+    }
+
+    /// Reference implementation that sorts every window.
+    fn rank_by_sorting(
+        data: &[u8],
+        width: usize,
+        height: usize,
+        radius: usize,
+        params: RankParams,
+    ) -> Vec<u8> {
+        let mut out = vec![0u8; data.len()];
+        for y in 0..height {
+            for x in 0..width {
+                for c in 0..BYTES_PER_PIXEL {
+                    let mut window = Vec::new();
+                    for ny in
+                        y.saturating_sub(radius)..(y + radius + 1).min(height)
+                    {
+                        for nx in x.saturating_sub(radius)
+                            ..(x + radius + 1).min(width)
+                        {
+                            window.push(
+                                data[(ny * width + nx) * BYTES_PER_PIXEL + c],
+                            );
+                        }
+                    }
+                    window.sort_unstable();
+                    out[(y * width + x) * BYTES_PER_PIXEL + c] =
+                        window[params.rank(window.len())];
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn median_removes_salt_and_pepper() {
+        let mut data = gray(7, 7, 120);
+        set_gray(&mut data, 7, 2, 2, 255);
+        set_gray(&mut data, 7, 5, 4, 0);
+        rank_filter(&mut data, 7, 7, 1, RankParams::MEDIAN, 1);
+        assert_eq!(data, gray(7, 7, 120));
+    }
+
+    #[test]
+    fn min_erodes_and_max_dilates() {
+        let mut bright = gray(5, 5, 10);
+        set_gray(&mut bright, 5, 2, 2, 200);
+
+        let mut eroded = bright.clone();
+        rank_filter(&mut eroded, 5, 5, 1, RankParams::MIN, 1);
+        assert_eq!(eroded, gray(5, 5, 10));
+
+        let mut dilated = bright;
+        rank_filter(&mut dilated, 5, 5, 1, RankParams::MAX, 1);
+        for y in 0..5 {
+            for x in 0..5 {
+                let expected = if (1..=3).contains(&x) && (1..=3).contains(&y) {
+                    200
+                } else {
+                    10
+                };
+                assert_eq!(dilated[(y * 5 + x) * BYTES_PER_PIXEL], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn percentile_out_of_range_is_invalid() {
+        assert!(!RankParams { percentile: 101.0 }.is_valid());
+        assert!(
+            !RankParams {
+                percentile: f64::NAN
+            }
+            .is_valid()
+        );
+        assert!(RankParams::MAX.is_valid());
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Generates a random RGBA image with dimensions
+        /// in range [1, 12] and random pixel data.
+        fn arbitrary_image() -> impl Strategy<Value = (usize, usize, Vec<u8>)> {
+            (1..=12usize, 1..=12usize).prop_flat_map(|(w, h)| {
+                let len = w * h * BYTES_PER_PIXEL;
+                (Just(w), Just(h), proptest::collection::vec(any::<u8>(), len))
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn radius_zero_is_identity(
+                (w, h, mut data) in arbitrary_image(),
+                percentile in 0.0..=100.0f64,
+            ) {
+                let original = data.clone();
+                rank_filter(&mut data, w, h, 0, RankParams { percentile }, 1);
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn matches_sorting_reference(
+                (w, h, data) in arbitrary_image(),
+                radius in 1..=4usize,
+                percentile in 0.0..=100.0f64,
+            ) {
+                let params = RankParams { percentile };
+                let expected = rank_by_sorting(&data, w, h, radius, params);
+                let mut actual = data;
+                rank_filter(&mut actual, w, h, radius, params, 1);
+                prop_assert_eq!(actual, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use serde::Deserialize;
+
+use crate::{BYTES_PER_PIXEL, Transfer, weighted_blur};
+
+/// Unsharp mask parameters. The blur radius is the top-level
+/// `radius` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SharpenParams {
+    /// Strength of the sharpening; `0.0` leaves the image
+    /// unchanged.
+    pub amount: f64,
+    /// Minimum per-channel difference (in 8-bit units) between
+    /// a pixel and its blurred value for it to be sharpened.
+    pub threshold: u8,
+}
+
+impl Default for SharpenParams {
+    fn default() -> Self {
+        Self {
+            amount: 1.0,
+            threshold: 0,
+        }
+    }
+}
+
+impl SharpenParams {
+    /// Returns `true` if `amount` is finite and non-negative.
+    pub fn is_valid(&self) -> bool {
+        self.amount.is_finite() && self.amount >= 0.0
+    }
+}
+
+/// Sharpens an RGBA buffer with an unsharp mask:
+/// `original + amount * (original - blurred)`, clamped to the
+/// channel range.
+///
+/// The blurred reference is produced by [`weighted_blur`]
+/// with the given `radius`. Alpha is left unchanged.
+pub fn unsharp_mask(
+    data: &mut [u8],
+    width: usize,
+    height: usize,
+    radius: usize,
+    params: &SharpenParams,
+    iterations: u32,
+    transfer: Transfer,
+) {
+    let mut blurred = vec![0u8; data.len()];
+
+    for _ in 0..iterations {
+        blurred.copy_from_slice(data);
+        weighted_blur(&mut blurred, width, height, radius, 1, transfer);
+
+        let (blurred, _) = blurred.as_chunks::<BYTES_PER_PIXEL>();
+        let (pixels, _) = data.as_chunks_mut::<BYTES_PER_PIXEL>();
+
+        for (pixel, blur) in pixels.iter_mut().zip(blurred) {
+            for (value, &reference) in pixel[..3].iter_mut().zip(&blur[..3]) {
+                if value.abs_diff(reference) <= params.threshold {
+                    continue;
+                }
+                let original = transfer.decode(*value);
+                let detail = original - transfer.decode(reference);
+                let max = transfer.decode(u8::MAX);
+                *value = transfer.encode(
+                    detail.mul_add(params.amount, original).clamp(0.0, max),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a one-row opaque gray step edge.
+    fn step_edge(width: usize, left: u8, right: u8) -> Vec<u8> {
+        (0..width)
+            .flat_map(|x| {
+                let v = if x < width / 2 { left } else { right };
+                [v, v, v, 255]
+            })
+            .collect()
+    }
+
+    fn red(data: &[u8], x: usize) -> u8 {
+        data[x * BYTES_PER_PIXEL]
+    }
+
+    #[test]
+    fn contrast_increases_across_edge() {
+        let mut data = step_edge(8, 64, 192);
+        unsharp_mask(
+            &mut data,
+            8,
+            1,
+            1,
+            &SharpenParams::default(),
+            1,
+            Transfer::Encoded,
+        );
+
+        // Pixels next to the edge overshoot outwards...
+        assert!(red(&data, 3) < 64, "dark side R={}", red(&data, 3));
+        assert!(red(&data, 4) > 192, "bright side R={}", red(&data, 4));
+        // ...so the step across the edge is larger than before.
+        assert!(red(&data, 4) - red(&data, 3) > 192 - 64);
+        // Flat areas far from the edge stay put.
+        assert_eq!(red(&data, 0), 64);
+        assert_eq!(red(&data, 7), 192);
+    }
+
+    #[test]
+    fn result_is_clamped_to_channel_range() {
+        let mut data = step_edge(6, 0, 255);
+        let params = SharpenParams {
+            amount: 5.0,
+            threshold: 0,
+        };
+        unsharp_mask(&mut data, 6, 1, 2, &params, 1, Transfer::Encoded);
+        assert_eq!(data, step_edge(6, 0, 255));
+    }
+
+    #[test]
+    fn threshold_skips_small_differences() {
+        let mut data = step_edge(8, 100, 104);
+        let original = data.clone();
+        let params = SharpenParams {
+            amount: 2.0,
+            threshold: 4,
+        };
+        unsharp_mask(&mut data, 8, 1, 1, &params, 1, Transfer::Encoded);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn alpha_is_unchanged() {
+        let mut data = vec![
+            0, 0, 0, 10, //
+            255, 255, 255, 250, //
+        ];
+        unsharp_mask(
+            &mut data,
+            2,
+            1,
+            1,
+            &SharpenParams::default(),
+            1,
+            Transfer::Encoded,
+        );
+        assert_eq!((data[3], data[7]), (10, 250));
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn zero_amount_is_identity(
+                (w, h, mut data) in (1..=12usize, 1..=12usize)
+                    .prop_flat_map(|(w, h)| {
+                        let len = w * h * BYTES_PER_PIXEL;
+                        (
+                            Just(w),
+                            Just(h),
+                            proptest::collection::vec(any::<u8>(), len),
+                        )
+                    }),
+                radius in 0..=3usize,
+            ) {
+                let original = data.clone();
+                let params = SharpenParams {
+                    amount: 0.0,
+                    threshold: 0,
+                };
+                unsharp_mask(
+                    &mut data,
+                    w,
+                    h,
+                    radius,
+                    &params,
+                    1,
+                    Transfer::LinearLight,
+                );
+                prop_assert_eq!(data, original);
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::LazyLock;
+
+/// Linear-light intensity in `[0.0, 1.0]` for every 8-bit
+/// sRGB code value.
+static DECODE: LazyLock<[f64; 256]> = LazyLock::new(|| {
+    std::array::from_fn(|code| {
+        #[allow(clippy::cast_precision_loss)]
+        srgb_to_linear(code as f64 / 255.0)
+    })
+});
+
+/// Linear-light values halfway (in sRGB space) between
+/// consecutive code values. The encoded value of a linear
+/// intensity is the number of thresholds below it.
+static ENCODE_THRESHOLDS: LazyLock<[f64; 255]> = LazyLock::new(|| {
+    std::array::from_fn(|code| {
+        #[allow(clippy::cast_precision_loss)]
+        srgb_to_linear((code as f64 + 0.5) / 255.0)
+    })
+});
+
+/// Converts an 8-bit sRGB code value to linear light.
+pub fn to_linear(value: u8) -> f64 {
+    DECODE[usize::from(value)]
+}
+
+/// Converts a linear-light intensity back to the nearest
+/// 8-bit sRGB code value. Out-of-range input is clamped.
+pub fn from_linear(value: f64) -> u8 {
+    let code = ENCODE_THRESHOLDS.partition_point(|&t| t < value);
+    u8::try_from(code).unwrap_or(u8::MAX)
+}
+
+/// sRGB electro-optical transfer function (IEC 61966-2-1).
+fn srgb_to_linear(encoded: f64) -> f64 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_map_exactly() {
+        assert!(to_linear(0).abs() < f64::EPSILON);
+        assert!((to_linear(255) - 1.0).abs() < f64::EPSILON);
+        assert_eq!(from_linear(0.0), 0);
+        assert_eq!(from_linear(1.0), 255);
+    }
+
+    #[test]
+    fn round_trip_is_identity() {
+        for code in 0..=u8::MAX {
+            assert_eq!(from_linear(to_linear(code)), code);
+        }
+    }
+
+    #[test]
+    fn linear_half_is_perceptual_mid_gray() {
+        assert_eq!(from_linear(0.5), 188);
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        assert_eq!(from_linear(-0.5), 0);
+        assert_eq!(from_linear(2.0), 255);
+    }
+}
@@ -0,0 +1,199 @@
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+
+use plugin_sdk::Plugin;
+use plugin_sdk::ffi::TileInfo;
+
+use crate::error::AppError;
+use crate::image_view::ImageViewMut;
+use crate::plugin::ImagePlugin;
+use crate::plugin_loader::{AuxInput, aux_names, check, image_descs};
+use crate::registry::PluginRegistry;
+
+/// Registers the bundled plugins enabled by the `builtin-mirror`
+/// and `builtin-blur` features under their library names.
+#[cfg_attr(
+    not(any(feature = "builtin-mirror", feature = "builtin-blur")),
+    allow(unused_variables, clippy::missing_const_for_fn)
+)]
+pub fn register_builtins(plugins: &mut PluginRegistry) {
+    #[cfg(feature = "builtin-mirror")]
+    plugins.register_builtin(
+        "mirror_plugin",
+        mirror_core::VERSION,
+        BuiltinPlugin::<mirror_core::MirrorPlugin>::new(),
+    );
+    #[cfg(feature = "builtin-blur")]
+    plugins.register_builtin(
+        "blur_plugin",
+        blur_core::VERSION,
+        BuiltinPlugin::<blur_core::BlurPlugin>::new(),
+    );
+}
+
+/// Plugin written with `plugin_sdk` and linked into the host,
+/// so no library has to be loaded.
+///
+/// Calls go through the same pointer validation, parameter
+/// parsing and panic catching as the C ABI a dynamic plugin
+/// exports with `declare_plugin!`; a fresh `P` is created for
+/// every call.
+pub struct BuiltinPlugin<P> {
+    plugin: PhantomData<fn() -> P>,
+}
+
+impl<P: Plugin + Default> BuiltinPlugin<P> {
+    /// Creates the plugin.
+    pub const fn new() -> Self {
+        Self {
+            plugin: PhantomData,
+        }
+    }
+}
+
+impl<P: Plugin + Default> Default for BuiltinPlugin<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Plugin + Default> ImagePlugin for BuiltinPlugin<P> {
+    /// SDK plugins always accept auxiliary inputs; plugins that
+    /// do not use them reject non-empty ones with code 5.
+    fn supports_aux_inputs(&self) -> bool {
+        true
+    }
+
+    fn thread_safe(&self) -> bool {
+        P::THREAD_SAFE
+    }
+
+    fn tile_halo(&self, params: &str) -> Result<Option<u32>, AppError> {
+        let params = CString::new(params).unwrap_or_default();
+        let mut info = TileInfo::default();
+
+        // SAFETY: params is a valid C string and info is a live,
+        // writable TileInfo for the entire call.
+        let code = unsafe {
+            plugin_sdk::ffi::tile_info(
+                P::default,
+                params.as_ptr(),
+                &raw mut info,
+            )
+        };
+        check(code, last_error)?;
+
+        Ok(info.tile_safe.then_some(info.halo))
+    }
+
+    fn process_view(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: &str,
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        let names = aux_names(aux)?;
+        let params = CString::new(params).unwrap_or_default();
+
+        let (desc, raw) = image_descs(&mut image, aux, &names);
+
+        // SAFETY: the descriptor covers exactly the view's buffer,
+        // which stays alive and exclusively borrowed for the
+        // entire call; aux buffers are only read and their sizes
+        // were verified by aux_names.
+        let code = unsafe {
+            plugin_sdk::ffi::process_image_desc(
+                P::default,
+                &raw const desc,
+                params.as_ptr(),
+                raw.as_ptr(),
+                raw.len(),
+            )
+        };
+        check(code, last_error)
+    }
+}
+
+/// Reads the SDK's last error message on this thread.
+fn last_error() -> Option<String> {
+    let ptr = plugin_sdk::ffi::last_error();
+    if ptr.is_null() {
+        return None;
+    }
+
+    // SAFETY: a non-null pointer refers to the SDK's thread-local
+    // message, which is not modified until the next plugin call.
+    let message = unsafe { CStr::from_ptr(ptr) };
+    Some(message.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin_sdk::PluginError;
+    use serde::Deserialize;
+
+    /// Fills the image with `value`; panics on 13.
+    #[derive(Default)]
+    struct Fill;
+
+    #[derive(Deserialize)]
+    struct FillParams {
+        value: u8,
+    }
+
+    impl Plugin for Fill {
+        type Params = FillParams;
+
+        fn process(
+            &self,
+            mut image: plugin_sdk::ImageViewMut<'_>,
+            params: FillParams,
+        ) -> Result<(), PluginError> {
+            assert!(params.value != 13, "unlucky value");
+            image.rows_mut().for_each(|row| row.fill(params.value));
+            Ok(())
+        }
+
+        fn tile_halo(
+            &self,
+            params: &FillParams,
+        ) -> Result<Option<u32>, PluginError> {
+            Ok(Some(u32::from(params.value)))
+        }
+    }
+
+    #[test]
+    fn processes_strided_view() {
+        let plugin = BuiltinPlugin::<Fill>::new();
+        let mut data = [0u8; 3 * 4];
+        let mut image = ImageViewMut::new(&mut data, 3, 1).unwrap();
+        let view = image.sub_view(1, 0, 1, 1).unwrap();
+        plugin.process_view(view, r#"{"value": 7}"#, &[]).unwrap();
+        assert_eq!(data, [0, 0, 0, 0, 7, 7, 7, 7, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn errors_and_panics_are_reported() {
+        let plugin = BuiltinPlugin::<Fill>::new();
+        let mut data = [0u8; 4];
+
+        let err = plugin.process_image(1, 1, &mut data, "{}").unwrap_err();
+        assert!(matches!(err, AppError::PluginExec { code: 4 }), "{err}");
+
+        let err = plugin
+            .process_image(1, 1, &mut data, r#"{"value": 13}"#)
+            .unwrap_err();
+        assert!(
+            matches!(&err, AppError::PluginPanicked { message } if message == "unlucky value"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn tile_halo_comes_from_plugin() {
+        let plugin = BuiltinPlugin::<Fill>::new();
+        assert_eq!(plugin.tile_halo(r#"{"value": 3}"#).unwrap(), Some(3));
+        assert!(plugin.tile_halo("{}").is_err());
+    }
+}
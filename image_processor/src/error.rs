@@ -15,6 +15,15 @@ pub enum AppError {
         source: image::ImageError,
     },
 
+    #[error("failed to decode image: {source}")]
+    ImageDecode { source: image::ImageError },
+
+    #[error("failed to encode image: {source}")]
+    ImageEncode { source: image::ImageError },
+
+    #[error("unsupported image format '{format}'")]
+    UnsupportedFormat { format: String },
+
     #[error("failed to load plugin '{path}': {source}")]
     PluginLoad {
         path: PathBuf,
@@ -27,9 +36,119 @@ pub enum AppError {
     )]
     SymbolLoad(libloading::Error),
 
+    #[error("failed to load WebAssembly plugin '{path}': {message}")]
+    WasmLoad { path: PathBuf, message: String },
+
+    #[error("WebAssembly plugin failed: {message}")]
+    Wasm { message: String },
+
+    #[error("untrusted plugin '{path}': {reason}")]
+    UntrustedPlugin { path: PathBuf, reason: String },
+
+    #[error("invalid plugin manifest '{path}': {message}")]
+    InvalidManifest { path: PathBuf, message: String },
+
+    #[error("plugin '{path}' does not match its manifest: {message}")]
+    ManifestMismatch { path: PathBuf, message: String },
+
+    #[error("plugin '{path}' is not supported by this host: {reason}")]
+    IncompatiblePlugin { path: PathBuf, reason: String },
+
+    #[error("invalid plugin '{spec}': {message}")]
+    InvalidPluginSpec { spec: String, message: String },
+
+    #[error(
+        "no version of plugin '{name}' matches '{requirement}' \
+         (available: {})",
+        .available.join(", ")
+    )]
+    PluginVersionNotFound {
+        name: String,
+        requirement: String,
+        available: Vec<String>,
+    },
+
+    #[error(
+        "plugin '{name}' version {version} is installed twice: \
+         '{first}' and '{second}'"
+    )]
+    PluginVersionConflict {
+        name: String,
+        version: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
+    #[error("invalid trust store '{path}': {message}")]
+    InvalidTrustStore { path: PathBuf, message: String },
+
+    #[error(
+        "invalid signing key '{path}': expected a hex-encoded \
+         32-byte ed25519 secret key"
+    )]
+    InvalidSigningKey { path: PathBuf },
+
+    #[error("plugin '{path}' is not in the plugin directory '{dir}'")]
+    PluginNotInDirectory { path: PathBuf, dir: PathBuf },
+
+    #[error(
+        "buffer of {len} bytes does not match a {width}x{height} \
+         RGBA image with a stride of {stride} bytes"
+    )]
+    InvalidImageView {
+        width: u32,
+        height: u32,
+        stride: usize,
+        len: usize,
+    },
+
     #[error("plugin returned error code {code}")]
     PluginExec { code: std::ffi::c_int },
 
+    #[error("plugin returned error code {code}: {message}")]
+    PluginFailed {
+        code: std::ffi::c_int,
+        message: String,
+    },
+
+    #[error("plugin panicked: {message}")]
+    PluginPanicked { message: String },
+
+    #[error(
+        "plugin does not accept auxiliary inputs \
+         (missing 'process_image_with_aux')"
+    )]
+    AuxInputsUnsupported,
+
+    #[error("invalid auxiliary input '{name}'")]
+    InvalidAuxInput { name: String },
+
+    #[error(
+        "region {}x{} at ({}, {}) is empty or outside \
+         the {width}x{height} image",
+        .region.width, .region.height, .region.x, .region.y
+    )]
+    InvalidRegion {
+        region: crate::image_view::Rect,
+        width: u32,
+        height: u32,
+    },
+
+    #[error("invalid pipeline '{path}': {message}")]
+    InvalidPipeline { path: PathBuf, message: String },
+
+    #[error("invalid pipeline: {message}")]
+    InvalidPipelineJson { message: String },
+
+    #[error("plugin is not tile-safe for these parameters")]
+    NotTileSafe,
+
+    #[error("HTTP server failed: {message}")]
+    Serve { message: String },
+
+    #[error("failed to watch for changes: {message}")]
+    Watch { message: String },
+
     #[error("I/O error for '{path}': {source}")]
     Io {
         path: PathBuf,
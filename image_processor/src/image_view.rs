@@ -0,0 +1,394 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::error::AppError;
+
+/// Bytes per RGBA pixel.
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Rectangle in pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Rect {
+    /// Left edge.
+    pub x: u32,
+    /// Top edge.
+    pub y: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns `true` if the rectangle is non-empty and lies
+    /// inside a `width x height` image.
+    pub fn fits_in(self, width: u32, height: u32) -> bool {
+        let fits_x = self.x.checked_add(self.width).is_some_and(|r| r <= width);
+        let fits_y =
+            self.y.checked_add(self.height).is_some_and(|b| b <= height);
+        self.width > 0 && self.height > 0 && fits_x && fits_y
+    }
+
+    /// Grows the rectangle by `margin` pixels on every side,
+    /// clipped to a `width x height` image.
+    #[must_use]
+    pub fn expand(self, margin: u32, width: u32, height: u32) -> Self {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        let right = self.x.saturating_add(self.width).saturating_add(margin);
+        let bottom = self.y.saturating_add(self.height).saturating_add(margin);
+        Self {
+            x,
+            y,
+            width: right.min(width) - x,
+            height: bottom.min(height) - y,
+        }
+    }
+}
+
+/// Error returned when parsing a [`Rect`] from a string.
+#[derive(Debug, thiserror::Error)]
+#[error("expected a rectangle as 'x,y,width,height'")]
+pub struct ParseRectError;
+
+impl FromStr for Rect {
+    type Err = ParseRectError;
+
+    /// Parses `x,y,width,height`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ParseRectError)?;
+        let [x, y, width, height] = values[..] else {
+            return Err(ParseRectError);
+        };
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Mutable view of an RGBA8 image whose rows may be padded or
+/// belong to a larger image.
+///
+/// Row `y` starts at byte `y * stride` of the underlying
+/// buffer; only the first `width * 4` bytes of each row are
+/// pixel data.
+#[derive(Debug)]
+pub struct ImageViewMut<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> ImageViewMut<'a> {
+    /// Creates a view of a tightly packed buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidImageView` unless
+    /// `data.len() == width * height * 4`.
+    pub fn new(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Self, AppError> {
+        let stride = row_len(width);
+        if Some(data.len()) != span(width, height, stride) {
+            return Err(invalid(width, height, stride, data.len()));
+        }
+        Ok(Self {
+            data,
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Creates a view whose rows start `stride` bytes apart.
+    ///
+    /// `data` may extend past the last row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidImageView` if `stride` is
+    /// shorter than a row or `data` does not cover all rows.
+    pub fn with_stride(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> Result<Self, AppError> {
+        let fits =
+            span(width, height, stride).is_some_and(|span| span <= data.len());
+        if stride < row_len(width) || !fits {
+            return Err(invalid(width, height, stride, data.len()));
+        }
+        Ok(Self {
+            data,
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Width in pixels.
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Distance in bytes between the starts of two rows.
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns `true` if rows have no padding between them.
+    pub const fn is_packed(&self) -> bool {
+        self.stride == row_len(self.width)
+    }
+
+    /// Returns a shorter-lived view of the same pixels, e.g. to
+    /// pass to [`PluginLoader::process_view`] and keep using
+    /// this one afterwards.
+    ///
+    /// [`PluginLoader::process_view`]: crate::plugin_loader::PluginLoader::process_view
+    pub const fn reborrow(&mut self) -> ImageViewMut<'_> {
+        ImageViewMut {
+            data: self.data,
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+        }
+    }
+
+    /// Returns a view of the `width x height` rectangle whose
+    /// top-left corner is at `(x, y)`, sharing this buffer.
+    ///
+    /// Returns `None` if the rectangle does not fit inside the
+    /// image.
+    pub fn sub_view(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Option<ImageViewMut<'_>> {
+        let fits_x = x.checked_add(width).is_some_and(|r| r <= self.width);
+        let fits_y = y.checked_add(height).is_some_and(|b| b <= self.height);
+        if !fits_x || !fits_y {
+            return None;
+        }
+
+        let data = if height == 0 {
+            &mut []
+        } else {
+            let offset = y as usize * self.stride + row_len(x);
+            let end = offset + span(width, height, self.stride)?;
+            &mut self.data[offset..end]
+        };
+        Some(ImageViewMut {
+            data,
+            width,
+            height,
+            stride: self.stride,
+        })
+    }
+
+    /// Pixel bytes of each row, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        let row_len = row_len(self.width);
+        self.data
+            .chunks(self.stride.max(1))
+            .take(self.height as usize)
+            .map(move |row| &row[..row_len])
+    }
+
+    /// Mutable pixel bytes of each row, top to bottom.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let row_len = row_len(self.width);
+        self.data
+            .chunks_mut(self.stride.max(1))
+            .take(self.height as usize)
+            .map(move |row| &mut row[..row_len])
+    }
+
+    /// Underlying buffer, starting at the top-left pixel.
+    pub(crate) const fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+/// Copies `rect` out of a packed RGBA image `width` pixels
+/// wide.
+///
+/// # Panics
+///
+/// Panics if `rect` lies outside the image.
+pub(crate) fn crop(data: &[u8], width: u32, rect: Rect) -> Vec<u8> {
+    let row = row_len(rect.width);
+    (rect.y..rect.y + rect.height)
+        .flat_map(|y| {
+            let start = row_len(width) * y as usize + row_len(rect.x);
+            &data[start..start + row]
+        })
+        .copied()
+        .collect()
+}
+
+/// Bytes of pixel data in a row of `width` pixels.
+const fn row_len(width: u32) -> usize {
+    width as usize * BYTES_PER_PIXEL
+}
+
+/// Bytes from the first pixel to the end of the last row, or
+/// `None` on overflow.
+fn span(width: u32, height: u32, stride: usize) -> Option<usize> {
+    if height == 0 {
+        return Some(0);
+    }
+    stride
+        .checked_mul(height as usize - 1)?
+        .checked_add(row_len(width))
+}
+
+/// Builds the error for a buffer that does not fit a layout.
+const fn invalid(
+    width: u32,
+    height: u32,
+    stride: usize,
+    len: usize,
+) -> AppError {
+    AppError::InvalidImageView {
+        width,
+        height,
+        stride,
+        len,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_parses_from_comma_separated_values() {
+        let rect: Rect = "1, 2,30,40".parse().unwrap();
+        assert_eq!(
+            rect,
+            Rect {
+                x: 1,
+                y: 2,
+                width: 30,
+                height: 40
+            }
+        );
+        assert!("1,2,3".parse::<Rect>().is_err());
+        assert!("1,2,3,4,5".parse::<Rect>().is_err());
+        assert!("1,2,-3,4".parse::<Rect>().is_err());
+    }
+
+    #[test]
+    fn rect_fits_and_expands_within_image() {
+        let rect = Rect {
+            x: 1,
+            y: 2,
+            width: 3,
+            height: 2,
+        };
+        assert!(rect.fits_in(4, 4));
+        assert!(!rect.fits_in(3, 4));
+        assert!(!Rect { width: 0, ..rect }.fits_in(4, 4));
+        assert!(
+            !Rect {
+                x: u32::MAX,
+                ..rect
+            }
+            .fits_in(4, 4)
+        );
+
+        let expanded = Rect {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 4,
+        };
+        assert_eq!(rect.expand(2, 4, 4), expanded);
+        assert_eq!(rect.expand(0, 4, 4), rect);
+    }
+
+    #[test]
+    fn crop_extracts_rectangle() {
+        // 3x2 image with pixel index in every byte.
+        let data: Vec<u8> =
+            (0..6u8).flat_map(|i| [i; BYTES_PER_PIXEL]).collect();
+        let rect = Rect {
+            x: 1,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let pixels: Vec<u8> = crop(&data, 3, rect)
+            .chunks(BYTES_PER_PIXEL)
+            .map(|pixel| pixel[0])
+            .collect();
+        assert_eq!(pixels, [1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn new_requires_exact_length() {
+        assert!(ImageViewMut::new(&mut [0; 16], 2, 2).is_ok());
+        assert!(ImageViewMut::new(&mut [0; 15], 2, 2).is_err());
+        assert!(ImageViewMut::new(&mut [0; 17], 2, 2).is_err());
+    }
+
+    #[test]
+    fn with_stride_checks_row_and_buffer_size() {
+        assert!(ImageViewMut::with_stride(&mut [0; 20], 2, 2, 12).is_ok());
+        assert!(ImageViewMut::with_stride(&mut [0; 19], 2, 2, 12).is_err());
+        assert!(ImageViewMut::with_stride(&mut [0; 20], 2, 2, 4).is_err());
+    }
+
+    #[test]
+    fn sub_view_shares_buffer() {
+        // 3x3 image with pixel index in every byte.
+        let mut data: Vec<u8> =
+            (0..9u8).flat_map(|i| [i; BYTES_PER_PIXEL]).collect();
+        let mut image = ImageViewMut::new(&mut data, 3, 3).unwrap();
+
+        let mut sub = image.sub_view(1, 1, 2, 2).unwrap();
+        assert!(!sub.is_packed());
+        let rows: Vec<Vec<u8>> = sub.rows().map(<[u8]>::to_vec).collect();
+        assert_eq!(rows, [[4, 4, 4, 4, 5, 5, 5, 5], [7, 7, 7, 7, 8, 8, 8, 8]]);
+
+        sub.rows_mut().for_each(|row| row.fill(0));
+        let zeroed: Vec<usize> = data
+            .chunks(BYTES_PER_PIXEL)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0] == 0)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(zeroed, [0, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn sub_view_out_of_bounds_is_none() {
+        let mut data = [0u8; 36];
+        let mut image = ImageViewMut::new(&mut data, 3, 3).unwrap();
+        assert!(image.sub_view(2, 0, 2, 1).is_none());
+        assert!(image.sub_view(0, 3, 1, 1).is_none());
+        assert!(image.sub_view(u32::MAX, 0, 2, 1).is_none());
+        assert_eq!(image.sub_view(0, 3, 3, 0).unwrap().rows().count(), 0);
+    }
+}
@@ -1,2 +1,18 @@
+pub mod builtin;
 pub mod error;
+pub mod image_view;
+pub mod manifest;
+pub mod pipeline;
+pub mod plugin;
 pub mod plugin_loader;
+pub mod process;
+pub mod region;
+pub mod registry;
+pub mod resolver;
+pub mod server;
+pub mod tiling;
+pub mod trust;
+pub mod wasm_plugin;
+pub mod watch;
+
+pub use process::{process_bytes, process_rgba};
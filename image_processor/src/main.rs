@@ -1,82 +1,382 @@
 use std::fs;
-use std::path::PathBuf;
-use std::process;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::{process, thread};
 
-use clap::Parser;
-use image::GenericImageView as _;
+use clap::{Parser, Subcommand};
+use image::RgbaImage;
+use image_processor::builtin::register_builtins;
 use image_processor::error::AppError;
-use image_processor::plugin_loader::PluginLoader;
+use image_processor::image_view::Rect;
+use image_processor::pipeline::{Pipeline, Step};
+use image_processor::plugin_loader::AuxInput;
+use image_processor::process_rgba;
+use image_processor::registry::PluginRegistry;
+use image_processor::server::Server;
+use image_processor::trust::{TrustStore, sign_plugin};
+use image_processor::wasm_plugin::{
+    DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES, WasmLimits,
+};
+use image_processor::watch::{ChangeWatcher, plugin_paths};
 
 /// CLI application for processing PNG images
 /// using dynamically loaded plugins.
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the input PNG image
-    #[arg(long)]
-    input: PathBuf,
+    #[arg(long, required = true)]
+    input: Option<PathBuf>,
 
     /// Path to save the processed image
+    #[arg(long, required = true)]
+    output: Option<PathBuf>,
+
+    /// Plugin name (without extension, e.g. mirror), optionally
+    /// with a version requirement (`blur_plugin@^1.2`), or a
+    /// filename such as `mirror_plugin.wasm`
+    #[arg(long, required_unless_present = "pipeline")]
+    plugin: Option<String>,
+
+    /// Path to a text file with processing parameters
+    #[arg(long, required_unless_present = "pipeline")]
+    params: Option<PathBuf>,
+
+    /// Path to a JSON pipeline file listing plugin steps
+    /// (replaces --plugin and --params)
+    #[arg(
+        long,
+        conflicts_with_all = ["plugin", "params", "region", "feather", "tile_size"]
+    )]
+    pipeline: Option<PathBuf>,
+
+    #[command(flatten)]
+    load: LoadArgs,
+
+    /// Optional mask image passed to the plugin as the
+    /// auxiliary input named "mask"
     #[arg(long)]
-    output: PathBuf,
+    mask: Option<PathBuf>,
 
-    /// Plugin name (without extension, e.g. mirror)
+    /// Process the image in tiles of this many pixels per side
+    /// (the plugin must be tile-safe for the given parameters).
+    /// Bounds the plugin's working memory only: the whole image
+    /// is still decoded into memory
     #[arg(long)]
-    plugin: String,
+    tile_size: Option<NonZeroU32>,
 
-    /// Path to a text file with processing parameters
+    /// Only process this rectangle, given as x,y,width,height
+    #[arg(long, conflicts_with = "tile_size")]
+    region: Option<Rect>,
+
+    /// Width in pixels of the soft edge around --region
+    #[arg(long, default_value_t = 0, requires = "region")]
+    feather: u32,
+
+    /// Keep running and process the image again whenever the
+    /// input, parameters, pipeline, mask or plugin libraries
+    /// change (stop with Ctrl+C)
     #[arg(long)]
-    params: PathBuf,
+    watch: bool,
+}
 
-    /// Path to the directory containing plugins
+/// Options for finding and loading plugins, shared by the
+/// commands that run them.
+#[derive(clap::Args, Debug)]
+struct LoadArgs {
+    /// Path to the directory containing plugins (these override
+    /// built-in plugins of the same name)
     #[arg(long, default_value = "target/debug")]
     plugin_path: PathBuf,
+
+    /// Maximum linear memory of a WebAssembly plugin, in MiB
+    #[arg(long, default_value_t = DEFAULT_MAX_MEMORY_BYTES >> 20)]
+    wasm_memory_mib: usize,
+
+    /// Fuel (roughly, instructions) a WebAssembly plugin may
+    /// use per call
+    #[arg(long, default_value_t = DEFAULT_FUEL)]
+    wasm_fuel: u64,
+
+    /// JSON trust store listing the plugin files (with SHA-256
+    /// digests) allowed to load from --plugin-path
+    #[arg(long)]
+    trust_store: Option<PathBuf>,
+
+    /// Only load plugins signed by a key in the trust store
+    #[arg(long, requires = "trust_store")]
+    require_signed: bool,
 }
 
-fn run(args: &Args) -> Result<(), AppError> {
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Sign a plugin file and add it to a trust store
+    SignPlugin {
+        /// Plugin file to sign, e.g. `target/debug/libblur_plugin.so`
+        plugin: PathBuf,
+
+        /// Plugin directory the plugin is loaded from; the trust
+        /// store lists it by its path relative to this directory
+        #[arg(long, default_value = "target/debug")]
+        plugin_path: PathBuf,
+
+        /// File with a hex-encoded 32-byte ed25519 secret key
+        #[arg(long)]
+        key: PathBuf,
+
+        /// Trust store to update (created if missing)
+        #[arg(long)]
+        trust_store: PathBuf,
+    },
+
+    /// List the plugins described by manifests in the plugin
+    /// directory, and the built-in plugins, without loading them
+    ListPlugins {
+        /// Path to the directory containing plugins
+        #[arg(long, default_value = "target/debug")]
+        plugin_path: PathBuf,
+    },
+
+    /// Run an HTTP service processing images posted to
+    /// `/process/{plugin}`
+    Serve(ServeArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    #[command(flatten)]
+    load: LoadArgs,
+
+    /// Plugin to serve, e.g. `blur_plugin@^1.2` (repeatable;
+    /// default: the built-in plugins; plugins from
+    /// --plugin-path are only served when named here)
+    #[arg(long = "plugin")]
+    plugins: Vec<String>,
+
+    /// Number of threads handling requests (default: one per
+    /// CPU)
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
+
+    /// Largest accepted request body, in MiB
+    #[arg(long, default_value_t = 64)]
+    max_body_mib: usize,
+}
+
+/// Builds the pipeline to run: the `--pipeline` file, or a
+/// single step from `--plugin` and `--params`.
+fn pipeline(args: &Args) -> Result<Pipeline, AppError> {
+    if let Some(path) = &args.pipeline {
+        return Pipeline::load(path);
+    }
+
+    let (Some(plugin), Some(params_path)) = (&args.plugin, &args.params) else {
+        unreachable!("clap requires --plugin and --params without --pipeline");
+    };
     let params =
-        fs::read_to_string(&args.params).map_err(|source| AppError::Io {
-            path: args.params.clone(),
+        fs::read_to_string(params_path).map_err(|source| AppError::Io {
+            path: params_path.clone(),
             source,
         })?;
 
-    log::info!("Loading image: {}", args.input.display());
+    Ok(Pipeline {
+        steps: vec![Step {
+            plugin: plugin.clone(),
+            params: serde_json::Value::String(params),
+            region: args.region,
+            feather: args.feather,
+            tile_size: args.tile_size,
+        }],
+    })
+}
 
-    let img =
-        image::open(&args.input).map_err(|source| AppError::ImageLoad {
-            path: args.input.clone(),
+/// Opens an image file as RGBA.
+fn open_image(path: &PathBuf) -> Result<RgbaImage, AppError> {
+    image::open(path)
+        .map(image::DynamicImage::into_rgba8)
+        .map_err(|source| AppError::ImageLoad {
+            path: path.clone(),
             source,
-        })?;
+        })
+}
 
-    let (width, height) = img.dimensions();
-    let mut rgba_image = img.into_rgba8();
+/// Registry of the plugins in `plugin_path` and the built-in
+/// plugins.
+fn registry(plugin_path: &Path) -> PluginRegistry {
+    let mut plugins = PluginRegistry::new(plugin_path);
+    register_builtins(&mut plugins);
+    plugins
+}
 
-    log::info!("Image size: {width}x{height}");
+impl LoadArgs {
+    /// Sets up the plugin registry from these options.
+    fn registry(&self) -> Result<PluginRegistry, AppError> {
+        let mut plugins = registry(&self.plugin_path);
+        plugins.set_wasm_limits(WasmLimits {
+            max_memory_bytes: self.wasm_memory_mib.saturating_mul(1 << 20),
+            fuel: self.wasm_fuel,
+        });
+        if let Some(path) = &self.trust_store {
+            let mut trust_store = TrustStore::load(path)?;
+            trust_store.set_require_signed(self.require_signed);
+            plugins.set_trust_store(trust_store);
+        }
+        Ok(plugins)
+    }
+}
 
-    let rgba_data = rgba_image.as_mut();
+fn run(args: &Args) -> Result<(), AppError> {
+    let (Some(input), Some(output)) = (&args.input, &args.output) else {
+        unreachable!("clap requires --input and --output without a subcommand");
+    };
+    let pipeline = pipeline(args)?;
 
-    let loader = PluginLoader::load(&args.plugin, &args.plugin_path)?;
+    log::info!("Loading image: {}", input.display());
+    let mut image = open_image(input)?;
+    log::info!("Image size: {}x{}", image.width(), image.height());
 
-    loader.process_image(width, height, rgba_data, &params)?;
+    let mask = args
+        .mask
+        .as_ref()
+        .map(|path| {
+            log::info!("Loading mask: {}", path.display());
+            open_image(path)
+        })
+        .transpose()?;
+    let aux: Vec<AuxInput<'_>> = mask
+        .iter()
+        .map(|mask| AuxInput {
+            name: "mask",
+            width: mask.width(),
+            height: mask.height(),
+            rgba_data: mask.as_raw(),
+        })
+        .collect();
 
-    log::info!("Saving result: {}", args.output.display());
+    let mut plugins = args.load.registry()?;
+    plugins.set_copy_libraries(args.watch);
+    process_rgba(&plugins, &mut image, &pipeline.steps, &aux)?;
 
-    rgba_image
-        .save(&args.output)
-        .map_err(|source| AppError::ImageSave {
-            path: args.output.clone(),
-            source,
-        })?;
+    log::info!("Saving result: {}", output.display());
+    image.save(output).map_err(|source| AppError::ImageSave {
+        path: output.clone(),
+        source,
+    })?;
 
     log::info!("Done!");
     Ok(())
 }
 
+/// Files whose changes make `--watch` process the image again.
+fn watch_paths(args: &Args) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = [
+        &args.input,
+        &args.params,
+        &args.pipeline,
+        &args.mask,
+        &args.load.trust_store,
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect();
+    // An unreadable pipeline is reported by `run`; its file is
+    // still watched.
+    if let Ok(pipeline) = pipeline(args) {
+        paths.extend(plugin_paths(&pipeline, &args.load.plugin_path));
+    }
+    paths
+}
+
+/// Processes the image, then again after every change to its
+/// inputs or plugins. Every run loads its plugins afresh, from
+/// temporary copies, and unloads them when done; failed runs
+/// are reported and the next change is awaited.
+fn watch(args: &Args) -> Result<(), AppError> {
+    loop {
+        // Watch before running, so changes during a run are seen.
+        let watcher = ChangeWatcher::new(&watch_paths(args))?;
+        match run(args) {
+            Ok(()) => eprintln!("Done, watching for changes (Ctrl+C to stop)"),
+            Err(err) => eprintln!("Error: {err}"),
+        }
+        watcher.wait(None)?;
+        eprintln!("Change detected, processing again");
+    }
+}
+
+/// Prints the plugins found by `list-plugins`.
+fn list_plugins(plugin_path: &Path) -> Result<(), AppError> {
+    let plugins = registry(plugin_path);
+
+    let manifests = plugins.manifests()?;
+    for manifest in &manifests {
+        println!(
+            "{} {}\t{}",
+            manifest.name, manifest.version, manifest.description
+        );
+    }
+    for (name, version) in plugins.builtins() {
+        println!("{name} {version}\t(built-in)");
+    }
+    Ok(())
+}
+
+/// Loads the plugins and runs the HTTP service of `serve`.
+fn serve(args: &ServeArgs) -> Result<(), AppError> {
+    let plugins = args.load.registry()?;
+    let mut names = args.plugins.clone();
+    if names.is_empty() {
+        // Installed plugins, such as the Python bridge, may do
+        // more than their parameters suggest, so only serve
+        // them when asked to.
+        names.extend(plugins.builtins().into_iter().map(|(n, _)| n.to_owned()));
+    }
+
+    let mut server = Server::bind(&args.bind, &plugins, &names)?;
+    server.set_max_body_bytes(args.max_body_mib.saturating_mul(1 << 20));
+    let threads = args.threads.unwrap_or_else(|| {
+        thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+    });
+    eprintln!("Listening on http://{}", server.local_addr());
+    server.run(threads);
+    Ok(())
+}
+
 fn main() {
     env_logger::init();
     let args = Args::parse();
 
-    if let Err(err) = run(&args) {
+    let result = match &args.command {
+        Some(Command::SignPlugin {
+            plugin,
+            plugin_path,
+            key,
+            trust_store,
+        }) => {
+            sign_plugin(plugin, plugin_path, key, trust_store).map(|trusted| {
+                println!("Signed {} (sha256 {})", trusted.file, trusted.sha256);
+            })
+        }
+        Some(Command::ListPlugins { plugin_path }) => list_plugins(plugin_path),
+        Some(Command::Serve(serve_args)) => serve(serve_args),
+        None if args.watch => watch(&args),
+        None => run(&args),
+    };
+
+    if let Err(err) = result {
         eprintln!("Error: {err}");
         process::exit(1);
     }
@@ -0,0 +1,440 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use plugin_sdk::ffi::ABI_VERSION;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::resolver::installed_versions;
+
+/// Filename of the manifest in a plugin's directory.
+pub const MANIFEST_FILE: &str = "plugin.toml";
+
+/// Name of the only pixel format the host passes to plugins.
+pub const PIXEL_FORMAT_RGBA8: &str = "rgba8";
+
+/// Plugin manifest, read from `<plugin dir>/<name>/plugin.toml`
+/// or `<plugin dir>/<name>/<version>/plugin.toml` next to the
+/// plugin library:
+///
+/// ```toml
+/// name = "blur_plugin"
+/// version = "0.1.0"
+/// abi_version = 1
+/// description = "Blur filters"
+/// pixel_formats = ["rgba8"]
+/// changes_dimensions = false
+/// thread_safe = true
+///
+/// [params_schema]
+/// type = "object"
+/// ```
+///
+/// The manifest describes a plugin without loading its code;
+/// [`PluginLoader::load`](crate::plugin_loader::PluginLoader::load)
+/// compares it with the descriptor the library exports.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    /// Plugin name, as passed to `--plugin`.
+    pub name: String,
+    /// Plugin version (semver), used to resolve version
+    /// requirements such as `blur_plugin@^1.2`.
+    pub version: String,
+    /// Version of the plugin C ABI the library implements.
+    pub abi_version: u32,
+    /// One-line description for listings.
+    #[serde(default)]
+    pub description: String,
+    /// Library filename in the plugin's directory; defaults to
+    /// the platform filename for `name` (e.g. `libname.so`).
+    #[serde(default)]
+    pub library: Option<String>,
+    /// Pixel formats the plugin accepts.
+    #[serde(default = "default_pixel_formats")]
+    pub pixel_formats: Vec<String>,
+    /// Whether the plugin changes the image dimensions.
+    #[serde(default)]
+    pub changes_dimensions: bool,
+    /// Whether the plugin may be called from several threads
+    /// at once.
+    #[serde(default)]
+    pub thread_safe: bool,
+    /// JSON Schema of the plugin parameters.
+    #[serde(default)]
+    pub params_schema: Option<serde_json::Value>,
+}
+
+fn default_pixel_formats() -> Vec<String> {
+    vec![PIXEL_FORMAT_RGBA8.to_owned()]
+}
+
+/// What a plugin library reports about itself through
+/// `plugin_descriptor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptor {
+    /// Plugin C ABI version.
+    pub abi_version: u32,
+    /// Plugin name.
+    pub name: String,
+    /// Plugin version.
+    pub version: String,
+    /// Whether RGBA8 input is supported.
+    pub rgba8: bool,
+    /// Whether the plugin may be called concurrently.
+    pub thread_safe: bool,
+    /// Whether the plugin changes the image dimensions.
+    pub changes_dimensions: bool,
+}
+
+impl PluginManifest {
+    /// Reads and parses a manifest file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the file cannot be read and
+    /// `AppError::InvalidManifest` if it is not a valid
+    /// manifest.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|source| AppError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(path, &text)
+    }
+
+    /// Parses `text`, read from the manifest file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidManifest` if it is not a valid
+    /// manifest.
+    pub fn parse(path: &Path, text: &str) -> Result<Self, AppError> {
+        toml::from_str(text).map_err(|e| AppError::InvalidManifest {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Reads the manifests of all installed plugin versions in
+    /// `plugin_dir` (see [`installed_versions`]), sorted by name
+    /// and version, without loading any library. Plugins with
+    /// invalid manifests are logged and skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the directory cannot be read.
+    pub fn discover(plugin_dir: &Path) -> Result<Vec<Self>, AppError> {
+        let io_error = |source| AppError::Io {
+            path: plugin_dir.to_path_buf(),
+            source,
+        };
+
+        let mut installed = Vec::new();
+        for entry in fs::read_dir(plugin_dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str())
+            else {
+                continue;
+            };
+            match installed_versions(name, plugin_dir) {
+                Ok(versions) => installed.extend(versions),
+                Err(err) => log::warn!("Skipping plugin: {err}"),
+            }
+        }
+
+        installed.sort_by(|a, b| {
+            (&a.manifest.name, &a.version).cmp(&(&b.manifest.name, &b.version))
+        });
+        Ok(installed
+            .into_iter()
+            .map(|plugin| plugin.manifest)
+            .collect())
+    }
+
+    /// Path of the plugin library described by this manifest,
+    /// which lives in the manifest's directory `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidManifest` if `library` is not
+    /// a plain filename, e.g. an absolute path or one leaving
+    /// `dir` through `..`.
+    pub fn library_path(
+        &self,
+        dir: &Path,
+        default_file: &str,
+    ) -> Result<PathBuf, AppError> {
+        let file = self.library.as_deref().unwrap_or(default_file);
+        let mut components = Path::new(file).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(dir.join(file)),
+            _ => Err(AppError::InvalidManifest {
+                path: dir.join(MANIFEST_FILE),
+                message: format!(
+                    "library '{file}' must be a filename in the plugin's \
+                     directory"
+                ),
+            }),
+        }
+    }
+
+    /// Checks that the host can run the plugin: it must
+    /// implement this host's ABI version, accept RGBA8 pixels
+    /// and keep the image dimensions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::IncompatiblePlugin` naming the first
+    /// unsupported requirement.
+    pub fn check_supported(&self, path: &Path) -> Result<(), AppError> {
+        let reason = if self.abi_version != ABI_VERSION {
+            format!(
+                "requires ABI version {}, the host implements {ABI_VERSION}",
+                self.abi_version
+            )
+        } else if !self.pixel_formats.iter().any(|f| f == PIXEL_FORMAT_RGBA8) {
+            format!(
+                "supports pixel formats {:?}, the host only provides \
+                 '{PIXEL_FORMAT_RGBA8}'",
+                self.pixel_formats
+            )
+        } else if self.changes_dimensions {
+            "changes the image dimensions, which the host does not support"
+                .to_owned()
+        } else {
+            return Ok(());
+        };
+
+        Err(AppError::IncompatiblePlugin {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+
+    /// Compares the manifest with the library's descriptor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ManifestMismatch` listing every field
+    /// on which they disagree.
+    pub fn check_descriptor(
+        &self,
+        path: &Path,
+        descriptor: &Descriptor,
+    ) -> Result<(), AppError> {
+        let mut mismatches = Vec::new();
+        let mut compare = |field: &str, manifest: String, library: String| {
+            if manifest != library {
+                mismatches.push(format!(
+                    "{field}: manifest says {manifest}, library says {library}"
+                ));
+            }
+        };
+
+        compare("name", self.name.clone(), descriptor.name.clone());
+        compare("version", self.version.clone(), descriptor.version.clone());
+        compare(
+            "abi_version",
+            self.abi_version.to_string(),
+            descriptor.abi_version.to_string(),
+        );
+        compare(
+            "rgba8 support",
+            self.pixel_formats
+                .iter()
+                .any(|f| f == PIXEL_FORMAT_RGBA8)
+                .to_string(),
+            descriptor.rgba8.to_string(),
+        );
+        compare(
+            "thread_safe",
+            self.thread_safe.to_string(),
+            descriptor.thread_safe.to_string(),
+        );
+        compare(
+            "changes_dimensions",
+            self.changes_dimensions.to_string(),
+            descriptor.changes_dimensions.to_string(),
+        );
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ManifestMismatch {
+                path: path.to_path_buf(),
+                message: mismatches.join("; "),
+            })
+        }
+    }
+}
+
+/// Manifest fixtures shared by the unit tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::fs;
+    use std::path::Path;
+
+    use super::MANIFEST_FILE;
+
+    /// Writes `text` as the manifest in `plugin_dir/dir/`.
+    pub fn write_manifest(plugin_dir: &Path, dir: &str, text: &str) {
+        let dir = plugin_dir.join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(MANIFEST_FILE), text).unwrap();
+    }
+
+    /// Smallest valid manifest of plugin `name` at `version`.
+    pub fn minimal_manifest(name: &str, version: &str) -> String {
+        format!("name = \"{name}\"\nversion = \"{version}\"\nabi_version = 1\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{minimal_manifest, write_manifest};
+    use super::*;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"
+        name = "blur_plugin"
+        version = "1.2.0"
+        abi_version = 1
+        description = "Blur filters"
+        thread_safe = true
+
+        [params_schema]
+        type = "object"
+        properties.radius = { type = "integer", minimum = 0 }
+    "#;
+
+    fn descriptor() -> Descriptor {
+        Descriptor {
+            abi_version: 1,
+            name: "blur_plugin".into(),
+            version: "1.2.0".into(),
+            rgba8: true,
+            thread_safe: true,
+            changes_dimensions: false,
+        }
+    }
+
+    #[test]
+    fn parses_manifest_with_defaults() {
+        let manifest: PluginManifest = toml::from_str(MANIFEST).unwrap();
+        assert_eq!(manifest.name, "blur_plugin");
+        assert_eq!(manifest.pixel_formats, ["rgba8"]);
+        assert!(!manifest.changes_dimensions);
+        assert_eq!(
+            manifest.params_schema.as_ref().unwrap()["properties"]["radius"]["type"],
+            "integer"
+        );
+        assert_eq!(
+            manifest
+                .library_path(
+                    Path::new("plugins/blur_plugin"),
+                    "libblur_plugin.so"
+                )
+                .unwrap(),
+            Path::new("plugins/blur_plugin/libblur_plugin.so")
+        );
+    }
+
+    #[rstest]
+    #[case("/usr/lib/libevil.so")]
+    #[case("../other_plugin/libother.so")]
+    #[case("sub/libblur_plugin.so")]
+    #[case("..")]
+    #[case("")]
+    fn library_must_be_a_filename(#[case] library: &str) {
+        let mut manifest: PluginManifest = toml::from_str(MANIFEST).unwrap();
+        manifest.library = Some(library.to_owned());
+        let err = manifest
+            .library_path(Path::new("plugins/blur_plugin"), "libblur_plugin.so")
+            .unwrap_err();
+        assert!(
+            matches!(&err, AppError::InvalidManifest { path, .. }
+                if path == Path::new("plugins/blur_plugin/plugin.toml")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn discovers_manifests_without_loading_libraries() {
+        let dir = TempDir::new().unwrap();
+        write_manifest(dir.path(), "blur_plugin", MANIFEST);
+        write_manifest(
+            dir.path(),
+            "a_plugin",
+            &minimal_manifest("a_plugin", "0.1.0"),
+        );
+        write_manifest(
+            dir.path(),
+            "blur_plugin/0.9.0",
+            &MANIFEST.replace("1.2.0", "0.9.0"),
+        );
+        write_manifest(dir.path(), "broken", "name = ");
+        fs::write(dir.path().join("libother.so"), "").unwrap();
+
+        let versions: Vec<String> = PluginManifest::discover(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|manifest| format!("{} {}", manifest.name, manifest.version))
+            .collect();
+        assert_eq!(
+            versions,
+            ["a_plugin 0.1.0", "blur_plugin 0.9.0", "blur_plugin 1.2.0"]
+        );
+    }
+
+    #[test]
+    fn unsupported_requirements_are_rejected() {
+        let manifest: PluginManifest = toml::from_str(MANIFEST).unwrap();
+        manifest.check_supported(Path::new("p")).unwrap();
+
+        for unsupported in [
+            PluginManifest {
+                abi_version: 2,
+                ..manifest.clone()
+            },
+            PluginManifest {
+                pixel_formats: vec!["gray8".into()],
+                ..manifest.clone()
+            },
+            PluginManifest {
+                changes_dimensions: true,
+                ..manifest
+            },
+        ] {
+            assert!(
+                matches!(
+                    unsupported.check_supported(Path::new("p")),
+                    Err(AppError::IncompatiblePlugin { .. })
+                ),
+                "{unsupported:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn descriptor_mismatches_are_listed() {
+        let manifest: PluginManifest = toml::from_str(MANIFEST).unwrap();
+        manifest
+            .check_descriptor(Path::new("p"), &descriptor())
+            .unwrap();
+
+        let other = Descriptor {
+            version: "1.3.0".into(),
+            thread_safe: false,
+            ..descriptor()
+        };
+        let err = manifest
+            .check_descriptor(Path::new("p"), &other)
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("version: manifest says 1.2.0"), "{message}");
+        assert!(message.contains("thread_safe"), "{message}");
+        assert!(!message.contains("name:"), "{message}");
+    }
+}
@@ -0,0 +1,279 @@
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::image_view::Rect;
+use crate::plugin::ImagePlugin;
+use crate::plugin_loader::AuxInput;
+use crate::region::process_region;
+use crate::registry::PluginRegistry;
+use crate::resolver::PluginSpec;
+use crate::tiling::process_tiled;
+
+/// Sequence of plugin runs applied to one image, loaded from
+/// a JSON file:
+///
+/// ```json
+/// {"steps": [
+///     {"plugin": "blur_plugin", "params": {"radius": 2},
+///      "region": {"x": 0, "y": 0, "width": 64, "height": 32},
+///      "feather": 4},
+///     {"plugin": "mirror_plugin", "params": {"horizontal": true}}
+/// ]}
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    /// Steps, applied in order.
+    pub steps: Vec<Step>,
+}
+
+/// One plugin run within a [`Pipeline`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Plugin name (without extension), optionally with a
+    /// version requirement: `blur_plugin@>=1.2, <2`.
+    pub plugin: String,
+    /// Plugin parameters: a JSON value, or a string passed to
+    /// the plugin verbatim.
+    #[serde(default = "empty_params")]
+    pub params: serde_json::Value,
+    /// Restricts the step to this rectangle.
+    #[serde(default)]
+    pub region: Option<Rect>,
+    /// Soft edge width in pixels around `region`.
+    #[serde(default)]
+    pub feather: u32,
+    /// Processes the image in tiles of this size.
+    #[serde(default)]
+    pub tile_size: Option<NonZeroU32>,
+}
+
+fn empty_params() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+impl Pipeline {
+    /// Reads a pipeline from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the file cannot be read and
+    /// `AppError::InvalidPipeline` if it is not a valid
+    /// pipeline.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|source| AppError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text).map_err(|message| AppError::InvalidPipeline {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// Parses a pipeline from JSON text, e.g. one received over
+    /// an API rather than read from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidPipelineJson` if `text` is not
+    /// a valid pipeline.
+    pub fn from_json(text: &str) -> Result<Self, AppError> {
+        Self::parse(text)
+            .map_err(|message| AppError::InvalidPipelineJson { message })
+    }
+
+    /// Parses and checks a pipeline, describing the first
+    /// problem found.
+    fn parse(text: &str) -> Result<Self, String> {
+        let pipeline: Self =
+            serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if let Some(i) = pipeline
+            .steps
+            .iter()
+            .position(|step| step.region.is_some() && step.tile_size.is_some())
+        {
+            return Err(format!(
+                "step {} combines 'region' and 'tile_size'",
+                i + 1
+            ));
+        }
+        for (i, step) in pipeline.steps.iter().enumerate() {
+            step.plugin
+                .parse::<PluginSpec>()
+                .map_err(|e| format!("step {}: {e}", i + 1))?;
+        }
+        Ok(pipeline)
+    }
+
+    /// Runs every step on the image in place; see
+    /// [`run_steps`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from loading or running a step.
+    pub fn run(
+        &self,
+        plugins: &PluginRegistry,
+        width: u32,
+        height: u32,
+        rgba_data: &mut [u8],
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        run_steps(&self.steps, plugins, width, height, rgba_data, aux)
+    }
+}
+
+/// Runs `steps` on the image in place, looking plugins up in
+/// `plugins`. Every step gets the same auxiliary inputs.
+///
+/// # Errors
+///
+/// Returns the first error from loading or running a step.
+pub fn run_steps(
+    steps: &[Step],
+    plugins: &PluginRegistry,
+    width: u32,
+    height: u32,
+    rgba_data: &mut [u8],
+    aux: &[AuxInput<'_>],
+) -> Result<(), AppError> {
+    for (i, step) in steps.iter().enumerate() {
+        log::info!("Step {}/{}: {}", i + 1, steps.len(), step.plugin);
+        let plugin = plugins.get(&step.plugin)?;
+        step.run(plugin.as_ref(), width, height, rgba_data, aux)?;
+    }
+    Ok(())
+}
+
+impl Step {
+    /// Step running `plugin` on the whole image with `params`
+    /// (a JSON value, or a string passed verbatim).
+    pub fn new(plugin: impl Into<String>, params: serde_json::Value) -> Self {
+        Self {
+            plugin: plugin.into(),
+            params,
+            region: None,
+            feather: 0,
+            tile_size: None,
+        }
+    }
+
+    /// Parameter string passed to the plugin.
+    fn params(&self) -> String {
+        match &self.params {
+            serde_json::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    /// Runs the step with an already loaded plugin.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from processing the image.
+    pub fn run(
+        &self,
+        plugin: &dyn ImagePlugin,
+        width: u32,
+        height: u32,
+        rgba_data: &mut [u8],
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        let params = self.params();
+        match (self.region, self.tile_size) {
+            (Some(region), _) => process_region(
+                plugin,
+                width,
+                height,
+                rgba_data,
+                &params,
+                aux,
+                region,
+                self.feather,
+            ),
+            (None, Some(tile_size)) => process_tiled(
+                plugin, width, height, rgba_data, &params, aux, tile_size,
+            ),
+            (None, None) => plugin
+                .process_image_with_aux(width, height, rgba_data, &params, aux),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(json: &str) -> Step {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn params_are_passed_as_json_or_verbatim() {
+        assert_eq!(step(r#"{"plugin": "p"}"#).params(), "{}");
+        assert_eq!(
+            step(r#"{"plugin": "p", "params": {"radius": 2}}"#).params(),
+            r#"{"radius":2}"#
+        );
+        assert_eq!(
+            step(r#"{"plugin": "p", "params": "raw text"}"#).params(),
+            "raw text"
+        );
+    }
+
+    #[test]
+    fn steps_parse_region_and_feather() {
+        let step = step(
+            r#"{"plugin": "blur_plugin",
+                "region": {"x": 1, "y": 2, "width": 3, "height": 4},
+                "feather": 2}"#,
+        );
+        assert_eq!(
+            step.region,
+            Some(Rect {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4
+            })
+        );
+        assert_eq!(step.feather, 2);
+        assert!(step.tile_size.is_none());
+    }
+
+    #[test]
+    fn invalid_pipelines_are_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("pipeline.json");
+
+        for json in [
+            r#"{"steps": [{"plugin": "p", "unknown": 1}]}"#,
+            r#"{"steps": [{"plugin": "p", "tile_size": 0}]}"#,
+            r#"{"steps": [{"plugin": "p@not a version"}]}"#,
+            r#"{"steps": [{"plugin": "p", "tile_size": 8,
+                "region": {"x": 0, "y": 0, "width": 1, "height": 1}}]}"#,
+        ] {
+            fs::write(&path, json).unwrap();
+            assert!(
+                matches!(
+                    Pipeline::load(&path),
+                    Err(AppError::InvalidPipeline { .. })
+                ),
+                "{json}"
+            );
+            assert!(
+                matches!(
+                    Pipeline::from_json(json),
+                    Err(AppError::InvalidPipelineJson { .. })
+                ),
+                "{json}"
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::AppError;
+use crate::image_view::ImageViewMut;
+use crate::plugin_loader::AuxInput;
+
+/// An image processing plugin, wherever its code comes from:
+/// a dynamically loaded library or WebAssembly module
+/// ([`PluginLoader`]), a plugin linked into the host
+/// ([`BuiltinPlugin`]), or a test double.
+///
+/// [`PluginLoader`]: crate::plugin_loader::PluginLoader
+/// [`BuiltinPlugin`]: crate::builtin::BuiltinPlugin
+pub trait ImagePlugin: Send + Sync {
+    /// Returns `true` if the plugin accepts auxiliary inputs.
+    fn supports_aux_inputs(&self) -> bool;
+
+    /// Returns `true` if the plugin may process several images
+    /// at once from different threads; callers serialize calls
+    /// to plugins that are not.
+    fn thread_safe(&self) -> bool {
+        false
+    }
+
+    /// Asks the plugin whether it is tile-safe for `params`.
+    ///
+    /// Returns `Some(halo)` — the pixels of context each tile
+    /// needs — if it is, or `None` if it is not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::PluginExec` (or `PluginPanicked`) if
+    /// the plugin rejects `params`.
+    fn tile_halo(&self, params: &str) -> Result<Option<u32>, AppError>;
+
+    /// Processes a (possibly strided) image view in place, e.g.
+    /// a region of a larger image, with optional auxiliary
+    /// inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::AuxInputsUnsupported` if `aux` is
+    /// non-empty but the plugin does not accept auxiliary
+    /// inputs, `AppError::InvalidAuxInput` if an input's buffer
+    /// does not match its dimensions,
+    /// `AppError::PluginPanicked` if the plugin panicked, and
+    /// `AppError::PluginExec` for any other plugin error.
+    fn process_view(
+        &self,
+        image: ImageViewMut<'_>,
+        params: &str,
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError>;
+
+    /// Processes a packed `width x height` RGBA image in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidImageView` if `rgba_data` is
+    /// not exactly `width * height * 4` bytes (checked before
+    /// the plugin is called), or any error from
+    /// [`Self::process_view`].
+    fn process_image(
+        &self,
+        width: u32,
+        height: u32,
+        rgba_data: &mut [u8],
+        params: &str,
+    ) -> Result<(), AppError> {
+        let image = ImageViewMut::new(rgba_data, width, height)?;
+        self.process_view(image, params, &[])
+    }
+
+    /// Processes a packed image with auxiliary inputs (e.g. a
+    /// mask).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::process_image`].
+    fn process_image_with_aux(
+        &self,
+        width: u32,
+        height: u32,
+        rgba_data: &mut [u8],
+        params: &str,
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        let image = ImageViewMut::new(rgba_data, width, height)?;
+        self.process_view(image, params, aux)
+    }
+}
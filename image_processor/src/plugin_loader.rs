@@ -1,9 +1,25 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString, OsStr};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use libloading::{Library, Symbol};
+use plugin_sdk::ffi::{
+    self, AuxImage, ImageDesc, PIXEL_FORMAT_RGBA8, PIXEL_FORMATS_RGBA8,
+    PLUGIN_FLAG_CHANGES_DIMENSIONS, PLUGIN_FLAG_THREAD_SAFE, PluginDescriptor,
+    TileInfo,
+};
+use plugin_sdk::{BYTES_PER_PIXEL, PluginError};
+use semver::Version;
+use tempfile::TempDir;
 
 use crate::error::AppError;
+use crate::image_view::ImageViewMut;
+use crate::manifest::{Descriptor, PluginManifest};
+use crate::plugin::ImagePlugin;
+use crate::resolver::{PluginSpec, resolve};
+use crate::trust::TrustStore;
+use crate::wasm_plugin::{WasmLimits, WasmPlugin};
 
 /// Plugin function type matching the C signature:
 /// `int process_image(uint32_t width, uint32_t height,
@@ -16,39 +32,321 @@ type ProcessImageFn = unsafe extern "C" fn(
     *const std::ffi::c_char,
 ) -> std::ffi::c_int;
 
-/// Plugin loader — wraps a dynamic library and provides
-/// a safe interface for calling `process_image`.
+/// Optional plugin function accepting auxiliary inputs:
+/// `int process_image_with_aux(uint32_t width, uint32_t height,
+///                             uint8_t* rgba_data, const char* params,
+///                             const AuxInput* aux, size_t aux_count)`
+type ProcessImageWithAuxFn = unsafe extern "C" fn(
+    u32,
+    u32,
+    *mut u8,
+    *const std::ffi::c_char,
+    *const ffi::AuxInput,
+    usize,
+) -> std::ffi::c_int;
+
+/// Optional plugin function taking image descriptors, preferred
+/// when exported:
+/// `int process_image_desc(const ImageDesc* image, const char* params,
+///                         const AuxImage* aux, size_t aux_count)`
+type ProcessImageDescFn = unsafe extern "C" fn(
+    *const ImageDesc,
+    *const std::ffi::c_char,
+    *const AuxImage,
+    usize,
+) -> std::ffi::c_int;
+
+/// Optional plugin function reporting tiling support:
+/// `int plugin_tile_info(const char* params, TileInfo* info)`
+type TileInfoFn = unsafe extern "C" fn(
+    *const std::ffi::c_char,
+    *mut TileInfo,
+) -> std::ffi::c_int;
+
+/// Optional plugin function returning the message of the last
+/// failed call on the current thread, or null:
+/// `const char* plugin_last_error(void)`
+type LastErrorFn = unsafe extern "C" fn() -> *const std::ffi::c_char;
+
+/// Optional plugin function returning the plugin's static
+/// descriptor:
+/// `const PluginDescriptor* plugin_descriptor(void)`
+type DescriptorFn = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+/// Named secondary RGBA image passed to a plugin alongside
+/// the main buffer, e.g. a blur mask.
+#[derive(Debug, Clone, Copy)]
+pub struct AuxInput<'a> {
+    /// Name the plugin refers to in its parameters.
+    pub name: &'a str,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// RGBA pixel buffer (length = width * height * 4).
+    pub rgba_data: &'a [u8],
+}
+
+/// Plugin loader — wraps a dynamic library or a WebAssembly
+/// module and provides a safe [`ImagePlugin`] interface for
+/// calling `process_image`.
 pub struct PluginLoader {
+    backend: Backend,
+    manifest: Option<PluginManifest>,
+}
+
+/// Where a loaded plugin's code runs.
+enum Backend {
+    /// Native library, loaded into the host process.
+    Native(NativePlugin),
+    /// WebAssembly module, run in a sandbox.
+    Wasm(WasmPlugin),
+}
+
+/// Entry points of a native plugin library.
+struct NativePlugin {
     _library: Library,
+    /// Directory holding the copy `_library` was loaded from,
+    /// if any; declared after it so it is removed only once the
+    /// library is unloaded.
+    _copy_dir: Option<TempDir>,
     process_fn: ProcessImageFn,
+    process_with_aux_fn: Option<ProcessImageWithAuxFn>,
+    process_desc_fn: Option<ProcessImageDescFn>,
+    tile_info_fn: Option<TileInfoFn>,
+    descriptor_fn: Option<DescriptorFn>,
+    last_error_fn: Option<LastErrorFn>,
+}
+
+/// Settings for [`PluginLoader::load_with`].
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Limits for every call into a WebAssembly plugin.
+    pub wasm_limits: WasmLimits,
+    /// Store plugin files must pass before they are loaded.
+    pub trust_store: Option<Arc<TrustStore>>,
+    /// Load native libraries from a private temporary copy, so a
+    /// rebuilt library at the same path is really loaded again
+    /// (the dynamic loader reuses a library still mapped from
+    /// the same path). Libraries are always copied when a trust
+    /// store is set.
+    pub copy_libraries: bool,
 }
 
 impl PluginLoader {
-    /// Loads a plugin by name from the specified directory.
+    /// Loads a plugin by name from the specified directory,
+    /// with the default [`WasmLimits`].
     ///
-    /// Constructs a platform-specific library filename:
+    /// A name without an extension refers to a native library
+    /// with a platform-specific filename:
     /// - Linux: `lib{name}.so`
     /// - Windows: `{name}.dll`
     /// - macOS: `lib{name}.dylib`
     ///
+    /// A name with an extension is used as the filename; `.wasm`
+    /// (or `.wat`) files are loaded as WebAssembly plugins, and
+    /// anything else as a native library.
+    ///
+    /// If `{plugin_dir}/{name}/plugin.toml` or
+    /// `{plugin_dir}/{name}/{version}/plugin.toml` exists, the
+    /// plugin is loaded from the directory of the highest such
+    /// version instead: the manifest names the library (by
+    /// default the platform filename above), and a native
+    /// library's `plugin_descriptor` must agree with it.
+    ///
+    /// `name@requirement` (e.g. `blur_plugin@^1.2`) only accepts
+    /// versions matching the semver requirement; a library
+    /// without a manifest must report a matching version in its
+    /// descriptor.
+    ///
     /// # Errors
     ///
     /// Returns `AppError::PluginLoad` if the library file
-    /// cannot be loaded, or `AppError::SymbolLoad` if the
-    /// `process_image` symbol is not found.
+    /// cannot be loaded, `AppError::SymbolLoad` if the
+    /// `process_image` symbol is not found,
+    /// `AppError::WasmLoad` if a WebAssembly module cannot be
+    /// loaded, `AppError::InvalidManifest` or
+    /// `AppError::IncompatiblePlugin` for an unusable manifest,
+    /// `AppError::ManifestMismatch` if the library's descriptor
+    /// contradicts its manifest, `AppError::InvalidPluginSpec`
+    /// for an invalid requirement, and
+    /// `AppError::PluginVersionNotFound` or
+    /// `AppError::PluginVersionConflict` if the installed
+    /// versions do not resolve to exactly one plugin.
     pub fn load(
         plugin_name: &str,
         plugin_dir: &Path,
     ) -> Result<Self, AppError> {
-        let lib_path = library_path(plugin_name, plugin_dir);
+        Self::load_with(plugin_name, plugin_dir, &LoadOptions::default())
+    }
+
+    /// Loads a plugin like [`Self::load`] with `options`: the
+    /// file is checked against the trust store, if any, before
+    /// any of its code is loaded, WebAssembly plugins get the
+    /// given limits, and native libraries are copied first if
+    /// [`LoadOptions::copy_libraries`] is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::UntrustedPlugin` if the trust store
+    /// rejects the file, `AppError::Io` if the library cannot be
+    /// copied, or any error from [`Self::load`].
+    pub fn load_with(
+        plugin_name: &str,
+        plugin_dir: &Path,
+        options: &LoadOptions,
+    ) -> Result<Self, AppError> {
+        let spec: PluginSpec = plugin_name.parse()?;
+        let installed = if Path::new(&spec.name).extension().is_none() {
+            resolve(&spec, plugin_dir)?
+        } else {
+            None
+        };
+        let path = installed.as_ref().map_or_else(
+            || Ok(plugin_path(&spec.name, plugin_dir)),
+            |installed| {
+                let default_file = library_filename(&spec.name, Os::current());
+                installed
+                    .manifest
+                    .library_path(&installed.dir, &default_file)
+            },
+        )?;
+        let (manifest, manifest_text) = installed
+            .map(|installed| (installed.manifest, installed.manifest_text))
+            .unzip();
+        if let Some(manifest) = &manifest {
+            manifest.check_supported(&path)?;
+        }
+
+        let source = PluginSource::fetch(
+            plugin_dir,
+            &path,
+            manifest_text.as_deref().map(str::as_bytes),
+            options,
+        )?;
+
+        log::info!("Loading plugin: {}", path.display());
+
+        let backend = source.load(&path, &options.wasm_limits)?;
+
+        if let Some(manifest) = &manifest {
+            match &backend {
+                Backend::Native(plugin) => match plugin.descriptor() {
+                    Some(descriptor) => {
+                        manifest.check_descriptor(&path, &descriptor)?;
+                    }
+                    None => log::warn!(
+                        "Plugin exports no descriptor, manifest not checked: {}",
+                        path.display()
+                    ),
+                },
+                Backend::Wasm(_) => log::debug!(
+                    "Manifest of WebAssembly plugin not cross-checked: {}",
+                    path.display()
+                ),
+            }
+        }
+
+        if manifest.is_none() && spec.requirement.is_some() {
+            check_embedded_version(&spec, &backend)?;
+        }
+
+        Ok(Self { backend, manifest })
+    }
+
+    /// Manifest the plugin was loaded with, if it has one.
+    pub const fn manifest(&self) -> Option<&PluginManifest> {
+        self.manifest.as_ref()
+    }
+}
+
+impl ImagePlugin for PluginLoader {
+    /// Returns `true` if the plugin exports
+    /// `process_image_with_aux` or `process_image_desc`.
+    fn supports_aux_inputs(&self) -> bool {
+        match &self.backend {
+            Backend::Native(plugin) => plugin.supports_aux_inputs(),
+            Backend::Wasm(plugin) => plugin.supports_aux_inputs(),
+        }
+    }
+
+    /// Native plugins are thread-safe if their manifest, or
+    /// else their descriptor, says so. Every call into a
+    /// WebAssembly plugin gets its own instance.
+    fn thread_safe(&self) -> bool {
+        match &self.backend {
+            Backend::Native(plugin) => self.manifest.as_ref().map_or_else(
+                || plugin.descriptor().is_some_and(|d| d.thread_safe),
+                |manifest| manifest.thread_safe,
+            ),
+            Backend::Wasm(_) => true,
+        }
+    }
+
+    /// Calls `plugin_tile_info`; plugins that do not export it
+    /// are not tile-safe.
+    fn tile_halo(&self, params: &str) -> Result<Option<u32>, AppError> {
+        let params_cstring = CString::new(params).unwrap_or_default();
+        match &self.backend {
+            Backend::Native(plugin) => plugin.tile_halo(&params_cstring),
+            Backend::Wasm(plugin) => plugin.tile_halo(&params_cstring),
+        }
+    }
+
+    /// Native plugins exporting `process_image_desc` receive the
+    /// view directly. Older plugins get the pixels in place if
+    /// the view is packed, or a packed copy that is written back
+    /// after a successful call otherwise. WebAssembly plugins
+    /// always work on a copy in their linear memory, and fail
+    /// with `AppError::Wasm` if they trap or exceed their
+    /// limits.
+    fn process_view(
+        &self,
+        image: ImageViewMut<'_>,
+        params: &str,
+        aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        if !aux.is_empty() && !self.supports_aux_inputs() {
+            return Err(AppError::AuxInputsUnsupported);
+        }
 
-        log::info!("Loading plugin: {}", lib_path.display());
+        let names = aux_names(aux)?;
+        let params_cstring = CString::new(params).unwrap_or_default();
 
+        log::debug!(
+            "Calling plugin: {}x{}, stride {}, {} aux inputs, params={:?}",
+            image.width(),
+            image.height(),
+            image.stride(),
+            aux.len(),
+            params
+        );
+
+        match &self.backend {
+            Backend::Native(plugin) => {
+                plugin.process_view(image, &params_cstring, aux, &names)
+            }
+            Backend::Wasm(plugin) => {
+                plugin.process_view(image, &params_cstring, aux, &names)
+            }
+        }
+    }
+}
+
+impl NativePlugin {
+    /// Loads a native plugin library and resolves its entry
+    /// points; `copy_dir` is the temporary directory `lib_path`
+    /// was copied to, if any.
+    fn load(
+        lib_path: &Path,
+        copy_dir: Option<TempDir>,
+    ) -> Result<Self, AppError> {
         // SAFETY: loading a dynamic library is inherently unsafe as we trust external code.
-        // The library must be compiled from trusted source code.
-        let library = unsafe { Library::new(&lib_path) }.map_err(|source| {
+        // The library must be compiled from trusted source code; use a
+        // trust store (LoadOptions::trust_store) to enforce that.
+        let library = unsafe { Library::new(lib_path) }.map_err(|source| {
             AppError::PluginLoad {
-                path: lib_path.clone(),
+                path: lib_path.to_path_buf(),
                 source,
             }
         })?;
@@ -62,59 +360,349 @@ impl PluginLoader {
             *sym
         };
 
+        // SAFETY: same convention as above; the symbol is optional.
+        let process_with_aux_fn = unsafe {
+            library
+                .get::<ProcessImageWithAuxFn>(b"process_image_with_aux")
+                .ok()
+                .map(|sym| *sym)
+        };
+
+        // SAFETY: same convention as above; the symbol is optional.
+        let process_desc_fn = unsafe {
+            library
+                .get::<ProcessImageDescFn>(b"process_image_desc")
+                .ok()
+                .map(|sym| *sym)
+        };
+
+        // SAFETY: same convention as above; the symbol is optional.
+        let tile_info_fn = unsafe {
+            library
+                .get::<TileInfoFn>(b"plugin_tile_info")
+                .ok()
+                .map(|sym| *sym)
+        };
+
+        // SAFETY: same convention as above; the symbol is optional.
+        let descriptor_fn = unsafe {
+            library
+                .get::<DescriptorFn>(b"plugin_descriptor")
+                .ok()
+                .map(|sym| *sym)
+        };
+
+        // SAFETY: same convention as above; the symbol is optional.
+        let last_error_fn = unsafe {
+            library
+                .get::<LastErrorFn>(b"plugin_last_error")
+                .ok()
+                .map(|sym| *sym)
+        };
+
         Ok(Self {
             _library: library,
+            _copy_dir: copy_dir,
             process_fn,
+            process_with_aux_fn,
+            process_desc_fn,
+            tile_info_fn,
+            descriptor_fn,
+            last_error_fn,
         })
     }
 
-    /// Calls the plugin function to process an image.
-    ///
-    /// # Arguments
-    /// - `width`, `height` — image dimensions in pixels
-    /// - `rgba_data` — mutable RGBA pixel buffer
-    ///   (length = width * height * 4)
-    /// - `params` — parameter string for the plugin
-    ///
-    /// # Errors
+    /// Returns `true` if the library exports an entry point
+    /// taking auxiliary inputs.
+    const fn supports_aux_inputs(&self) -> bool {
+        self.process_with_aux_fn.is_some() || self.process_desc_fn.is_some()
+    }
+
+    /// Reads the library's descriptor, if it exports
+    /// `plugin_descriptor`.
+    fn descriptor(&self) -> Option<Descriptor> {
+        let descriptor_fn = self.descriptor_fn?;
+
+        // SAFETY: plugin_descriptor takes no arguments and returns
+        // a pointer to a static descriptor (or null).
+        let ptr = unsafe { descriptor_fn() };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: a non-null descriptor and its strings are static
+        // data of the library, which stays loaded while self lives.
+        let (raw, name, version) = unsafe {
+            let raw = &*ptr;
+            let string = |ptr: *const std::ffi::c_char| {
+                if ptr.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+                }
+            };
+            (raw, string(raw.name), string(raw.version))
+        };
+
+        Some(Descriptor {
+            abi_version: raw.abi_version,
+            name,
+            version,
+            rgba8: raw.pixel_formats & PIXEL_FORMATS_RGBA8 != 0,
+            thread_safe: raw.flags & PLUGIN_FLAG_THREAD_SAFE != 0,
+            changes_dimensions: raw.flags & PLUGIN_FLAG_CHANGES_DIMENSIONS != 0,
+        })
+    }
+
+    /// Calls `plugin_tile_info`, if exported.
+    fn tile_halo(&self, params: &CStr) -> Result<Option<u32>, AppError> {
+        let Some(tile_info_fn) = self.tile_info_fn else {
+            return Ok(None);
+        };
+
+        let mut info = TileInfo::default();
+
+        // SAFETY: params is a valid C string and info is a live,
+        // writable TileInfo for the entire call.
+        let code = unsafe { tile_info_fn(params.as_ptr(), &raw mut info) };
+        check(code, || self.last_error())?;
+
+        Ok(info.tile_safe.then_some(info.halo))
+    }
+
+    /// Calls the best available entry point on `image`; see
+    /// [`PluginLoader::process_view`].
+    fn process_view(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: &CStr,
+        aux: &[AuxInput<'_>],
+        names: &[CString],
+    ) -> Result<(), AppError> {
+        let code = if let Some(process_fn) = self.process_desc_fn {
+            let (desc, raw) = image_descs(&mut image, aux, names);
+
+            // SAFETY: the descriptor covers exactly the view's
+            // buffer, which stays alive and exclusively borrowed
+            // for the entire call; aux buffers are only read and
+            // their sizes were verified by the caller.
+            unsafe {
+                process_fn(
+                    &raw const desc,
+                    params.as_ptr(),
+                    raw.as_ptr(),
+                    raw.len(),
+                )
+            }
+        } else if image.is_packed() {
+            let (width, height) = (image.width(), image.height());
+            self.call_legacy(
+                width,
+                height,
+                image.as_mut_slice(),
+                params,
+                aux,
+                names,
+            )?
+        } else {
+            let mut packed: Vec<u8> = image.rows().flatten().copied().collect();
+            let code = self.call_legacy(
+                image.width(),
+                image.height(),
+                &mut packed,
+                params,
+                aux,
+                names,
+            )?;
+            if code == 0 {
+                let row_len = image.width() as usize * 4;
+                for (dst, src) in image.rows_mut().zip(packed.chunks(row_len)) {
+                    dst.copy_from_slice(src);
+                }
+            }
+            code
+        };
+
+        check(code, || self.last_error())
+    }
+
+    /// Calls `process_image`, or `process_image_with_aux` when
+    /// there are auxiliary inputs, on a packed buffer.
     ///
-    /// Returns `AppError::PluginExec` if the plugin returns
-    /// a non-zero error code.
-    pub fn process_image(
+    /// Returns the plugin's return code, or
+    /// `AppError::AuxInputsUnsupported` if there are auxiliary
+    /// inputs but no `process_image_with_aux`.
+    fn call_legacy(
         &self,
         width: u32,
         height: u32,
         rgba_data: &mut [u8],
-        params: &str,
-    ) -> Result<(), AppError> {
-        let params_cstring = CString::new(params).unwrap_or_default();
+        params: &CStr,
+        aux: &[AuxInput<'_>],
+        names: &[CString],
+    ) -> Result<std::ffi::c_int, AppError> {
+        if aux.is_empty() {
+            // SAFETY: we pass a valid pointer to image data and a C string for parameters.
+            // The rgba_data buffer remains alive for the entire call.
+            // Buffer size = width * height * 4 bytes (checked by the caller).
+            return Ok(unsafe {
+                (self.process_fn)(
+                    width,
+                    height,
+                    rgba_data.as_mut_ptr(),
+                    params.as_ptr(),
+                )
+            });
+        }
 
-        log::debug!(
-            "Calling plugin: {}x{}, {} bytes, params={:?}",
-            width,
-            height,
-            rgba_data.len(),
-            params
-        );
+        let Some(process_fn) = self.process_with_aux_fn else {
+            return Err(AppError::AuxInputsUnsupported);
+        };
+
+        let raw: Vec<ffi::AuxInput> = aux
+            .iter()
+            .zip(names)
+            .map(|(input, name)| ffi::AuxInput {
+                name: name.as_ptr(),
+                width: input.width,
+                height: input.height,
+                rgba_data: input.rgba_data.as_ptr(),
+            })
+            .collect();
 
-        // SAFETY: we pass a valid pointer to image data and a C string for parameters.
-        // The rgba_data buffer remains alive for the entire call.
-        // Buffer size = width * height * 4 bytes.
-        let code = unsafe {
-            (self.process_fn)(
+        // SAFETY: all pointers reference buffers that stay alive
+        // for the entire call; aux buffer sizes were verified by
+        // the caller.
+        Ok(unsafe {
+            process_fn(
                 width,
                 height,
                 rgba_data.as_mut_ptr(),
-                params_cstring.as_ptr(),
+                params.as_ptr(),
+                raw.as_ptr(),
+                raw.len(),
             )
-        };
+        })
+    }
 
-        if code == 0 {
-            Ok(())
-        } else {
-            Err(AppError::PluginExec { code })
+    /// Reads the plugin's last error message, if it exports
+    /// `plugin_last_error` and has one.
+    fn last_error(&self) -> Option<String> {
+        let last_error_fn = self.last_error_fn?;
+
+        // SAFETY: the symbol follows the plugin API convention;
+        // it is called on the same thread as the failed call.
+        let ptr = unsafe { last_error_fn() };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: a non-null pointer refers to a null-terminated
+        // string that stays valid until the next plugin call.
+        let message = unsafe { CStr::from_ptr(ptr) };
+        Some(message.to_string_lossy().into_owned())
+    }
+}
+
+/// Maps a plugin return code to a result, fetching the message
+/// of a caught panic or reported error with `last_error`.
+pub(crate) fn check(
+    code: std::ffi::c_int,
+    last_error: impl FnOnce() -> Option<String>,
+) -> Result<(), AppError> {
+    match code {
+        0 => Ok(()),
+        code if code == PluginError::Panicked.code() => {
+            Err(AppError::PluginPanicked {
+                message: last_error()
+                    .unwrap_or_else(|| "no message available".to_owned()),
+            })
+        }
+        code => Err(last_error()
+            .map_or(AppError::PluginExec { code }, |message| {
+                AppError::PluginFailed { code, message }
+            })),
+    }
+}
+
+/// Checks auxiliary input buffer sizes and converts their names
+/// to C strings.
+pub(crate) fn aux_names(
+    aux: &[AuxInput<'_>],
+) -> Result<Vec<CString>, AppError> {
+    aux.iter()
+        .map(|input| {
+            let invalid = || AppError::InvalidAuxInput {
+                name: input.name.to_owned(),
+            };
+            let expected = u64::from(input.width) * u64::from(input.height) * 4;
+            if u64::try_from(input.rgba_data.len()).ok() != Some(expected) {
+                return Err(invalid());
+            }
+            CString::new(input.name).map_err(|_| invalid())
+        })
+        .collect()
+}
+
+/// C descriptors of `image` and of the auxiliary inputs `aux`,
+/// named `names`, for `process_image_desc`.
+///
+/// They point into the buffers, which must stay alive, and
+/// `image` exclusively borrowed, while the plugin runs.
+pub(crate) fn image_descs(
+    image: &mut ImageViewMut<'_>,
+    aux: &[AuxInput<'_>],
+    names: &[CString],
+) -> (ImageDesc, Vec<AuxImage>) {
+    let (width, height, stride) =
+        (image.width(), image.height(), image.stride());
+    let data = image.as_mut_slice();
+    let desc = ImageDesc {
+        data: data.as_mut_ptr(),
+        len: data.len(),
+        width,
+        height,
+        stride_bytes: stride,
+        format: PIXEL_FORMAT_RGBA8,
+    };
+    let aux = aux
+        .iter()
+        .zip(names)
+        .map(|(input, name)| AuxImage {
+            name: name.as_ptr(),
+            image: ImageDesc {
+                data: input.rgba_data.as_ptr().cast_mut(),
+                len: input.rgba_data.len(),
+                width: input.width,
+                height: input.height,
+                stride_bytes: input.width as usize * BYTES_PER_PIXEL,
+                format: PIXEL_FORMAT_RGBA8,
+            },
+        })
+        .collect();
+    (desc, aux)
+}
+
+/// Checks that every auxiliary input has the size of a
+/// `width x height` image, as required to crop them along with
+/// it.
+pub(crate) fn check_aux_sizes(
+    aux: &[AuxInput<'_>],
+    width: u32,
+    height: u32,
+) -> Result<(), AppError> {
+    let expected = u64::from(width) * u64::from(height) * 4;
+    for input in aux {
+        let same_size = input.width == width
+            && input.height == height
+            && u64::try_from(input.rgba_data.len()).ok() == Some(expected);
+        if !same_size {
+            return Err(AppError::InvalidAuxInput {
+                name: input.name.to_owned(),
+            });
         }
     }
+    Ok(())
 }
 
 /// Target operating system for library name resolution.
@@ -154,10 +742,156 @@ fn library_path(name: &str, dir: &Path) -> PathBuf {
     dir.join(library_filename(name, Os::current()))
 }
 
+/// Constructs the full path to a plugin: `name` itself if it
+/// has an extension, or the platform library filename.
+pub(crate) fn plugin_path(name: &str, dir: &Path) -> PathBuf {
+    if Path::new(name).extension().is_some() {
+        dir.join(name)
+    } else {
+        library_path(name, dir)
+    }
+}
+
+/// Plugin code read from disk and checked against the trust
+/// store, if any, ready to load without reading the original
+/// file again.
+enum PluginSource {
+    /// Native library at `path`, a private copy in `copy_dir`
+    /// unless the original is loaded directly.
+    Native {
+        path: PathBuf,
+        copy_dir: Option<TempDir>,
+    },
+    /// WebAssembly module bytes.
+    Wasm(Vec<u8>),
+}
+
+impl PluginSource {
+    /// Reads the plugin at `path` in `plugin_dir` and checks it,
+    /// with `manifest`, the text of the manifest it was
+    /// resolved from, against the trust store.
+    ///
+    /// The bytes that are hashed are the bytes later loaded:
+    /// WebAssembly modules are compiled from them, and native
+    /// libraries are written to a private temporary directory
+    /// and loaded from there. A file replaced after the check
+    /// is therefore never loaded. Without a trust store or
+    /// [`LoadOptions::copy_libraries`], native libraries are
+    /// loaded from `path` directly.
+    fn fetch(
+        plugin_dir: &Path,
+        path: &Path,
+        manifest: Option<&[u8]>,
+        options: &LoadOptions,
+    ) -> Result<Self, AppError> {
+        let wasm = is_wasm(path);
+        if !wasm && !options.copy_libraries && options.trust_store.is_none() {
+            return Ok(Self::Native {
+                path: path.to_path_buf(),
+                copy_dir: None,
+            });
+        }
+
+        let contents = fs::read(path).map_err(|source| AppError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if let Some(trust_store) = &options.trust_store {
+            trust_store
+                .verify_contents(plugin_dir, path, &contents, manifest)?;
+        }
+        if wasm {
+            return Ok(Self::Wasm(contents));
+        }
+
+        let copy_dir = private_copy(path, &contents)?;
+        Ok(Self::Native {
+            path: copy_dir.path().join(file_name(path)),
+            copy_dir: Some(copy_dir),
+        })
+    }
+
+    /// Loads the plugin; `path` is the original file, used in
+    /// messages.
+    fn load(
+        self,
+        path: &Path,
+        limits: &WasmLimits,
+    ) -> Result<Backend, AppError> {
+        match self {
+            Self::Native { path, copy_dir } => {
+                NativePlugin::load(&path, copy_dir).map(Backend::Native)
+            }
+            Self::Wasm(bytes) => {
+                WasmPlugin::load(path, &bytes, limits).map(Backend::Wasm)
+            }
+        }
+    }
+}
+
+/// Writes `contents` of the library at `path` into a new
+/// temporary directory, keeping its filename.
+fn private_copy(path: &Path, contents: &[u8]) -> Result<TempDir, AppError> {
+    let io_error = |source| AppError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let dir = tempfile::Builder::new()
+        .prefix("image_processor-plugin-")
+        .tempdir()
+        .map_err(io_error)?;
+    let copy = dir.path().join(file_name(path));
+    log::debug!("Copying plugin to {}", copy.display());
+    fs::write(&copy, contents).map_err(io_error)?;
+    Ok(dir)
+}
+
+/// Filename of `path`, or `plugin` if it has none.
+fn file_name(path: &Path) -> &OsStr {
+    path.file_name().unwrap_or_else(|| OsStr::new("plugin"))
+}
+
+/// Checks the version a plugin without a manifest reports in
+/// its descriptor against `spec`; plugins that report none
+/// cannot satisfy a version requirement.
+fn check_embedded_version(
+    spec: &PluginSpec,
+    backend: &Backend,
+) -> Result<(), AppError> {
+    let version = match backend {
+        Backend::Native(plugin) => {
+            plugin.descriptor().map(|descriptor| descriptor.version)
+        }
+        Backend::Wasm(_) => None,
+    };
+    let matches = version
+        .as_deref()
+        .and_then(|version| Version::parse(version).ok())
+        .is_some_and(|version| spec.matches(&version));
+    if matches {
+        return Ok(());
+    }
+
+    Err(spec.not_found(vec![
+        version.unwrap_or_else(|| "unknown (no plugin_descriptor)".to_owned()),
+    ]))
+}
+
+/// Returns `true` if `path` names a WebAssembly module (binary
+/// `.wasm` or text `.wat`).
+fn is_wasm(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        ext.eq_ignore_ascii_case("wasm") || ext.eq_ignore_ascii_case("wat")
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::manifest::testing::{minimal_manifest, write_manifest};
+    use crate::trust::sign_plugin;
     use rstest::rstest;
+    use sha2::{Digest, Sha256};
 
     #[rstest]
     #[case(Os::Linux, "invert", "libinvert.so")]
@@ -182,6 +916,164 @@ mod tests {
         assert_eq!(path, expected);
     }
 
+    #[rstest]
+    #[case("mirror_plugin.wasm", true)]
+    #[case("invert.WAT", true)]
+    #[case("libmirror_plugin.so", false)]
+    fn plugin_path_uses_explicit_filename(
+        #[case] name: &str,
+        #[case] wasm: bool,
+    ) {
+        let path = plugin_path(name, Path::new("plugins"));
+        assert_eq!(path, Path::new("plugins").join(name));
+        assert_eq!(is_wasm(&path), wasm);
+    }
+
+    #[test]
+    fn plugin_path_without_extension_is_native_library() {
+        let path = plugin_path("invert", Path::new("target/debug"));
+        assert_eq!(path, library_path("invert", Path::new("target/debug")));
+        assert!(!is_wasm(&path));
+    }
+
+    #[test]
+    fn check_includes_reported_messages() {
+        assert!(check(0, || None).is_ok());
+        assert!(matches!(
+            check(4, || None),
+            Err(AppError::PluginExec { code: 4 })
+        ));
+        assert!(matches!(
+            check(8, || Some("script failed".to_owned())),
+            Err(AppError::PluginFailed { code: 8, message })
+                if message == "script failed"
+        ));
+        assert!(matches!(
+            check(PluginError::Panicked.code(), || None),
+            Err(AppError::PluginPanicked { .. })
+        ));
+    }
+
+    /// WebAssembly module whose `process_image` returns `code`.
+    fn wat_module(code: i32) -> String {
+        format!(
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "plugin_alloc") (param i32) (result i32)
+                   (i32.const 1024))
+                 (func (export "process_image")
+                   (param i32 i32 i32 i32) (result i32)
+                   (i32.const {code})))"#
+        )
+    }
+
+    /// Options with a trust store listing `plugin` as it is now.
+    fn trusting(dir: &Path, plugin: &Path) -> LoadOptions {
+        let digest = hex::encode(Sha256::digest(fs::read(plugin).unwrap()));
+        let store = serde_json::json!({"plugins": [{
+            "file": plugin.file_name().unwrap().to_str().unwrap(),
+            "sha256": digest,
+        }]});
+        let path = dir.join("trust.json");
+        fs::write(&path, store.to_string()).unwrap();
+        LoadOptions {
+            trust_store: Some(Arc::new(TrustStore::load(&path).unwrap())),
+            ..LoadOptions::default()
+        }
+    }
+
+    #[test]
+    fn wasm_file_swapped_after_verification_is_not_loaded() {
+        let dir = TempDir::new().unwrap();
+        let plugin = dir.path().join("plugin.wat");
+        fs::write(&plugin, wat_module(0)).unwrap();
+        let options = trusting(dir.path(), &plugin);
+
+        let source =
+            PluginSource::fetch(dir.path(), &plugin, None, &options).unwrap();
+        fs::write(&plugin, wat_module(4)).unwrap();
+        let backend = source.load(&plugin, &WasmLimits::default()).unwrap();
+        let loaded = PluginLoader {
+            backend,
+            manifest: None,
+        };
+        loaded.process_image(1, 1, &mut [0; 4], "{}").unwrap();
+
+        // Swapped before the check: rejected.
+        let err = PluginSource::fetch(dir.path(), &plugin, None, &options)
+            .err()
+            .unwrap();
+        assert!(matches!(err, AppError::UntrustedPlugin { .. }), "{err}");
+    }
+
+    #[test]
+    fn trusted_versions_of_one_plugin_load_separately() {
+        let dir = TempDir::new().unwrap();
+        let key = dir.path().join("signing.key");
+        fs::write(&key, hex::encode([7; 32])).unwrap();
+        let store = dir.path().join("trust.json");
+        for (version, code) in [("1.0.0", 0), ("2.0.0", 4)] {
+            let manifest = format!(
+                "{}library = \"plugin.wat\"\n",
+                minimal_manifest("fake", version)
+            );
+            write_manifest(dir.path(), &format!("fake/{version}"), &manifest);
+            let plugin =
+                dir.path().join("fake").join(version).join("plugin.wat");
+            fs::write(&plugin, wat_module(code)).unwrap();
+            sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        }
+        let options = LoadOptions {
+            trust_store: Some(Arc::new(TrustStore::load(&store).unwrap())),
+            ..LoadOptions::default()
+        };
+        let load = |spec| PluginLoader::load_with(spec, dir.path(), &options);
+
+        let old = load("fake@1").unwrap();
+        old.process_image(1, 1, &mut [0; 4], "{}").unwrap();
+        let new = load("fake@2").unwrap();
+        assert!(matches!(
+            new.process_image(1, 1, &mut [0; 4], "{}"),
+            Err(AppError::PluginExec { code: 4 })
+        ));
+
+        // The manifest is signed along with the module.
+        let manifest = format!(
+            "{}library = \"plugin.wat\"\ndescription = \"swapped\"\n",
+            minimal_manifest("fake", "2.0.0")
+        );
+        write_manifest(dir.path(), "fake/2.0.0", &manifest);
+        let err = load("fake@2").err().unwrap();
+        assert!(matches!(err, AppError::UntrustedPlugin { .. }), "{err}");
+    }
+
+    #[test]
+    #[ignore = "requires built plugin libraries (cargo build --workspace)"]
+    fn library_swapped_after_verification_is_not_loaded() {
+        let dir = TempDir::new().unwrap();
+        let file = library_filename("mirror_plugin", Os::current());
+        let plugin = dir.path().join(&file);
+        let built = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../target/debug")
+            .join(&file);
+        fs::copy(built, &plugin).unwrap();
+        let options = trusting(dir.path(), &plugin);
+
+        let source =
+            PluginSource::fetch(dir.path(), &plugin, None, &options).unwrap();
+        fs::write(&plugin, b"not a library").unwrap();
+        let backend = source.load(&plugin, &WasmLimits::default()).unwrap();
+        let loaded = PluginLoader {
+            backend,
+            manifest: None,
+        };
+        let mut data = [1, 1, 1, 1, 2, 2, 2, 2];
+        loaded
+            .process_image(2, 1, &mut data, r#"{"horizontal": true}"#)
+            .unwrap();
+        assert_eq!(data, [2, 2, 2, 2, 1, 1, 1, 1]);
+    }
+
     #[test]
     fn load_nonexistent_plugin_returns_error() {
         let result = PluginLoader::load(
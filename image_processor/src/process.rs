@@ -0,0 +1,88 @@
+use std::io::Cursor;
+use std::ops::DerefMut;
+
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::pipeline::{Step, run_steps};
+use crate::plugin_loader::AuxInput;
+use crate::registry::PluginRegistry;
+
+/// Decodes an encoded image (PNG, JPEG, ...), returning it as
+/// RGBA along with its format.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageDecode` if the format is not
+/// recognized or the image cannot be decoded.
+pub fn decode(input: &[u8]) -> Result<(RgbaImage, ImageFormat), AppError> {
+    let format = image::guess_format(input)
+        .map_err(|source| AppError::ImageDecode { source })?;
+    let image = image::load_from_memory_with_format(input, format)
+        .map_err(|source| AppError::ImageDecode { source })?;
+    Ok((image.into_rgba8(), format))
+}
+
+/// Encodes an RGBA image in `format`. The alpha channel is
+/// dropped for formats without one, such as JPEG.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageEncode` if the image cannot be
+/// encoded in `format`.
+pub fn encode(
+    image: RgbaImage,
+    format: ImageFormat,
+) -> Result<Vec<u8>, AppError> {
+    let image = if format == ImageFormat::Jpeg {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).into_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(image)
+    };
+    let mut encoded = Cursor::new(Vec::new());
+    image
+        .write_to(&mut encoded, format)
+        .map_err(|source| AppError::ImageEncode { source })?;
+    Ok(encoded.into_inner())
+}
+
+/// Runs `steps` on `image` in place, looking plugins up in
+/// `plugins`. Every step gets the same auxiliary inputs.
+///
+/// `image` may own its pixels or borrow them, e.g. from a
+/// caller's buffer with [`ImageBuffer::from_raw`].
+///
+/// # Errors
+///
+/// Returns the first error from loading or running a step.
+pub fn process_rgba<C: DerefMut<Target = [u8]>>(
+    plugins: &PluginRegistry,
+    image: &mut ImageBuffer<Rgba<u8>, C>,
+    steps: &[Step],
+    aux: &[AuxInput<'_>],
+) -> Result<(), AppError> {
+    let (width, height) = image.dimensions();
+    run_steps(steps, plugins, width, height, image, aux)
+}
+
+/// Decodes `input`, runs `steps` on it and encodes the result
+/// in `output_format`, or in the input format if `None`;
+/// nothing is read from or written to disk besides the plugins
+/// `plugins` loads.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageDecode` or `AppError::ImageEncode`
+/// if the image cannot be decoded or encoded, and any error
+/// from [`process_rgba`].
+pub fn process_bytes(
+    plugins: &PluginRegistry,
+    input: &[u8],
+    steps: &[Step],
+    output_format: Option<ImageFormat>,
+) -> Result<Vec<u8>, AppError> {
+    let (mut image, input_format) = decode(input)?;
+    log::info!("Image size: {}x{}", image.width(), image.height());
+    process_rgba(plugins, &mut image, steps, &[])?;
+    encode(image, output_format.unwrap_or(input_format))
+}
@@ -0,0 +1,192 @@
+use crate::error::AppError;
+use crate::image_view::{ImageViewMut, Rect, crop};
+use crate::plugin::ImagePlugin;
+use crate::plugin_loader::{AuxInput, check_aux_sizes};
+
+/// Runs a plugin on `region` of the image only and composites
+/// the result back into the image.
+///
+/// The plugin sees the region as a whole image: it gets a view
+/// into the buffer (no copy) and auxiliary inputs cropped to
+/// the region, so any in-place plugin works unchanged. With
+/// `feather > 0` the result fades in over `feather` pixels
+/// along the region edges that lie inside the image.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidRegion` if `region` is empty or
+/// not inside the image, `AppError::InvalidImageView` if
+/// `rgba_data` does not match the dimensions,
+/// `AppError::InvalidAuxInput` if an auxiliary input is not
+/// the size of the image, and any error from the plugin.
+#[allow(clippy::too_many_arguments)]
+pub fn process_region(
+    plugin: &dyn ImagePlugin,
+    width: u32,
+    height: u32,
+    rgba_data: &mut [u8],
+    params: &str,
+    aux: &[AuxInput<'_>],
+    region: Rect,
+    feather: u32,
+) -> Result<(), AppError> {
+    if !region.fits_in(width, height) {
+        return Err(AppError::InvalidRegion {
+            region,
+            width,
+            height,
+        });
+    }
+    check_aux_sizes(aux, width, height)?;
+
+    let original = (feather > 0).then(|| crop(rgba_data, width, region));
+    let aux_pixels: Vec<Vec<u8>> = aux
+        .iter()
+        .map(|input| crop(input.rgba_data, width, region))
+        .collect();
+    let region_aux: Vec<AuxInput<'_>> = aux
+        .iter()
+        .zip(&aux_pixels)
+        .map(|(input, data)| AuxInput {
+            name: input.name,
+            width: region.width,
+            height: region.height,
+            rgba_data: data,
+        })
+        .collect();
+
+    let mut image = ImageViewMut::new(rgba_data, width, height)?;
+    let mut view = image
+        .sub_view(region.x, region.y, region.width, region.height)
+        .ok_or(AppError::InvalidRegion {
+            region,
+            width,
+            height,
+        })?;
+
+    log::info!(
+        "Processing region {}x{} at ({}, {})",
+        region.width,
+        region.height,
+        region.x,
+        region.y
+    );
+
+    plugin.process_view(view.reborrow(), params, &region_aux)?;
+
+    if let Some(original) = original {
+        let ramp = Ramp::new(region, width, height, feather);
+        feather_edges(&mut view, &original, &ramp);
+    }
+
+    Ok(())
+}
+
+/// Blend weight of the processed result along feathered edges.
+struct Ramp {
+    width: u32,
+    height: u32,
+    feather: u32,
+    /// Whether the left, top, right and bottom edges lie
+    /// inside the image (and are feathered).
+    inner_edges: [bool; 4],
+}
+
+impl Ramp {
+    const fn new(region: Rect, width: u32, height: u32, feather: u32) -> Self {
+        Self {
+            width: region.width,
+            height: region.height,
+            feather,
+            inner_edges: [
+                region.x > 0,
+                region.y > 0,
+                region.x + region.width < width,
+                region.y + region.height < height,
+            ],
+        }
+    }
+
+    /// Weight of the processed pixel at region coordinates
+    /// `(x, y)` as `numerator / (feather + 1)`.
+    fn weight(&self, x: u32, y: u32) -> u32 {
+        let distances = [x, y, self.width - 1 - x, self.height - 1 - y];
+        let distance = distances
+            .into_iter()
+            .zip(self.inner_edges)
+            .filter_map(|(d, inner)| inner.then_some(d))
+            .min()
+            .unwrap_or(u32::MAX);
+        distance.saturating_add(1).min(self.feather + 1)
+    }
+}
+
+/// Blends the processed `view` with the `original` region
+/// pixels according to `ramp`.
+fn feather_edges(view: &mut ImageViewMut<'_>, original: &[u8], ramp: &Ramp) {
+    let full = ramp.feather + 1;
+    let row_len = ramp.width as usize * 4;
+
+    for ((y, row), original) in
+        (0..).zip(view.rows_mut()).zip(original.chunks(row_len))
+    {
+        for ((x, pixel), original) in
+            (0..).zip(row.chunks_mut(4)).zip(original.chunks(4))
+        {
+            let weight = ramp.weight(x, y);
+            if weight == full {
+                continue;
+            }
+            for (value, &before) in pixel.iter_mut().zip(original) {
+                let mixed = u32::from(*value) * weight
+                    + u32::from(before) * (full - weight)
+                    + full / 2;
+                *value = u8::try_from(mixed / full).unwrap_or(u8::MAX);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn ramp_rises_from_inner_edges() {
+        let ramp = Ramp::new(rect(2, 0, 6, 3), 10, 3, 2);
+        let weights: Vec<u32> = (0..6).map(|x| ramp.weight(x, 1)).collect();
+        assert_eq!(weights, [1, 2, 3, 3, 2, 1]);
+    }
+
+    #[test]
+    fn ramp_skips_edges_on_image_border() {
+        // Region touches the left and top image borders.
+        let ramp = Ramp::new(rect(0, 0, 4, 4), 8, 8, 1);
+        assert_eq!(ramp.weight(0, 0), 2);
+        assert_eq!(ramp.weight(3, 0), 1);
+        assert_eq!(ramp.weight(0, 3), 1);
+
+        let whole = Ramp::new(rect(0, 0, 4, 4), 4, 4, 3);
+        assert_eq!(whole.weight(0, 0), 4);
+    }
+
+    #[test]
+    fn feather_blends_towards_original() {
+        let mut data = vec![200u8; 3 * 4];
+        let original = vec![0u8; 3 * 4];
+        let ramp = Ramp::new(rect(1, 0, 3, 1), 5, 1, 1);
+        let mut view = ImageViewMut::new(&mut data, 3, 1).unwrap();
+        feather_edges(&mut view, &original, &ramp);
+        let reds: Vec<u8> = data.chunks(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(reds, [100, 200, 100]);
+    }
+}
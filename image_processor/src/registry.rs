@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use semver::Version;
+
+use crate::error::AppError;
+use crate::manifest::PluginManifest;
+use crate::plugin::ImagePlugin;
+use crate::plugin_loader::{LoadOptions, PluginLoader, plugin_path};
+use crate::resolver::{PluginSpec, installed_versions};
+use crate::trust::TrustStore;
+use crate::wasm_plugin::WasmLimits;
+
+/// Resolves plugin names to plugins: registered ones first
+/// (e.g. test doubles), then libraries and WebAssembly modules
+/// loaded from a directory, then built-in plugins.
+pub struct PluginRegistry {
+    plugin_dir: PathBuf,
+    options: LoadOptions,
+    plugins: HashMap<String, Arc<dyn ImagePlugin>>,
+    builtins: HashMap<String, Builtin>,
+}
+
+/// Built-in plugin with the version it reports.
+struct Builtin {
+    version: String,
+    plugin: Arc<dyn ImagePlugin>,
+}
+
+impl Builtin {
+    fn matches(&self, spec: &PluginSpec) -> bool {
+        Version::parse(&self.version).is_ok_and(|v| spec.matches(&v))
+    }
+
+    fn use_for(&self, spec: &PluginSpec) -> Arc<dyn ImagePlugin> {
+        log::info!("Using built-in plugin: {} {}", spec.name, self.version);
+        Arc::clone(&self.plugin)
+    }
+}
+
+impl PluginRegistry {
+    /// Creates a registry loading plugins from `plugin_dir`
+    /// with the default [`WasmLimits`].
+    pub fn new(plugin_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            plugin_dir: plugin_dir.into(),
+            options: LoadOptions::default(),
+            plugins: HashMap::new(),
+            builtins: HashMap::new(),
+        }
+    }
+
+    /// Sets the limits for WebAssembly plugins loaded from now
+    /// on.
+    pub const fn set_wasm_limits(&mut self, limits: WasmLimits) {
+        self.options.wasm_limits = limits;
+    }
+
+    /// Loads native libraries from temporary copies from now on
+    /// (see [`LoadOptions::copy_libraries`]).
+    pub const fn set_copy_libraries(&mut self, copy: bool) {
+        self.options.copy_libraries = copy;
+    }
+
+    /// Only loads plugins from the directory that pass
+    /// `trust_store`; registered and built-in plugins are part
+    /// of the host and not checked.
+    pub fn set_trust_store(&mut self, trust_store: TrustStore) {
+        self.options.trust_store = Some(Arc::new(trust_store));
+    }
+
+    /// Directory plugins are loaded from.
+    pub fn plugin_dir(&self) -> &Path {
+        &self.plugin_dir
+    }
+
+    /// Registers `plugin` under `name`, taking precedence over
+    /// any library of that name whatever version is required.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        plugin: impl ImagePlugin + 'static,
+    ) {
+        self.plugins.insert(name.into(), Arc::new(plugin));
+    }
+
+    /// Registers `plugin` as version `version` of the built-in
+    /// plugin `name`, used if the plugin directory has no plugin
+    /// of that name, or none with a required version.
+    pub fn register_builtin(
+        &mut self,
+        name: impl Into<String>,
+        version: impl Into<String>,
+        plugin: impl ImagePlugin + 'static,
+    ) {
+        self.builtins.insert(
+            name.into(),
+            Builtin {
+                version: version.into(),
+                plugin: Arc::new(plugin),
+            },
+        );
+    }
+
+    /// Reads the manifests of the plugins in the plugin
+    /// directory, without loading them (see
+    /// [`PluginManifest::discover`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the directory cannot be read.
+    pub fn manifests(&self) -> Result<Vec<PluginManifest>, AppError> {
+        PluginManifest::discover(&self.plugin_dir)
+    }
+
+    /// Names and versions of the built-in plugins, sorted by
+    /// name.
+    pub fn builtins(&self) -> Vec<(&str, &str)> {
+        let mut builtins: Vec<(&str, &str)> = self
+            .builtins
+            .iter()
+            .map(|(name, builtin)| (name.as_str(), builtin.version.as_str()))
+            .collect();
+        builtins.sort_unstable();
+        builtins
+    }
+
+    /// Returns the plugin registered as `name`, else loads it
+    /// from the plugin directory (see [`PluginLoader::load`]),
+    /// else falls back to the built-in plugin `name`. `name`
+    /// may require versions, as in `blur_plugin@^1.2`; the
+    /// built-in plugin is also used if it matches and no plugin
+    /// in the directory does.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::PluginVersionNotFound` listing the
+    /// versions in the directory and the built-in one if none
+    /// matches, or any other error from
+    /// [`PluginLoader::load_with`].
+    pub fn get(&self, name: &str) -> Result<Arc<dyn ImagePlugin>, AppError> {
+        let spec: PluginSpec = name.parse()?;
+        if let Some(plugin) = self.plugins.get(&spec.name) {
+            log::info!("Using registered plugin: {}", spec.name);
+            return Ok(Arc::clone(plugin));
+        }
+
+        let Some(builtin) = self.builtins.get(&spec.name) else {
+            let loader =
+                PluginLoader::load_with(name, &self.plugin_dir, &self.options)?;
+            return Ok(Arc::new(loader));
+        };
+        if !plugin_path(&spec.name, &self.plugin_dir).exists()
+            && installed_versions(&spec.name, &self.plugin_dir)?.is_empty()
+        {
+            return if builtin.matches(&spec) {
+                Ok(builtin.use_for(&spec))
+            } else {
+                Err(spec
+                    .not_found(vec![format!("{} (built-in)", builtin.version)]))
+            };
+        }
+
+        match PluginLoader::load_with(name, &self.plugin_dir, &self.options) {
+            Ok(loader) => Ok(Arc::new(loader)),
+            Err(AppError::PluginVersionNotFound { .. })
+                if builtin.matches(&spec) =>
+            {
+                Ok(builtin.use_for(&spec))
+            }
+            Err(AppError::PluginVersionNotFound {
+                name,
+                requirement,
+                mut available,
+            }) => {
+                available.push(format!("{} (built-in)", builtin.version));
+                Err(AppError::PluginVersionNotFound {
+                    name,
+                    requirement,
+                    available,
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_view::ImageViewMut;
+    use crate::manifest::testing::{minimal_manifest, write_manifest};
+    use crate::plugin_loader::AuxInput;
+
+    /// Plugin that does nothing.
+    struct Noop;
+
+    impl ImagePlugin for Noop {
+        fn supports_aux_inputs(&self) -> bool {
+            false
+        }
+
+        fn tile_halo(&self, _params: &str) -> Result<Option<u32>, AppError> {
+            Ok(Some(0))
+        }
+
+        fn process_view(
+            &self,
+            _image: ImageViewMut<'_>,
+            _params: &str,
+            _aux: &[AuxInput<'_>],
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registered_plugins_are_found_before_loading() {
+        let mut plugins = PluginRegistry::new("nonexistent_dir");
+        plugins.register("noop", Noop);
+
+        let plugin = plugins.get("noop").unwrap();
+        assert_eq!(plugin.tile_halo("").unwrap(), Some(0));
+        assert!(matches!(
+            plugins.get("other"),
+            Err(AppError::PluginLoad { .. })
+        ));
+    }
+
+    #[test]
+    fn plugins_in_directory_override_builtins() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut plugins = PluginRegistry::new(dir.path());
+        plugins.register_builtin("noop", "1.0.0", Noop);
+        assert!(plugins.get("noop").is_ok());
+
+        // Not a real library, but it takes precedence anyway.
+        std::fs::write(plugin_path("noop", dir.path()), "").unwrap();
+        assert!(matches!(
+            plugins.get("noop"),
+            Err(AppError::PluginLoad { .. })
+        ));
+    }
+
+    #[test]
+    fn builtin_is_used_when_it_matches_required_version() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut plugins = PluginRegistry::new(dir.path());
+        plugins.register_builtin("noop", "1.4.0", Noop);
+        assert!(plugins.get("noop@^1.2").is_ok());
+
+        let err = plugins.get("noop@^2").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "no version of plugin 'noop' matches '^2' \
+             (available: 1.4.0 (built-in))"
+        );
+
+        // An installed version that does not match falls back to
+        // the built-in plugin without loading anything.
+        write_manifest(
+            dir.path(),
+            "noop/2.0.0",
+            &minimal_manifest("noop", "2.0.0"),
+        );
+        assert!(plugins.get("noop@^1.2").is_ok());
+
+        let err = plugins.get("noop@^3").err().unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("(available: 2.0.0, 1.4.0 (built-in))"),
+            "{err}"
+        );
+    }
+}
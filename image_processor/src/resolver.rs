@@ -0,0 +1,362 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use semver::{Version, VersionReq};
+
+use crate::error::AppError;
+use crate::manifest::{MANIFEST_FILE, PluginManifest};
+
+/// Plugin reference as written in `--plugin` and pipeline
+/// steps: a name, optionally followed by `@` and a version
+/// requirement, e.g. `blur_plugin@^1.2` or
+/// `blur_plugin@>=1.2, <2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginSpec {
+    /// Plugin name, or a filename such as `plugin.wasm`.
+    pub name: String,
+    /// Versions the plugin may have; any if `None`.
+    pub requirement: Option<VersionReq>,
+}
+
+impl PluginSpec {
+    /// Returns `true` if `version` satisfies the requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.requirement
+            .as_ref()
+            .is_none_or(|requirement| requirement.matches(version))
+    }
+
+    /// Error for a plugin that has no version matching the
+    /// requirement, listing the `available` versions.
+    pub(crate) fn not_found(&self, available: Vec<String>) -> AppError {
+        AppError::PluginVersionNotFound {
+            name: self.name.clone(),
+            requirement: self
+                .requirement
+                .as_ref()
+                .map_or_else(|| "*".to_owned(), ToString::to_string),
+            available,
+        }
+    }
+}
+
+impl FromStr for PluginSpec {
+    type Err = AppError;
+
+    fn from_str(spec: &str) -> Result<Self, AppError> {
+        let Some((name, requirement)) = spec.split_once('@') else {
+            return Ok(Self {
+                name: spec.to_owned(),
+                requirement: None,
+            });
+        };
+        let invalid = |message: String| AppError::InvalidPluginSpec {
+            spec: spec.to_owned(),
+            message,
+        };
+
+        if name.is_empty() {
+            return Err(invalid("missing plugin name".to_owned()));
+        }
+        if Path::new(name).extension().is_some() {
+            return Err(invalid(
+                "versions can only be required for plugin names, \
+                 not filenames"
+                    .to_owned(),
+            ));
+        }
+        let requirement = VersionReq::parse(requirement.trim())
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            requirement: Some(requirement),
+        })
+    }
+}
+
+impl fmt::Display for PluginSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.requirement {
+            Some(requirement) => write!(f, "{}@{requirement}", self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+/// One installed version of a plugin: a directory holding its
+/// manifest and library.
+#[derive(Debug, Clone)]
+pub struct InstalledPlugin {
+    /// Version from the manifest.
+    pub version: Version,
+    /// Directory of the manifest.
+    pub dir: PathBuf,
+    /// The plugin's manifest.
+    pub manifest: PluginManifest,
+    /// Text the manifest was parsed from, as checked against
+    /// the trust store.
+    pub manifest_text: String,
+}
+
+/// Finds the installed versions of plugin `name` in
+/// `plugin_dir`, sorted from lowest to highest, without loading
+/// any library.
+///
+/// A plugin is installed either as
+/// `<plugin_dir>/<name>/plugin.toml` or, to keep several
+/// versions side by side, as
+/// `<plugin_dir>/<name>/<version>/plugin.toml`.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidManifest` if a manifest is invalid,
+/// names another plugin, has a version that is not semver or
+/// differs from its directory name,
+/// `AppError::PluginVersionConflict` if a version is installed
+/// twice, and `AppError::Io` if a directory cannot be read.
+pub fn installed_versions(
+    name: &str,
+    plugin_dir: &Path,
+) -> Result<Vec<InstalledPlugin>, AppError> {
+    let package_dir = plugin_dir.join(name);
+    let mut installed = Vec::new();
+    if !package_dir.is_dir() {
+        return Ok(installed);
+    }
+
+    installed.extend(read_installed(name, &package_dir, None)?);
+
+    let io_error = |source| AppError::Io {
+        path: package_dir.clone(),
+        source,
+    };
+    for entry in fs::read_dir(&package_dir).map_err(io_error)? {
+        let dir = entry.map_err(io_error)?.path();
+        let Some(version) = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| Version::parse(name).ok())
+        else {
+            continue;
+        };
+        if dir.is_dir() {
+            installed.extend(read_installed(name, &dir, Some(&version))?);
+        }
+    }
+
+    installed.sort_by(|a, b| a.version.cmp(&b.version));
+    if let Some(pair) = installed
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(AppError::PluginVersionConflict {
+            name: name.to_owned(),
+            version: pair[0].version.to_string(),
+            first: pair[0].dir.clone(),
+            second: pair[1].dir.clone(),
+        });
+    }
+    Ok(installed)
+}
+
+/// Reads the manifest in `dir`, if any, as an installed version
+/// of plugin `name`; `dir_version` is the version the directory
+/// is named after.
+fn read_installed(
+    name: &str,
+    dir: &Path,
+    dir_version: Option<&Version>,
+) -> Result<Option<InstalledPlugin>, AppError> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let manifest_text =
+        fs::read_to_string(&path).map_err(|source| AppError::Io {
+            path: path.clone(),
+            source,
+        })?;
+    let manifest = PluginManifest::parse(&path, &manifest_text)?;
+    let invalid = |message: String| AppError::InvalidManifest {
+        path: path.clone(),
+        message,
+    };
+    if manifest.name != name {
+        return Err(invalid(format!(
+            "declares plugin '{}' but is in the directory of '{name}'",
+            manifest.name
+        )));
+    }
+    let version = Version::parse(&manifest.version).map_err(|e| {
+        invalid(format!("invalid version '{}': {e}", manifest.version))
+    })?;
+    if let Some(dir_version) = dir_version
+        && *dir_version != version
+    {
+        return Err(invalid(format!(
+            "declares version {version} but is in the directory of \
+             version {dir_version}"
+        )));
+    }
+
+    Ok(Some(InstalledPlugin {
+        version,
+        dir: dir.to_path_buf(),
+        manifest,
+        manifest_text,
+    }))
+}
+
+/// Chooses the highest installed version of `spec.name` that
+/// satisfies its requirement (see [`installed_versions`]).
+///
+/// Returns `None` if no version is installed; the plugin may
+/// still be a bare library in `plugin_dir`.
+///
+/// # Errors
+///
+/// Returns `AppError::PluginVersionNotFound` listing the
+/// installed versions if none matches, or any error from
+/// [`installed_versions`].
+pub fn resolve(
+    spec: &PluginSpec,
+    plugin_dir: &Path,
+) -> Result<Option<InstalledPlugin>, AppError> {
+    let mut installed = installed_versions(&spec.name, plugin_dir)?;
+    if installed.is_empty() {
+        return Ok(None);
+    }
+
+    match installed
+        .iter()
+        .rposition(|plugin| spec.matches(&plugin.version))
+    {
+        Some(i) => Ok(Some(installed.swap_remove(i))),
+        None => Err(spec.not_found(
+            installed
+                .iter()
+                .map(|plugin| plugin.version.to_string())
+                .collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::testing::{minimal_manifest, write_manifest};
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    fn install(plugin_dir: &Path, dir: &str, name: &str, version: &str) {
+        write_manifest(plugin_dir, dir, &minimal_manifest(name, version));
+    }
+
+    fn spec(spec: &str) -> PluginSpec {
+        spec.parse().unwrap()
+    }
+
+    #[rstest]
+    #[case("blur_plugin", "blur_plugin", None)]
+    #[case("blur_plugin@^1.2", "blur_plugin", Some("^1.2"))]
+    #[case("blur_plugin@>=1.2, <2", "blur_plugin", Some(">=1.2, <2"))]
+    #[case("blur_plugin@ 1.2.3", "blur_plugin", Some("^1.2.3"))]
+    #[case("mirror_plugin.wasm", "mirror_plugin.wasm", None)]
+    fn parses_specs(
+        #[case] text: &str,
+        #[case] name: &str,
+        #[case] requirement: Option<&str>,
+    ) {
+        let spec = spec(text);
+        assert_eq!(spec.name, name);
+        assert_eq!(
+            spec.requirement.map(|r| r.to_string()).as_deref(),
+            requirement
+        );
+    }
+
+    #[rstest]
+    #[case("@^1")]
+    #[case("blur_plugin@")]
+    #[case("blur_plugin@banana")]
+    #[case("mirror_plugin.wasm@^1")]
+    fn rejects_invalid_specs(#[case] text: &str) {
+        assert!(matches!(
+            text.parse::<PluginSpec>(),
+            Err(AppError::InvalidPluginSpec { .. })
+        ));
+    }
+
+    #[test]
+    fn picks_highest_matching_version() {
+        let dir = TempDir::new().unwrap();
+        install(dir.path(), "blur_plugin", "blur_plugin", "2.0.0");
+        install(dir.path(), "blur_plugin/1.2.0", "blur_plugin", "1.2.0");
+        install(dir.path(), "blur_plugin/1.4.1", "blur_plugin", "1.4.1");
+        install(dir.path(), "blur_plugin/1.10.0", "blur_plugin", "1.10.0");
+        install(dir.path(), "blur_plugin/0.9.0", "blur_plugin", "0.9.0");
+
+        let resolved = |text: &str| {
+            resolve(&spec(text), dir.path())
+                .unwrap()
+                .unwrap()
+                .version
+                .to_string()
+        };
+        assert_eq!(resolved("blur_plugin"), "2.0.0");
+        assert_eq!(resolved("blur_plugin@^1.2"), "1.10.0");
+        assert_eq!(resolved("blur_plugin@>=1.2, <1.5"), "1.4.1");
+        assert_eq!(resolved("blur_plugin@=1.2.0"), "1.2.0");
+        assert_eq!(resolved("blur_plugin@<1"), "0.9.0");
+
+        assert!(resolve(&spec("other@^1"), dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn missing_version_lists_available_versions() {
+        let dir = TempDir::new().unwrap();
+        install(dir.path(), "blur_plugin/1.2.0", "blur_plugin", "1.2.0");
+        install(dir.path(), "blur_plugin/1.4.1", "blur_plugin", "1.4.1");
+
+        let err = resolve(&spec("blur_plugin@^2"), dir.path()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no version of plugin 'blur_plugin' matches '^2' \
+             (available: 1.2.0, 1.4.1)"
+        );
+    }
+
+    #[test]
+    fn version_installed_twice_is_a_conflict() {
+        let dir = TempDir::new().unwrap();
+        install(dir.path(), "blur_plugin", "blur_plugin", "1.2.0");
+        install(dir.path(), "blur_plugin/1.2.0", "blur_plugin", "1.2.0");
+
+        let err = installed_versions("blur_plugin", dir.path()).unwrap_err();
+        assert!(
+            matches!(&err, AppError::PluginVersionConflict { version, .. } if version == "1.2.0"),
+            "{err}"
+        );
+    }
+
+    #[rstest]
+    #[case("blur_plugin/1.3.0", "blur_plugin", "1.2.0")]
+    #[case("blur_plugin", "other", "1.2.0")]
+    #[case("blur_plugin", "blur_plugin", "1.2")]
+    fn inconsistent_manifests_are_rejected(
+        #[case] dir: &str,
+        #[case] name: &str,
+        #[case] version: &str,
+    ) {
+        let plugin_dir = TempDir::new().unwrap();
+        install(plugin_dir.path(), dir, name, version);
+        assert!(matches!(
+            installed_versions("blur_plugin", plugin_dir.path()),
+            Err(AppError::InvalidManifest { .. })
+        ));
+    }
+}
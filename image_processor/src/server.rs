@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+use image::ImageFormat;
+use percent_encoding::percent_decode_str;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::error::AppError;
+use crate::plugin::ImagePlugin;
+use crate::process::{decode, encode};
+use crate::registry::PluginRegistry;
+use crate::resolver::PluginSpec;
+
+/// Default limit on the size of a request body.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 << 20;
+
+/// Maps an error to the HTTP status code of the response
+/// reporting it: 4xx for problems with the request (image,
+/// parameters, plugin name), 5xx for failures of the plugin or
+/// the host.
+pub const fn status_code(err: &AppError) -> u16 {
+    match err {
+        AppError::ImageDecode { .. }
+        | AppError::InvalidPluginSpec { .. }
+        | AppError::InvalidPipelineJson { .. }
+        | AppError::InvalidImageView { .. }
+        | AppError::InvalidRegion { .. }
+        | AppError::InvalidAuxInput { .. }
+        | AppError::AuxInputsUnsupported
+        | AppError::NotTileSafe
+        // Bad dimensions, invalid parameters, auxiliary inputs,
+        // kernel or format: the plugin rejected the request.
+        | AppError::PluginExec { code: 2 | 4..=7 }
+        | AppError::PluginFailed { code: 2 | 4..=7, .. } => 400,
+        AppError::PluginVersionNotFound { .. } => 404,
+        AppError::UnsupportedFormat { .. } => 415,
+        _ => 500,
+    }
+}
+
+/// Plugin served by a [`Server`], loaded at startup.
+struct ServedPlugin {
+    plugin: Arc<dyn ImagePlugin>,
+    /// Serializes calls to plugins that are not thread-safe.
+    lock: Option<Mutex<()>>,
+}
+
+/// HTTP service running plugins on images:
+///
+/// - `POST /process/{plugin}?{params}` with an encoded image
+///   (PNG, JPEG, ...) as the body returns the processed image,
+///   in the input format or the one named by `format`;
+/// - `GET /plugins` lists the served plugins.
+///
+/// Parameters are given as a JSON query value,
+/// `params={"radius":2}`, or as separate query values,
+/// `radius=2&iterations=3`, each parsed as JSON if possible and
+/// taken as a string otherwise.
+///
+/// Plugins are loaded once, when the server is created, and
+/// served under their name, without any version requirement
+/// (`blur_plugin@^1.2` is served at `/process/blur_plugin`).
+/// Requests are handled by a pool of threads; calls to plugins
+/// that are not thread-safe are serialized. Errors are returned
+/// as `{"error": message}` with the status from
+/// [`status_code`].
+pub struct Server {
+    http: tiny_http::Server,
+    plugins: BTreeMap<String, ServedPlugin>,
+    max_body_bytes: usize,
+    stopping: AtomicBool,
+}
+
+impl Server {
+    /// Loads `names` (e.g. `blur_plugin` or `blur_plugin@^1.2`)
+    /// from `registry` and listens on `addr`; `127.0.0.1:0`
+    /// picks a free port (see [`Self::local_addr`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`PluginRegistry::get`],
+    /// `AppError::InvalidPluginSpec` if two of `names` name the
+    /// same plugin, and `AppError::Serve` if `addr` cannot be
+    /// bound.
+    pub fn bind(
+        addr: &str,
+        registry: &PluginRegistry,
+        names: &[String],
+    ) -> Result<Self, AppError> {
+        let mut plugins = BTreeMap::new();
+        for spec in names {
+            let name = spec.parse::<PluginSpec>()?.name;
+            if plugins.contains_key(&name) {
+                return Err(AppError::InvalidPluginSpec {
+                    spec: spec.clone(),
+                    message: format!("plugin '{name}' is already served"),
+                });
+            }
+            let plugin = registry.get(spec)?;
+            let thread_safe = plugin.thread_safe();
+            log::info!("Serving plugin {spec} (thread-safe: {thread_safe})");
+            plugins.insert(
+                name,
+                ServedPlugin {
+                    plugin,
+                    lock: (!thread_safe).then(|| Mutex::new(())),
+                },
+            );
+        }
+
+        let http =
+            tiny_http::Server::http(addr).map_err(|e| AppError::Serve {
+                message: format!("cannot listen on {addr}: {e}"),
+            })?;
+        Ok(Self {
+            http,
+            plugins,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            stopping: AtomicBool::new(false),
+        })
+    }
+
+    /// Rejects request bodies larger than `max_body_bytes` with
+    /// status 413.
+    pub const fn set_max_body_bytes(&mut self, max_body_bytes: usize) {
+        self.max_body_bytes = max_body_bytes;
+    }
+
+    /// Address the server listens on.
+    ///
+    /// # Panics
+    ///
+    /// If the server listens on a Unix socket, which
+    /// [`Self::bind`] never does.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.http
+            .server_addr()
+            .to_ip()
+            .expect("server listens on a TCP address")
+    }
+
+    /// Handles requests on `threads` threads until
+    /// [`Self::stop`] is called.
+    pub fn run(&self, threads: NonZeroUsize) {
+        log::info!("Listening on http://{}", self.local_addr());
+        thread::scope(|scope| {
+            for _ in 0..threads.get() {
+                scope.spawn(|| self.serve());
+            }
+        });
+    }
+
+    /// Makes [`Self::run`] return once requests in progress are
+    /// answered.
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // Wakes one thread, which passes it on to the next.
+        self.http.unblock();
+    }
+
+    /// Worker thread: answers requests until stopped.
+    fn serve(&self) {
+        loop {
+            match self.http.recv() {
+                Ok(request) => self.respond(request),
+                Err(_) if self.stopping.load(Ordering::SeqCst) => {
+                    // Pass the wake-up on to the other threads.
+                    self.http.unblock();
+                    return;
+                }
+                Err(err) => log::warn!("Failed to receive request: {err}"),
+            }
+        }
+    }
+
+    fn respond(&self, mut request: Request) {
+        let started = Instant::now();
+        let method = request.method().clone();
+        let url = request.url().to_owned();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+        let result = match (&method, path.strip_prefix("/process/")) {
+            (Method::Post, Some(name)) => {
+                self.process(&mut request, name, query)
+            }
+            (_, Some(_)) => Err(HttpError::new(405, "use POST")),
+            (Method::Get, None) if path == "/plugins" => Ok(self.list()),
+            _ => Err(HttpError::new(404, "not found")),
+        };
+        let (status, response) = match result {
+            Ok(response) => (200, response),
+            Err(err) => (err.status, err.into_response()),
+        };
+
+        log::info!("{method} {path} -> {status} in {:?}", started.elapsed());
+        if let Err(err) = request.respond(response.with_status_code(status)) {
+            log::warn!("Failed to send response: {err}");
+        }
+    }
+
+    /// Answers `GET /plugins`.
+    fn list(&self) -> Response<Cursor<Vec<u8>>> {
+        let plugins: Vec<serde_json::Value> = self
+            .plugins
+            .iter()
+            .map(|(name, served)| {
+                serde_json::json!({
+                    "name": name,
+                    "thread_safe": served.lock.is_none(),
+                })
+            })
+            .collect();
+        json_response(&serde_json::json!({ "plugins": plugins }))
+    }
+
+    /// Answers `POST /process/{name}?{query}`.
+    fn process(
+        &self,
+        request: &mut Request,
+        name: &str,
+        query: &str,
+    ) -> Result<Response<Cursor<Vec<u8>>>, HttpError> {
+        let name = percent_decode_str(name).decode_utf8_lossy();
+        let served = self.plugins.get(name.as_ref()).ok_or_else(|| {
+            HttpError::new(404, format!("plugin '{name}' is not served"))
+        })?;
+        let (params, format) = parse_query(query)?;
+        let body = self.read_body(request)?;
+
+        let (mut image, input_format) = decode(&body)?;
+        let format = format.unwrap_or(input_format);
+        let (width, height) = image.dimensions();
+
+        {
+            let _guard = served.lock.as_ref().map(|lock| {
+                lock.lock().unwrap_or_else(PoisonError::into_inner)
+            });
+            served
+                .plugin
+                .process_image(width, height, &mut image, &params)?;
+        }
+
+        let encoded = encode(image, format)?;
+        Ok(Response::from_data(encoded)
+            .with_header(content_type(format.to_mime_type())))
+    }
+
+    /// Reads the request body, up to the size limit.
+    fn read_body(&self, request: &mut Request) -> Result<Vec<u8>, HttpError> {
+        let too_large = || {
+            HttpError::new(
+                413,
+                format!("request body exceeds {} bytes", self.max_body_bytes),
+            )
+        };
+        if request
+            .body_length()
+            .is_some_and(|len| len > self.max_body_bytes)
+        {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        let limit = u64::try_from(self.max_body_bytes).unwrap_or(u64::MAX);
+        request
+            .as_reader()
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut body)
+            .map_err(|e| {
+                HttpError::new(400, format!("cannot read body: {e}"))
+            })?;
+        if body.len() > self.max_body_bytes {
+            return Err(too_large());
+        }
+        Ok(body)
+    }
+}
+
+/// Splits a query string into the plugin parameters (as JSON)
+/// and the requested output format, if any.
+fn parse_query(
+    query: &str,
+) -> Result<(String, Option<ImageFormat>), HttpError> {
+    let mut params = None;
+    let mut format = None;
+    let mut values = serde_json::Map::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "params" => params = Some(value.into_owned()),
+            "format" => {
+                format = Some(
+                    ImageFormat::from_extension(value.as_ref()).ok_or_else(
+                        || AppError::UnsupportedFormat {
+                            format: value.into_owned(),
+                        },
+                    )?,
+                );
+            }
+            _ => {
+                let value = serde_json::from_str(&value).unwrap_or_else(|_| {
+                    serde_json::Value::String(value.into_owned())
+                });
+                values.insert(key.into_owned(), value);
+            }
+        }
+    }
+
+    let params = match params {
+        Some(_) if !values.is_empty() => {
+            return Err(HttpError::new(
+                400,
+                "give parameters either as 'params' JSON or as separate \
+                 query values, not both",
+            ));
+        }
+        Some(params) => params,
+        None => serde_json::Value::Object(values).to_string(),
+    };
+    Ok((params, format))
+}
+
+/// Error answered to a request.
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn into_response(self) -> Response<Cursor<Vec<u8>>> {
+        json_response(&serde_json::json!({ "error": self.message }))
+    }
+}
+
+impl From<AppError> for HttpError {
+    fn from(err: AppError) -> Self {
+        Self::new(status_code(&err), err.to_string())
+    }
+}
+
+fn json_response(value: &serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(value.to_string())
+        .with_header(content_type("application/json"))
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime)
+        .expect("MIME types are valid header values")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_values_become_json_params() {
+        let (params, format) =
+            parse_query("radius=2&mode=gaussian&horizontal=true").unwrap();
+        let params: serde_json::Value = serde_json::from_str(&params).unwrap();
+        assert_eq!(
+            params,
+            serde_json::json!({
+                "radius": 2, "mode": "gaussian", "horizontal": true
+            })
+        );
+        assert!(format.is_none());
+
+        let (params, format) =
+            parse_query("params=%7B%22radius%22%3A+3%7D&format=jpg").unwrap();
+        assert_eq!(params, r#"{"radius": 3}"#);
+        assert_eq!(format, Some(ImageFormat::Jpeg));
+
+        assert_eq!(parse_query("").unwrap().0, "{}");
+    }
+
+    #[test]
+    fn invalid_queries_are_rejected() {
+        for query in ["params=%7B%7D&radius=2", "format=nope"] {
+            let err = parse_query(query).err().unwrap();
+            assert_eq!(
+                err.status,
+                if query.contains("nope") { 415 } else { 400 }
+            );
+        }
+    }
+
+    #[test]
+    fn errors_map_to_status_codes() {
+        assert_eq!(status_code(&AppError::PluginExec { code: 4 }), 400);
+        assert_eq!(status_code(&AppError::PluginExec { code: 3 }), 500);
+        assert_eq!(
+            status_code(&AppError::PluginPanicked {
+                message: String::new()
+            }),
+            500
+        );
+        assert_eq!(
+            status_code(&AppError::PluginVersionNotFound {
+                name: "p".into(),
+                requirement: "^2".into(),
+                available: vec![],
+            }),
+            404
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+
+use crate::error::AppError;
+use crate::image_view::{ImageViewMut, Rect, crop};
+use crate::plugin::ImagePlugin;
+use crate::plugin_loader::{AuxInput, check_aux_sizes};
+
+/// Bytes per RGBA pixel.
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Processed rows waiting to be written back.
+struct Strip {
+    y: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Runs a plugin over the image in `tile_size x tile_size`
+/// tiles, producing the same result as processing it whole.
+///
+/// Each tile is extended by the halo the plugin declares for
+/// `params` (see [`ImagePlugin::tile_halo`]), processed as a
+/// separate image (auxiliary inputs are cropped the same way),
+/// and only its interior is kept. Results are held back until
+/// no later tile reads those rows, so every tile sees original
+/// pixels. The plugin's working memory is bounded by the tile
+/// size; the host additionally keeps about
+/// `halo / tile_size + 1` strips of output.
+///
+/// # Errors
+///
+/// Returns `AppError::NotTileSafe` if the plugin does not
+/// declare itself tile-safe for `params`,
+/// `AppError::InvalidImageView` if `rgba_data` does not match
+/// the dimensions, `AppError::InvalidAuxInput` if an auxiliary
+/// input is not the size of the image, and any error from
+/// processing a tile.
+pub fn process_tiled(
+    plugin: &dyn ImagePlugin,
+    width: u32,
+    height: u32,
+    rgba_data: &mut [u8],
+    params: &str,
+    aux: &[AuxInput<'_>],
+    tile_size: NonZeroU32,
+) -> Result<(), AppError> {
+    ImageViewMut::new(rgba_data, width, height)?;
+    check_aux_sizes(aux, width, height)?;
+
+    let halo = plugin.tile_halo(params)?.ok_or(AppError::NotTileSafe)?;
+    let tile_size = tile_size.get();
+
+    log::info!("Processing in {tile_size}x{tile_size} tiles, halo {halo}");
+
+    let mut pending = VecDeque::new();
+
+    for y in (0..height).step_by(tile_size as usize) {
+        let strip_height = tile_size.min(height - y);
+        let mut strip = Strip {
+            y,
+            height: strip_height,
+            pixels: vec![0; row_len(width) * strip_height as usize],
+        };
+
+        for x in (0..width).step_by(tile_size as usize) {
+            let inner = Rect {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: strip_height,
+            };
+            let outer = inner.expand(halo, width, height);
+
+            log::debug!("Tile {inner:?} with context {outer:?}");
+
+            let mut pixels = crop(rgba_data, width, outer);
+            let aux_pixels: Vec<Vec<u8>> = aux
+                .iter()
+                .map(|input| crop(input.rgba_data, width, outer))
+                .collect();
+            let tile_aux: Vec<AuxInput<'_>> = aux
+                .iter()
+                .zip(&aux_pixels)
+                .map(|(input, data)| AuxInput {
+                    name: input.name,
+                    width: outer.width,
+                    height: outer.height,
+                    rgba_data: data,
+                })
+                .collect();
+
+            plugin.process_image_with_aux(
+                outer.width,
+                outer.height,
+                &mut pixels,
+                params,
+                &tile_aux,
+            )?;
+
+            let interior = Rect {
+                x: inner.x - outer.x,
+                y: inner.y - outer.y,
+                ..inner
+            };
+            let target = Rect { y: 0, ..inner };
+            copy_rect(
+                &pixels,
+                outer.width,
+                interior,
+                &mut strip.pixels,
+                width,
+                target,
+            );
+        }
+
+        pending.push_back(strip);
+
+        // Later strips read from row `y + strip_height - halo`
+        // on; everything above can be written back.
+        let needed = (y + strip_height).saturating_sub(halo);
+        while let Some(done) =
+            pending.pop_front_if(|s| s.y + s.height <= needed)
+        {
+            write_back(rgba_data, width, &done);
+        }
+    }
+
+    for strip in &pending {
+        write_back(rgba_data, width, strip);
+    }
+
+    Ok(())
+}
+
+/// Bytes of pixel data in a row of `width` pixels.
+const fn row_len(width: u32) -> usize {
+    width as usize * BYTES_PER_PIXEL
+}
+
+/// Copies `src_rect` of a packed image `src_width` pixels wide
+/// to `dst_rect` (same size) of one `dst_width` pixels wide.
+fn copy_rect(
+    src: &[u8],
+    src_width: u32,
+    src_rect: Rect,
+    dst: &mut [u8],
+    dst_width: u32,
+    dst_rect: Rect,
+) {
+    let len = row_len(src_rect.width);
+    for row in 0..src_rect.height {
+        let from = row_len(src_width) * (src_rect.y + row) as usize
+            + row_len(src_rect.x);
+        let to = row_len(dst_width) * (dst_rect.y + row) as usize
+            + row_len(dst_rect.x);
+        dst[to..to + len].copy_from_slice(&src[from..from + len]);
+    }
+}
+
+/// Writes a processed strip into the full image.
+fn write_back(data: &mut [u8], width: u32, strip: &Strip) {
+    let start = row_len(width) * strip.y as usize;
+    data[start..start + strip.pixels.len()].copy_from_slice(&strip.pixels);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_back_replaces_strip_rows() {
+        let mut data = vec![0u8; 2 * 3 * BYTES_PER_PIXEL];
+        let strip = Strip {
+            y: 1,
+            height: 1,
+            pixels: vec![7; 2 * BYTES_PER_PIXEL],
+        };
+        write_back(&mut data, 2, &strip);
+        let rows: Vec<u8> =
+            data.chunks(2 * BYTES_PER_PIXEL).map(|r| r[0]).collect();
+        assert_eq!(rows, [0, 7, 0]);
+    }
+}
@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+use crate::manifest::MANIFEST_FILE;
+
+/// Prefix of every signed message, so plugin signatures cannot
+/// be confused with signatures made for anything else.
+const SIGNATURE_CONTEXT: &[u8] = b"image_processor plugin v2\0";
+
+/// A plugin allowed by a trust store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustedPlugin {
+    /// Path of the plugin relative to the plugin directory,
+    /// with `/` separators, e.g. `libblur_plugin.so` or
+    /// `blur_plugin/1.2.0/libblur_plugin.so`.
+    pub file: String,
+    /// Hex-encoded SHA-256 digest of the file.
+    pub sha256: String,
+    /// Hex-encoded SHA-256 digest of the plugin's manifest, if
+    /// it is installed with one (see [`manifest_path`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_sha256: Option<String>,
+    /// Hex-encoded ed25519 signature over the path and digests
+    /// (see [`signed_message`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// On-disk format of a trust store.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrustStoreFile {
+    /// Hex-encoded ed25519 public keys trusted to sign plugins.
+    #[serde(default)]
+    public_keys: Vec<String>,
+    #[serde(default)]
+    plugins: Vec<TrustedPlugin>,
+}
+
+/// Plugins allowed to load, read from a JSON file:
+///
+/// ```json
+/// {"public_keys": ["<hex ed25519 public key>"],
+///  "plugins": [
+///     {"file": "blur_plugin/1.2.0/libblur_plugin.so",
+///      "sha256": "<hex digest>",
+///      "manifest_sha256": "<hex digest of plugin.toml>",
+///      "signature": "<hex ed25519 signature>"}
+/// ]}
+/// ```
+///
+/// Plugins are listed by their path relative to the plugin
+/// directory, so every installed version has its own entry. A
+/// plugin passes if it is listed with its current digest and
+/// that of its manifest, and its signature, if any, was made by
+/// one of the public keys.
+/// With [`Self::set_require_signed`], unsigned entries are
+/// rejected as well.
+#[derive(Debug)]
+pub struct TrustStore {
+    path: PathBuf,
+    keys: Vec<VerifyingKey>,
+    plugins: HashMap<String, TrustedPlugin>,
+    require_signed: bool,
+}
+
+impl TrustStore {
+    /// Reads a trust store from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the file cannot be read and
+    /// `AppError::InvalidTrustStore` if it is malformed.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let file = read_store(path)?;
+        let invalid = |message: String| AppError::InvalidTrustStore {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let keys = file
+            .public_keys
+            .iter()
+            .map(|key| {
+                parse_public_key(key).ok_or_else(|| {
+                    invalid(format!("invalid public key '{key}'"))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let plugins = file
+            .plugins
+            .into_iter()
+            .map(|plugin| (plugin.file.clone(), plugin))
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            keys,
+            plugins,
+            require_signed: false,
+        })
+    }
+
+    /// Sets whether plugins must carry a valid signature.
+    pub const fn set_require_signed(&mut self, require_signed: bool) {
+        self.require_signed = require_signed;
+    }
+
+    /// Checks the plugin file at `path` in `plugin_dir`, and its
+    /// manifest (see [`manifest_path`]), against the store.
+    ///
+    /// The files may change after the check; to load a plugin,
+    /// verify the bytes actually loaded with
+    /// [`Self::verify_contents`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`Self::verify_contents`], and
+    /// `AppError::Io` if a file cannot be read.
+    pub fn verify(
+        &self,
+        plugin_dir: &Path,
+        path: &Path,
+    ) -> Result<(), AppError> {
+        let contents = read(path)?;
+        let manifest = manifest_path(plugin_dir, path)
+            .map(|path| read(&path))
+            .transpose()?;
+        self.verify_contents(plugin_dir, path, &contents, manifest.as_deref())
+    }
+
+    /// Checks `contents`, read from the plugin file at `path` in
+    /// `plugin_dir`, and `manifest`, the text of the manifest it
+    /// is installed with, against the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::UntrustedPlugin` if the plugin is not
+    /// in `plugin_dir` or not listed, the digest of the file or
+    /// manifest differs, its signature is invalid, or it is
+    /// unsigned while signatures are required.
+    pub fn verify_contents(
+        &self,
+        plugin_dir: &Path,
+        path: &Path,
+        contents: &[u8],
+        manifest: Option<&[u8]>,
+    ) -> Result<(), AppError> {
+        let untrusted = |reason: String| AppError::UntrustedPlugin {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let Some(file) = relative_path(plugin_dir, path) else {
+            return Err(untrusted(format!(
+                "not in the plugin directory '{}'",
+                plugin_dir.display()
+            )));
+        };
+        let Some(trusted) = self.plugins.get(&file) else {
+            return Err(untrusted(format!(
+                "not listed in trust store '{}'",
+                self.path.display()
+            )));
+        };
+
+        let digest = hex::encode(Sha256::digest(contents));
+        if !digest.eq_ignore_ascii_case(&trusted.sha256) {
+            return Err(untrusted(format!(
+                "SHA-256 digest {digest} does not match the trust store"
+            )));
+        }
+
+        let manifest_digest =
+            manifest.map(|manifest| hex::encode(Sha256::digest(manifest)));
+        match (&manifest_digest, &trusted.manifest_sha256) {
+            (Some(digest), Some(listed))
+                if digest.eq_ignore_ascii_case(listed) => {}
+            (None, None) => {}
+            (Some(digest), _) => {
+                return Err(untrusted(format!(
+                    "manifest SHA-256 digest {digest} does not match the \
+                     trust store"
+                )));
+            }
+            (None, Some(_)) => {
+                return Err(untrusted(
+                    "manifest listed in the trust store is missing".into(),
+                ));
+            }
+        }
+
+        match &trusted.signature {
+            Some(signature) => {
+                let message =
+                    signed_message(&file, &digest, manifest_digest.as_deref());
+                let valid =
+                    parse_signature(signature).is_some_and(|signature| {
+                        self.keys.iter().any(|key| {
+                            key.verify_strict(&message, &signature).is_ok()
+                        })
+                    });
+                if !valid {
+                    return Err(untrusted(
+                        "signature is not valid for any trusted key".into(),
+                    ));
+                }
+            }
+            None if self.require_signed => {
+                return Err(untrusted("plugin is not signed".into()));
+            }
+            None => {}
+        }
+
+        log::info!("Plugin verified: {file} (sha256 {digest})");
+        Ok(())
+    }
+}
+
+/// Signs the plugin at `plugin` in `plugin_dir`, with its
+/// manifest (see [`manifest_path`]), using the hex-encoded
+/// ed25519 secret key in `key_file` and records it in the trust
+/// store at `store`.
+///
+/// The key's public key is added to the store, which is created
+/// if it does not exist; an existing entry for the same path is
+/// replaced.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidSigningKey` if the key file does
+/// not hold a 32-byte hex key, `AppError::PluginNotInDirectory`
+/// if `plugin` is not in `plugin_dir`,
+/// `AppError::InvalidTrustStore` if the existing store is
+/// malformed, and `AppError::Io` if a file cannot be read or
+/// written.
+pub fn sign_plugin(
+    plugin: &Path,
+    plugin_dir: &Path,
+    key_file: &Path,
+    store: &Path,
+) -> Result<TrustedPlugin, AppError> {
+    let key = read_signing_key(key_file)?;
+    let canonical = |path: &Path| {
+        path.canonicalize().map_err(|source| AppError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    let (plugin, plugin_dir) = (canonical(plugin)?, canonical(plugin_dir)?);
+    let file = relative_path(&plugin_dir, &plugin).ok_or_else(|| {
+        AppError::PluginNotInDirectory {
+            path: plugin.clone(),
+            dir: plugin_dir.clone(),
+        }
+    })?;
+    let digest = sha256_file(&plugin)?;
+    let manifest_digest = manifest_path(&plugin_dir, &plugin)
+        .map(|path| sha256_file(&path))
+        .transpose()?;
+    let signature =
+        key.sign(&signed_message(&file, &digest, manifest_digest.as_deref()));
+
+    let mut contents = if store.exists() {
+        read_store(store)?
+    } else {
+        TrustStoreFile::default()
+    };
+
+    let public_key = hex::encode(key.verifying_key().as_bytes());
+    if !contents
+        .public_keys
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(&public_key))
+    {
+        contents.public_keys.push(public_key);
+    }
+
+    let trusted = TrustedPlugin {
+        file,
+        sha256: digest,
+        manifest_sha256: manifest_digest,
+        signature: Some(hex::encode(signature.to_bytes())),
+    };
+    contents
+        .plugins
+        .retain(|plugin| plugin.file != trusted.file);
+    contents.plugins.push(trusted.clone());
+
+    let mut json = serde_json::to_string_pretty(&contents).map_err(|e| {
+        AppError::InvalidTrustStore {
+            path: store.to_path_buf(),
+            message: e.to_string(),
+        }
+    })?;
+    json.push('\n');
+    fs::write(store, json).map_err(|source| AppError::Io {
+        path: store.to_path_buf(),
+        source,
+    })?;
+
+    Ok(trusted)
+}
+
+/// Message signed for a plugin.
+///
+/// It is a fixed context string, the path relative to the
+/// plugin directory, a NUL byte, the lowercase hex SHA-256
+/// digest of the plugin, a NUL byte and that of its manifest
+/// (empty without one).
+pub fn signed_message(
+    file: &str,
+    sha256: &str,
+    manifest_sha256: Option<&str>,
+) -> Vec<u8> {
+    let mut message = SIGNATURE_CONTEXT.to_vec();
+    message.extend_from_slice(file.as_bytes());
+    message.push(0);
+    message.extend_from_slice(sha256.to_ascii_lowercase().as_bytes());
+    message.push(0);
+    if let Some(manifest_sha256) = manifest_sha256 {
+        message
+            .extend_from_slice(manifest_sha256.to_ascii_lowercase().as_bytes());
+    }
+    message
+}
+
+/// Manifest a plugin file is installed with: the `plugin.toml`
+/// next to it, if it lives in a directory of its own below
+/// `plugin_dir` (`<name>/` or `<name>/<version>/`). Libraries
+/// directly in `plugin_dir` have none.
+pub fn manifest_path(plugin_dir: &Path, path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    if dir == plugin_dir {
+        return None;
+    }
+    let manifest = dir.join(MANIFEST_FILE);
+    manifest.is_file().then_some(manifest)
+}
+
+/// Path of `path` relative to `plugin_dir`, with `/`
+/// separators, or `None` if it is not inside it.
+fn relative_path(plugin_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(plugin_dir).ok()?;
+    let parts = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Returns the lowercase hex SHA-256 digest of a file.
+fn sha256_file(path: &Path) -> Result<String, AppError> {
+    Ok(hex::encode(Sha256::digest(read(path)?)))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, AppError> {
+    fs::read(path).map_err(|source| AppError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn read_store(path: &Path) -> Result<TrustStoreFile, AppError> {
+    let text = fs::read_to_string(path).map_err(|source| AppError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    serde_json::from_str(&text).map_err(|e| AppError::InvalidTrustStore {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+fn read_signing_key(path: &Path) -> Result<SigningKey, AppError> {
+    let text = fs::read_to_string(path).map_err(|source| AppError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let bytes: [u8; 32] = hex::decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| AppError::InvalidSigningKey {
+            path: path.to_path_buf(),
+        })?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+fn parse_public_key(hex_key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+fn parse_signature(hex_signature: &str) -> Option<Signature> {
+    let bytes: [u8; 64] = hex::decode(hex_signature).ok()?.try_into().ok()?;
+    Some(Signature::from_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const KEY: &str =
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    /// Writes a fake plugin and a signing key into a fresh
+    /// directory.
+    fn setup() -> (TempDir, PathBuf, PathBuf, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let plugin = dir.path().join("libfake.so");
+        let key = dir.path().join("signing.key");
+        fs::write(&plugin, b"not really a library").unwrap();
+        fs::write(&key, format!("{KEY}\n")).unwrap();
+        let store = dir.path().join("trust.json");
+        (dir, plugin, key, store)
+    }
+
+    /// Returns the reason of an `UntrustedPlugin` error.
+    fn untrusted_reason(result: Result<(), AppError>) -> String {
+        match result {
+            Err(AppError::UntrustedPlugin { reason, .. }) => reason,
+            other => panic!("expected UntrustedPlugin, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn signed_plugin_passes_verification() {
+        let (dir, plugin, key, store) = setup();
+        let trusted = sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        assert_eq!(trusted.file, "libfake.so");
+
+        let mut trust = TrustStore::load(&store).unwrap();
+        trust.set_require_signed(true);
+        trust.verify(dir.path(), &plugin).unwrap();
+    }
+
+    #[test]
+    fn modified_plugin_is_rejected() {
+        let (dir, plugin, key, store) = setup();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        fs::write(&plugin, b"tampered").unwrap();
+
+        let trust = TrustStore::load(&store).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &plugin))
+                .contains("does not match")
+        );
+    }
+
+    #[test]
+    fn unlisted_plugin_is_rejected() {
+        let (dir, plugin, key, store) = setup();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        let other = dir.path().join("libother.so");
+        fs::write(&other, b"not really a library").unwrap();
+
+        let trust = TrustStore::load(&store).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &other))
+                .contains("not listed")
+        );
+    }
+
+    #[test]
+    fn signature_from_unknown_key_is_rejected() {
+        let (dir, plugin, key, store) = setup();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+
+        // Replace the trusted key with a different one.
+        let other = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        let text = fs::read_to_string(&store).unwrap();
+        let mut file: TrustStoreFile = serde_json::from_str(&text).unwrap();
+        file.public_keys = vec![hex::encode(other.as_bytes())];
+        fs::write(&store, serde_json::to_string(&file).unwrap()).unwrap();
+
+        let trust = TrustStore::load(&store).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &plugin))
+                .contains("signature")
+        );
+    }
+
+    #[test]
+    fn unsigned_plugin_passes_only_without_require_signed() {
+        let (dir, plugin, _key, store) = setup();
+        let file = TrustStoreFile {
+            public_keys: Vec::new(),
+            plugins: vec![TrustedPlugin {
+                file: "libfake.so".into(),
+                sha256: sha256_file(&plugin).unwrap().to_uppercase(),
+                manifest_sha256: None,
+                signature: None,
+            }],
+        };
+        fs::write(&store, serde_json::to_string(&file).unwrap()).unwrap();
+
+        let mut trust = TrustStore::load(&store).unwrap();
+        trust.verify(dir.path(), &plugin).unwrap();
+        trust.set_require_signed(true);
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &plugin))
+                .contains("not signed")
+        );
+    }
+
+    #[test]
+    fn signing_again_replaces_entry() {
+        let (dir, plugin, key, store) = setup();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        fs::write(&plugin, b"rebuilt").unwrap();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+
+        let file = read_store(&store).unwrap();
+        assert_eq!(file.public_keys.len(), 1);
+        assert_eq!(file.plugins.len(), 1);
+        TrustStore::load(&store)
+            .unwrap()
+            .verify(dir.path(), &plugin)
+            .unwrap();
+    }
+
+    #[test]
+    fn versions_of_one_plugin_are_trusted_separately() {
+        let (dir, _plugin, key, store) = setup();
+        let install = |version: &str, library: &[u8]| {
+            let version_dir = dir.path().join("fake").join(version);
+            fs::create_dir_all(&version_dir).unwrap();
+            fs::write(version_dir.join(MANIFEST_FILE), version).unwrap();
+            let plugin = version_dir.join("libfake.so");
+            fs::write(&plugin, library).unwrap();
+            plugin
+        };
+        let old = install("1.0.0", b"old library");
+        let new = install("2.0.0", b"new library");
+        let trusted = sign_plugin(&old, dir.path(), &key, &store).unwrap();
+        assert_eq!(trusted.file, "fake/1.0.0/libfake.so");
+        sign_plugin(&new, dir.path(), &key, &store).unwrap();
+
+        let mut trust = TrustStore::load(&store).unwrap();
+        trust.set_require_signed(true);
+        trust.verify(dir.path(), &old).unwrap();
+        trust.verify(dir.path(), &new).unwrap();
+    }
+
+    #[test]
+    fn modified_manifest_is_rejected() {
+        let (dir, _plugin, key, store) = setup();
+        let version_dir = dir.path().join("fake").join("1.0.0");
+        fs::create_dir_all(&version_dir).unwrap();
+        let plugin = version_dir.join("libfake.so");
+        fs::write(&plugin, b"not really a library").unwrap();
+        fs::write(version_dir.join(MANIFEST_FILE), "original").unwrap();
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        fs::write(version_dir.join(MANIFEST_FILE), "tampered").unwrap();
+
+        let trust = TrustStore::load(&store).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &plugin))
+                .contains("manifest SHA-256 digest")
+        );
+        fs::remove_file(version_dir.join(MANIFEST_FILE)).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(dir.path(), &plugin))
+                .contains("manifest listed in the trust store is missing")
+        );
+    }
+
+    #[test]
+    fn plugin_outside_plugin_directory_is_rejected() {
+        let (dir, plugin, key, store) = setup();
+        let plugin_dir = dir.path().join("plugins");
+        fs::create_dir(&plugin_dir).unwrap();
+        assert!(matches!(
+            sign_plugin(&plugin, &plugin_dir, &key, &store),
+            Err(AppError::PluginNotInDirectory { .. })
+        ));
+        sign_plugin(&plugin, dir.path(), &key, &store).unwrap();
+        let trust = TrustStore::load(&store).unwrap();
+        assert!(
+            untrusted_reason(trust.verify(&plugin_dir, &plugin))
+                .contains("not in the plugin directory")
+        );
+    }
+
+    #[test]
+    fn invalid_key_file_is_rejected() {
+        let (dir, plugin, key, store) = setup();
+        fs::write(&key, "abcd").unwrap();
+        assert!(matches!(
+            sign_plugin(&plugin, dir.path(), &key, &store),
+            Err(AppError::InvalidSigningKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,505 @@
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Trap, WasmParams, WasmResults,
+};
+
+use crate::error::AppError;
+use crate::image_view::ImageViewMut;
+use crate::plugin_loader::{AuxInput, check};
+
+/// Default cap on a WebAssembly plugin's linear memory (1 GiB).
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 1 << 30;
+
+/// Default fuel per call (about 10^11 WebAssembly
+/// instructions).
+pub const DEFAULT_FUEL: u64 = 100_000_000_000;
+
+/// Exports every WebAssembly plugin must provide.
+const REQUIRED_EXPORTS: [&str; 3] = ["memory", "plugin_alloc", "process_image"];
+
+/// Size of the wasm32 C layout of `AuxInput`: name pointer,
+/// width, height and data pointer, 4 bytes each.
+const AUX_INPUT_SIZE: usize = 16;
+
+/// Size of the wasm32 C layout of `TileInfo`: `bool` padded to
+/// 4 bytes, then the `u32` halo.
+const TILE_INFO_SIZE: usize = 8;
+
+/// Resource limits applied to every call into a WebAssembly
+/// plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// Maximum size of the plugin's linear memory in bytes.
+    pub max_memory_bytes: usize,
+    /// Fuel a call may consume (roughly one unit per
+    /// WebAssembly instruction) before it is stopped.
+    pub fuel: u64,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: DEFAULT_MAX_MEMORY_BYTES,
+            fuel: DEFAULT_FUEL,
+        }
+    }
+}
+
+/// Plugin compiled from a WebAssembly module.
+///
+/// The module exports the same C ABI as native plugins, built
+/// for `wasm32` (pointers are 32-bit offsets into its exported
+/// `memory`), plus `plugin_alloc(len) -> ptr` for the host to
+/// place buffers there. Every call runs in a fresh instance,
+/// so a plugin cannot keep state between calls, touch host
+/// memory or import host functions.
+pub(crate) struct WasmPlugin {
+    path: PathBuf,
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+}
+
+/// A single instantiation of a [`WasmPlugin`], used for one
+/// call.
+struct Session {
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+}
+
+impl WasmPlugin {
+    /// Compiles the module `bytes` (binary or text format), read
+    /// from `path`, and checks its imports and exports.
+    pub(crate) fn load(
+        path: &Path,
+        bytes: &[u8],
+        limits: &WasmLimits,
+    ) -> Result<Self, AppError> {
+        let load_error = |message: String| AppError::WasmLoad {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine =
+            Engine::new(&config).map_err(|e| load_error(format!("{e:#}")))?;
+        let module = Module::new(&engine, bytes)
+            .map_err(|e| load_error(format!("{e:#}")))?;
+
+        if let Some(import) = module.imports().next() {
+            return Err(load_error(format!(
+                "imports '{}::{}', but plugins may not import anything",
+                import.module(),
+                import.name()
+            )));
+        }
+        if let Some(name) = REQUIRED_EXPORTS
+            .into_iter()
+            .find(|name| module.get_export(name).is_none())
+        {
+            return Err(load_error(format!("missing export '{name}'")));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            module,
+            limits: *limits,
+        })
+    }
+
+    /// Returns `true` if the module exports
+    /// `process_image_with_aux`.
+    pub(crate) fn supports_aux_inputs(&self) -> bool {
+        self.module.get_export("process_image_with_aux").is_some()
+    }
+
+    /// Calls `plugin_tile_info`, if exported.
+    pub(crate) fn tile_halo(
+        &self,
+        params: &CStr,
+    ) -> Result<Option<u32>, AppError> {
+        if self.module.get_export("plugin_tile_info").is_none() {
+            return Ok(None);
+        }
+
+        let mut session = self.instantiate()?;
+        let params_ptr = session.write(params.to_bytes_with_nul())?;
+        let info_ptr = session.write(&[0; TILE_INFO_SIZE])?;
+        let code = session.call::<(u32, u32), i32>(
+            "plugin_tile_info",
+            (params_ptr, info_ptr),
+        )?;
+        check(code, || session.last_error())?;
+
+        let mut info = [0; TILE_INFO_SIZE];
+        session.read(info_ptr, &mut info)?;
+        let halo = u32::from_le_bytes([info[4], info[5], info[6], info[7]]);
+        Ok((info[0] != 0).then_some(halo))
+    }
+
+    /// Copies `image`, `params` and `aux` into a fresh instance,
+    /// calls `process_image` (or `process_image_with_aux`) and
+    /// copies the result back on success.
+    pub(crate) fn process_view(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: &CStr,
+        aux: &[AuxInput<'_>],
+        names: &[CString],
+    ) -> Result<(), AppError> {
+        let (width, height) = (image.width(), image.height());
+        let packed: Vec<u8> = image.rows().flatten().copied().collect();
+
+        let mut session = self.instantiate()?;
+        let params_ptr = session.write(params.to_bytes_with_nul())?;
+        let data_ptr = session.write(&packed)?;
+
+        let code = if aux.is_empty() {
+            session.call::<(u32, u32, u32, u32), i32>(
+                "process_image",
+                (width, height, data_ptr, params_ptr),
+            )?
+        } else {
+            let mut entries = Vec::with_capacity(aux.len() * AUX_INPUT_SIZE);
+            for (input, name) in aux.iter().zip(names) {
+                let name_ptr = session.write(name.as_bytes_with_nul())?;
+                let rgba_ptr = session.write(input.rgba_data)?;
+                for field in [name_ptr, input.width, input.height, rgba_ptr] {
+                    entries.extend_from_slice(&field.to_le_bytes());
+                }
+            }
+            let aux_ptr = session.write(&entries)?;
+            let aux_count = wasm_len(aux.len())?;
+            session.call::<(u32, u32, u32, u32, u32, u32), i32>(
+                "process_image_with_aux",
+                (width, height, data_ptr, params_ptr, aux_ptr, aux_count),
+            )?
+        };
+        check(code, || session.last_error())?;
+
+        let mut result = packed;
+        session.read(data_ptr, &mut result)?;
+        let row_len = width as usize * 4;
+        for (dst, src) in image.rows_mut().zip(result.chunks(row_len.max(1))) {
+            dst.copy_from_slice(src);
+        }
+        Ok(())
+    }
+
+    /// Creates a new instance with the configured limits.
+    fn instantiate(&self) -> Result<Session, AppError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.limits.fuel).map_err(|e| failure(&e))?;
+
+        let instance = Instance::new(&mut store, &self.module, &[])
+            .map_err(|e| failure(&e))?;
+        let memory =
+            instance.get_memory(&mut store, "memory").ok_or_else(|| {
+                AppError::WasmLoad {
+                    path: self.path.clone(),
+                    message: "export 'memory' is not a memory".to_owned(),
+                }
+            })?;
+
+        Ok(Session {
+            store,
+            instance,
+            memory,
+        })
+    }
+}
+
+impl Session {
+    /// Allocates a buffer with `plugin_alloc`, copies `bytes`
+    /// into it and returns its address.
+    fn write(&mut self, bytes: &[u8]) -> Result<u32, AppError> {
+        let ptr =
+            self.call::<u32, u32>("plugin_alloc", wasm_len(bytes.len())?)?;
+        self.memory
+            .write(&mut self.store, ptr as usize, bytes)
+            .map_err(|_| out_of_bounds(ptr, bytes.len()))?;
+        Ok(ptr)
+    }
+
+    /// Copies `buf.len()` bytes at `ptr` out of linear memory.
+    fn read(&self, ptr: u32, buf: &mut [u8]) -> Result<(), AppError> {
+        self.memory
+            .read(&self.store, ptr as usize, buf)
+            .map_err(|_| out_of_bounds(ptr, buf.len()))
+    }
+
+    /// Calls the exported function `name`.
+    ///
+    /// If the call traps and the plugin then reports a last
+    /// error (set by the SDK's panic hook before aborting), the
+    /// trap is reported as `AppError::PluginPanicked`.
+    fn call<P: WasmParams, R: WasmResults>(
+        &mut self,
+        name: &str,
+        params: P,
+    ) -> Result<R, AppError> {
+        let func = self
+            .instance
+            .get_typed_func::<P, R>(&mut self.store, name)
+            .map_err(|e| failure(&e))?;
+        func.call(&mut self.store, params).map_err(|error| {
+            self.last_error().map_or_else(
+                || failure(&error),
+                |message| AppError::PluginPanicked { message },
+            )
+        })
+    }
+
+    /// Reads the plugin's last error message, if it exports
+    /// `plugin_last_error` and has one.
+    fn last_error(&mut self) -> Option<String> {
+        let func = self
+            .instance
+            .get_typed_func::<(), u32>(&mut self.store, "plugin_last_error")
+            .ok()?;
+        let ptr = func.call(&mut self.store, ()).ok()?;
+        if ptr == 0 {
+            return None;
+        }
+
+        let tail = self.memory.data(&self.store).get(ptr as usize..)?;
+        let message = CStr::from_bytes_until_nul(tail).ok()?;
+        Some(message.to_string_lossy().into_owned())
+    }
+}
+
+/// Converts a host length to a wasm32 `size_t`.
+fn wasm_len(len: usize) -> Result<u32, AppError> {
+    u32::try_from(len).map_err(|_| AppError::Wasm {
+        message: format!("{len} bytes do not fit in 32-bit linear memory"),
+    })
+}
+
+/// Describes a trap or other wasmtime error.
+fn failure(error: &wasmtime::Error) -> AppError {
+    let message = error
+        .downcast_ref::<Trap>()
+        .map_or_else(|| format!("{error:#}"), ToString::to_string);
+    AppError::Wasm { message }
+}
+
+/// Error for a plugin-supplied buffer outside linear memory.
+fn out_of_bounds(ptr: u32, len: usize) -> AppError {
+    AppError::Wasm {
+        message: format!(
+            "buffer of {len} bytes at {ptr:#x} is outside linear memory"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::ImagePlugin;
+    use crate::plugin_loader::{LoadOptions, PluginLoader};
+
+    /// Bump allocator and memory shared by the test modules.
+    const PRELUDE: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "plugin_alloc") (param $len i32) (result i32)
+          (local $ptr i32)
+          (local.set $ptr (global.get $next))
+          (global.set $next (i32.add (global.get $next) (local.get $len)))
+          (local.get $ptr))
+    "#;
+
+    /// Inverts every byte of the image.
+    const INVERT: &str = r#"
+        (func (export "process_image")
+          (param $w i32) (param $h i32) (param $data i32) (param $params i32)
+          (result i32)
+          (local $end i32)
+          (local.set $end (i32.add (local.get $data)
+            (i32.mul (i32.mul (local.get $w) (local.get $h)) (i32.const 4))))
+          (block $done
+            (loop $next
+              (br_if $done (i32.ge_u (local.get $data) (local.get $end)))
+              (i32.store8 (local.get $data)
+                (i32.sub (i32.const 255) (i32.load8_u (local.get $data))))
+              (local.set $data (i32.add (local.get $data) (i32.const 1)))
+              (br $next)))
+          (i32.const 0))
+    "#;
+
+    /// Writes `(module PRELUDE body)` to a `.wat` file and loads
+    /// it.
+    fn load(body: &str, limits: &WasmLimits) -> Result<PluginLoader, AppError> {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("plugin.wat"),
+            format!("(module {body} {PRELUDE})"),
+        )
+        .unwrap();
+        let options = LoadOptions {
+            wasm_limits: *limits,
+            ..LoadOptions::default()
+        };
+        PluginLoader::load_with("plugin.wat", dir.path(), &options)
+    }
+
+    #[test]
+    fn processes_strided_view_in_linear_memory() {
+        let plugin = load(INVERT, &WasmLimits::default()).unwrap();
+
+        // 3x2 image; invert its right 2x2 column.
+        let mut data: Vec<u8> = (0..24).collect();
+        let mut image = ImageViewMut::new(&mut data, 3, 2).unwrap();
+        let sub = image.sub_view(1, 0, 2, 2).unwrap();
+        plugin.process_view(sub, "{}", &[]).unwrap();
+
+        let expected: Vec<u8> = (0..24)
+            .map(|i| if i % 12 < 4 { i } else { 255 - i })
+            .collect();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn aux_inputs_are_passed_as_wasm32_structs() {
+        // Copies the first byte of the first aux input into the
+        // image, and reports the aux width as the error code if
+        // the count is wrong.
+        let body = r#"
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (i32.const 1))
+            (func (export "process_image_with_aux")
+              (param $w i32) (param $h i32) (param $data i32)
+              (param $params i32) (param $aux i32) (param $count i32)
+              (result i32)
+              (if (i32.ne (local.get $count) (i32.const 1))
+                (then (return (i32.load offset=4 (local.get $aux)))))
+              (i32.store8 (local.get $data)
+                (i32.load8_u (i32.load offset=12 (local.get $aux))))
+              (i32.const 0))
+        "#;
+        let plugin = load(body, &WasmLimits::default()).unwrap();
+        assert!(plugin.supports_aux_inputs());
+
+        let mask = [77u8; 4];
+        let aux = [AuxInput {
+            name: "mask",
+            width: 1,
+            height: 1,
+            rgba_data: &mask,
+        }];
+        let mut data = [0u8; 4];
+        plugin
+            .process_image_with_aux(1, 1, &mut data, "{}", &aux)
+            .unwrap();
+        assert_eq!(data, [77, 0, 0, 0]);
+    }
+
+    #[test]
+    fn error_codes_and_tile_info_are_reported() {
+        let body = r#"
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (i32.const 4))
+            (func (export "plugin_tile_info")
+              (param $params i32) (param $info i32) (result i32)
+              (i32.store8 (local.get $info) (i32.const 1))
+              (i32.store offset=4 (local.get $info) (i32.const 3))
+              (i32.const 0))
+        "#;
+        let plugin = load(body, &WasmLimits::default()).unwrap();
+        assert!(!plugin.supports_aux_inputs());
+        assert_eq!(plugin.tile_halo("{}").unwrap(), Some(3));
+
+        let mut data = [0u8; 4];
+        let err = plugin.process_image(1, 1, &mut data, "{}").unwrap_err();
+        assert!(matches!(err, AppError::PluginExec { code: 4 }), "{err}");
+    }
+
+    #[test]
+    fn trap_with_last_error_is_reported_as_panic() {
+        let body = r#"
+            (data (i32.const 16) "boom\00")
+            (func (export "plugin_last_error") (result i32)
+              (i32.const 16))
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (unreachable))
+        "#;
+        let plugin = load(body, &WasmLimits::default()).unwrap();
+        let mut data = [0u8; 4];
+        let err = plugin.process_image(1, 1, &mut data, "{}").unwrap_err();
+        assert!(
+            matches!(&err, AppError::PluginPanicked { message } if message == "boom"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn fuel_limit_stops_runaway_plugin() {
+        let body = r#"
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (loop $forever (br $forever))
+              (i32.const 0))
+        "#;
+        let limits = WasmLimits {
+            fuel: 100_000,
+            ..WasmLimits::default()
+        };
+        let plugin = load(body, &limits).unwrap();
+        let mut data = [9u8; 4];
+        let err = plugin.process_image(1, 1, &mut data, "{}").unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{err}");
+        assert_eq!(data, [9; 4]);
+    }
+
+    #[test]
+    fn memory_limit_is_enforced() {
+        // Tries to grow memory by one page and fails if it cannot.
+        let body = r#"
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (if (result i32) (i32.lt_s (memory.grow (i32.const 1)) (i32.const 0))
+                (then (i32.const 8))
+                (else (i32.const 0))))
+        "#;
+        let mut data = [0u8; 4];
+
+        let roomy = load(body, &WasmLimits::default()).unwrap();
+        assert!(roomy.process_image(1, 1, &mut data, "{}").is_ok());
+
+        let limits = WasmLimits {
+            max_memory_bytes: 1 << 16,
+            ..WasmLimits::default()
+        };
+        let tight = load(body, &limits).unwrap();
+        let err = tight.process_image(1, 1, &mut data, "{}").unwrap_err();
+        assert!(matches!(err, AppError::PluginExec { code: 8 }), "{err}");
+    }
+
+    #[test]
+    fn modules_with_imports_or_missing_exports_are_rejected() {
+        let importing = r#"
+            (import "env" "log" (func))
+            (func (export "process_image")
+              (param i32 i32 i32 i32) (result i32)
+              (i32.const 0))
+        "#;
+        let err = load(importing, &WasmLimits::default()).err().unwrap();
+        assert!(err.to_string().contains("env::log"), "{err}");
+
+        let err = load("", &WasmLimits::default()).err().unwrap();
+        assert!(err.to_string().contains("'process_image'"), "{err}");
+    }
+}
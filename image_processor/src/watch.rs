@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::AppError;
+use crate::pipeline::Pipeline;
+use crate::plugin_loader::plugin_path;
+use crate::resolver::PluginSpec;
+
+/// How long changes must stop before [`ChangeWatcher::wait`]
+/// returns; builds and editors write files in bursts.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Watches files and directories for changes, e.g. to re-run
+/// processing while a plugin is being developed.
+pub struct ChangeWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+
+impl ChangeWatcher {
+    /// Watches `paths`: directories for changes anywhere below
+    /// them, anything else (including files that do not exist
+    /// yet) for changes to that file. Paths whose directory
+    /// does not exist are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Watch` if a directory cannot be
+    /// watched.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, AppError> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).map_err(|e| watch_error(&e))?;
+
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        let mut parents = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let dir = canonical(path)?;
+                watcher
+                    .watch(&dir, RecursiveMode::Recursive)
+                    .map_err(|e| watch_error(&e))?;
+                dirs.push(dir);
+                continue;
+            }
+
+            let parent = match path.parent() {
+                Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+                Some(parent) => parent,
+                None => Path::new("/"),
+            };
+            let (Some(name), true) = (path.file_name(), parent.is_dir()) else {
+                log::warn!(
+                    "Not watching {}: no such directory",
+                    path.display()
+                );
+                continue;
+            };
+            let parent = canonical(parent)?;
+            if !parents.contains(&parent) {
+                watcher
+                    .watch(&parent, RecursiveMode::NonRecursive)
+                    .map_err(|e| watch_error(&e))?;
+                parents.push(parent.clone());
+            }
+            files.push(parent.join(name));
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            files,
+            dirs,
+        })
+    }
+
+    /// Blocks until a watched path changes and no further
+    /// changes follow for a moment, or until `timeout` passes.
+    /// Returns `true` if something changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Watch` if watching fails.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool, AppError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let event = match deadline {
+                Some(deadline) => match self.events.recv_timeout(
+                    deadline.saturating_duration_since(Instant::now()),
+                ) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => return Ok(false),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(disconnected());
+                    }
+                },
+                None => self.events.recv().map_err(|_| disconnected())?,
+            };
+            if self.is_change(&event.map_err(|e| watch_error(&e))?) {
+                break;
+            }
+        }
+
+        let mut settled_at = Instant::now() + SETTLE_TIME;
+        loop {
+            match self.events.recv_timeout(
+                settled_at.saturating_duration_since(Instant::now()),
+            ) {
+                Ok(Ok(event)) if self.is_change(&event) => {
+                    settled_at = Instant::now() + SETTLE_TIME;
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => return Ok(true),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(disconnected());
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `event` changes a watched path. Reads
+    /// (including the host's own) and metadata changes are not
+    /// changes.
+    fn is_change(&self, event: &Event) -> bool {
+        let changes_content = match event.kind {
+            EventKind::Access(kind) => {
+                kind == AccessKind::Close(AccessMode::Write)
+            }
+            EventKind::Modify(ModifyKind::Metadata(_)) => false,
+            _ => true,
+        };
+        changes_content
+            && event.paths.iter().any(|path| {
+                self.files.contains(path)
+                    || self.dirs.iter().any(|dir| path.starts_with(dir))
+            })
+    }
+}
+
+/// Paths of the plugins `pipeline` loads from `plugin_dir`
+/// (whether or not they exist yet): library files and plugin
+/// directories with manifests, for [`ChangeWatcher::new`].
+pub fn plugin_paths(pipeline: &Pipeline, plugin_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for step in &pipeline.steps {
+        let Ok(spec) = step.plugin.parse::<PluginSpec>() else {
+            continue;
+        };
+        paths.push(plugin_path(&spec.name, plugin_dir));
+        let dir = plugin_dir.join(&spec.name);
+        if dir.is_dir() {
+            paths.push(dir);
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, AppError> {
+    path.canonicalize().map_err(|source| AppError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn watch_error(err: &notify::Error) -> AppError {
+    AppError::Watch {
+        message: err.to_string(),
+    }
+}
+
+fn disconnected() -> AppError {
+    AppError::Watch {
+        message: "file watcher stopped".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::thread;
+    use tempfile::TempDir;
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(10));
+
+    /// Writes `contents` to `path` shortly after the watcher
+    /// starts waiting.
+    fn write_later(
+        path: PathBuf,
+        contents: &'static str,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            fs::write(path, contents).unwrap();
+        })
+    }
+
+    #[test]
+    fn reports_changes_to_watched_files_only() {
+        let dir = TempDir::new().unwrap();
+        let params = dir.path().join("params.json");
+        let other = dir.path().join("other.json");
+        fs::write(&params, "{}").unwrap();
+        let watcher =
+            ChangeWatcher::new(std::slice::from_ref(&params)).unwrap();
+
+        let writer = write_later(other, "{}");
+        assert!(!watcher.wait(Some(Duration::from_millis(500))).unwrap());
+        writer.join().unwrap();
+
+        // Reading is not a change.
+        fs::read(&params).unwrap();
+        assert!(!watcher.wait(Some(Duration::from_millis(300))).unwrap());
+
+        let writer = write_later(params, r#"{"radius": 2}"#);
+        assert!(watcher.wait(TIMEOUT).unwrap());
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn reports_files_created_later_and_in_directories() {
+        let dir = TempDir::new().unwrap();
+        let library = dir.path().join("libnew_plugin.so");
+        let package = dir.path().join("blur_plugin");
+        fs::create_dir_all(package.join("1.2.0")).unwrap();
+        let watcher =
+            ChangeWatcher::new(&[library.clone(), package.clone()]).unwrap();
+
+        let writer = write_later(library, "");
+        assert!(watcher.wait(TIMEOUT).unwrap());
+        writer.join().unwrap();
+
+        let writer = write_later(package.join("1.2.0/plugin.toml"), "");
+        assert!(watcher.wait(TIMEOUT).unwrap());
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn plugin_paths_cover_libraries_and_plugin_directories() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("blur_plugin")).unwrap();
+        let pipeline: Pipeline = serde_json::from_str(
+            r#"{"steps": [
+                {"plugin": "blur_plugin@^1.2"},
+                {"plugin": "mirror_plugin.wasm"},
+                {"plugin": "blur_plugin"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut expected = vec![
+            plugin_path("blur_plugin", dir.path()),
+            dir.path().join("blur_plugin"),
+            dir.path().join("mirror_plugin.wasm"),
+        ];
+        expected.sort();
+        assert_eq!(plugin_paths(&pipeline, dir.path()), expected);
+    }
+}
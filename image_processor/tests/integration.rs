@@ -1,25 +1,60 @@
 use std::fs;
+use std::io::{Read as _, Write as _};
+use std::net::{SocketAddr, TcpStream};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use assert_cmd::assert::Assert;
 use assert_cmd::cargo::cargo_bin_cmd;
-use image::{ImageReader, Rgba, RgbaImage};
+use blur_core::BlurPlugin;
+use image::{ImageFormat, ImageReader, Rgba, RgbaImage};
+use image_processor::builtin::BuiltinPlugin;
+use image_processor::error::AppError;
+use image_processor::image_view::ImageViewMut;
+use image_processor::manifest::PluginManifest;
+use image_processor::pipeline::{Pipeline, Step};
+use image_processor::plugin::ImagePlugin;
+use image_processor::plugin_loader::{AuxInput, PluginLoader};
+use image_processor::registry::PluginRegistry;
+use image_processor::server::Server;
+use image_processor::{process_bytes, process_rgba};
+use mirror_core::MirrorPlugin;
+use plugin_sdk::{Plugin, PluginError};
+use serde::Deserialize;
 use tempfile::TempDir;
 
-/// Creates a 4x4 test image with a known pattern:
+/// Returns a 4x4 test image with a known pattern:
 /// top-left quadrant is red, rest is blue.
-fn create_test_image(path: &Path) {
-    let mut img = RgbaImage::new(4, 4);
-    for y in 0..4u32 {
-        for x in 0..4u32 {
-            let color = if x < 2 && y < 2 {
-                Rgba([255, 0, 0, 255]) // red
-            } else {
-                Rgba([0, 0, 255, 255]) // blue
-            };
-            img.put_pixel(x, y, color);
+fn test_image() -> RgbaImage {
+    RgbaImage::from_fn(4, 4, |x, y| {
+        if x < 2 && y < 2 {
+            Rgba([255, 0, 0, 255]) // red
+        } else {
+            Rgba([0, 0, 255, 255]) // blue
         }
-    }
-    img.save(path).expect("failed to save test image");
+    })
+}
+
+/// Saves [`test_image`] to `path`.
+fn create_test_image(path: &Path) {
+    test_image().save(path).expect("failed to save test image");
+}
+
+/// Returns a `width x height` image with a deterministic
+/// pseudo-random pattern.
+fn noise_image(width: u32, height: u32) -> RgbaImage {
+    let mut state = 0x2545_f491_u32;
+    RgbaImage::from_fn(width, height, |_, _| {
+        let mut channel = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        };
+        Rgba([channel(), channel(), channel(), 255])
+    })
 }
 
 /// Returns the absolute path to the built plugin directory.
@@ -29,103 +64,1324 @@ fn plugin_dir() -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/debug")
 }
 
-/// Helper: runs `image_processor` with given args,
-/// asserts success, and returns the output image.
-fn run_and_load(
-    input: &Path,
-    output: &Path,
-    plugin: &str,
-    params_path: &Path,
-) -> RgbaImage {
-    cargo_bin_cmd!("image_processor")
+/// Test plugin that panics with the given message.
+#[derive(Default)]
+struct Panicking;
+
+#[derive(Deserialize)]
+struct PanicParams {
+    message: String,
+}
+
+impl Plugin for Panicking {
+    type Params = PanicParams;
+
+    fn process(
+        &self,
+        _image: plugin_sdk::ImageViewMut<'_>,
+        params: PanicParams,
+    ) -> Result<(), PluginError> {
+        panic!("{}", params.message);
+    }
+}
+
+/// Test double counting how often it is called.
+#[derive(Default)]
+struct Counting {
+    calls: AtomicUsize,
+}
+
+impl ImagePlugin for Counting {
+    fn supports_aux_inputs(&self) -> bool {
+        false
+    }
+
+    fn tile_halo(&self, _params: &str) -> Result<Option<u32>, AppError> {
+        Ok(None)
+    }
+
+    fn process_view(
+        &self,
+        _image: ImageViewMut<'_>,
+        _params: &str,
+        _aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Registry with the bundled plugins linked into the test, so
+/// no plugin libraries need to be built.
+fn plugins() -> PluginRegistry {
+    let mut plugins = PluginRegistry::new(plugin_dir());
+    plugins.register("mirror_plugin", BuiltinPlugin::<MirrorPlugin>::new());
+    plugins.register("blur_plugin", BuiltinPlugin::<BlurPlugin>::new());
+    plugins.register("panicking", BuiltinPlugin::<Panicking>::new());
+    plugins
+}
+
+/// Runs the pipeline `steps` (a JSON array) on a copy of
+/// `image`, passing `mask` as the auxiliary input "mask".
+fn run(
+    image: &RgbaImage,
+    steps: &str,
+    mask: Option<&RgbaImage>,
+) -> Result<RgbaImage, AppError> {
+    let pipeline: Pipeline =
+        serde_json::from_str(&format!(r#"{{"steps": {steps}}}"#))
+            .expect("invalid test pipeline");
+    let aux: Vec<AuxInput<'_>> = mask
+        .iter()
+        .map(|mask| AuxInput {
+            name: "mask",
+            width: mask.width(),
+            height: mask.height(),
+            rgba_data: mask.as_raw(),
+        })
+        .collect();
+
+    let mut result = image.clone();
+    let (width, height) = result.dimensions();
+    pipeline.run(&plugins(), width, height, &mut result, &aux)?;
+    Ok(result)
+}
+
+#[test]
+fn mirror_horizontal_flips_pixels() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true}}]"#,
+        None,
+    )
+    .unwrap();
+
+    // After horizontal flip of a 4x4 image:
+    // top-right quadrant should now be red (was top-left)
+    let top_right = result.get_pixel(3, 0);
+    assert_eq!(top_right, &Rgba([255, 0, 0, 255]));
+
+    // top-left should now be blue (was top-right area)
+    let top_left = result.get_pixel(0, 0);
+    assert_eq!(top_left, &Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn mirror_vertical_flips_pixels() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"vertical": true}}]"#,
+        None,
+    )
+    .unwrap();
+
+    // After vertical flip:
+    // bottom-left quadrant should now be red (was top-left)
+    let bottom_left = result.get_pixel(0, 3);
+    assert_eq!(bottom_left, &Rgba([255, 0, 0, 255]));
+
+    // top-left should now be blue
+    let top_left = result.get_pixel(0, 0);
+    assert_eq!(top_left, &Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn blur_modifies_image() {
+    let original = test_image();
+    let result = run(
+        &original,
+        r#"[{"plugin": "blur_plugin",
+             "params": {"radius": 1, "iterations": 1}}]"#,
+        None,
+    )
+    .unwrap();
+
+    // Blurred image should differ from original
+    // (boundary between red and blue areas gets mixed)
+    assert_ne!(original, result);
+}
+
+#[test]
+fn blur_with_mask_only_touches_masked_area() {
+    // Left half white (blur), right half black (keep).
+    let mask = RgbaImage::from_fn(4, 4, |x, _| {
+        let v = if x < 2 { 255 } else { 0 };
+        Rgba([v, v, v, 255])
+    });
+
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "blur_plugin", "params": {"radius": 1, "mask": {}}}]"#,
+        Some(&mask),
+    )
+    .unwrap();
+
+    // Red/blue boundary inside the mask gets mixed...
+    assert_ne!(result.get_pixel(1, 1), &Rgba([255, 0, 0, 255]));
+    // ...while the unmasked right half is untouched.
+    assert_eq!(result.get_pixel(2, 0), &Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn mask_with_plugin_without_aux_support_returns_error() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true}}]"#,
+        Some(&test_image()),
+    );
+    assert!(matches!(result, Err(AppError::PluginExec { code: 5 })));
+}
+
+#[test]
+fn panicking_plugin_is_reported_as_error() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "panicking",
+             "params": {"message": "index out of bounds"}}]"#,
+        None,
+    );
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(&err, AppError::PluginPanicked { message }
+            if message == "index out of bounds"),
+        "{err}"
+    );
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn panicking_dynamic_plugin_is_reported_as_error() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.png");
+    let params = dir.path().join("params.json");
+
+    create_test_image(&input);
+    fs::write(&params, r#"{"message": "index out of bounds"}"#).unwrap();
+
+    let assert = cargo_bin_cmd!("image_processor")
         .arg("--input")
-        .arg(input)
+        .arg(&input)
         .arg("--output")
-        .arg(output)
+        .arg(dir.path().join("out.png"))
         .arg("--plugin")
-        .arg(plugin)
+        .arg("panic_plugin")
         .arg("--params")
-        .arg(params_path)
+        .arg(&params)
         .arg("--plugin-path")
         .arg(plugin_dir())
         .assert()
-        .success();
+        .code(1);
+
+    // The host exits normally and reports the panic message.
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains("plugin panicked: index out of bounds"),
+        "stderr: {stderr}"
+    );
+}
+
+/// Mirrors the middle two columns of a 4x2 image with
+/// `plugin` and checks that nothing else moved.
+fn assert_mirrors_strided_sub_view(plugin: &dyn ImagePlugin) {
+    // 4x2 image: pixel x has red = x, green = row.
+    let mut data: Vec<u8> = (0..2u8)
+        .flat_map(|y| (0..4u8).flat_map(move |x| [x, y, 0, 255]))
+        .collect();
+    let mut image = ImageViewMut::new(&mut data, 4, 2).unwrap();
+
+    // Mirror only the middle two columns of both rows.
+    let view = image.sub_view(1, 0, 2, 2).unwrap();
+    plugin
+        .process_view(view, r#"{"horizontal": true}"#, &[])
+        .unwrap();
+
+    let reds: Vec<u8> = data.chunks(4).map(|pixel| pixel[0]).collect();
+    assert_eq!(reds, [0, 2, 1, 3, 0, 2, 1, 3]);
+    let greens: Vec<u8> = data.chunks(4).map(|pixel| pixel[1]).collect();
+    assert_eq!(greens, [0, 0, 0, 0, 1, 1, 1, 1]);
+}
+
+#[test]
+fn plugin_processes_strided_sub_view_in_place() {
+    assert_mirrors_strided_sub_view(&BuiltinPlugin::<MirrorPlugin>::new());
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn dynamic_plugin_processes_strided_sub_view_in_place() {
+    let loader = PluginLoader::load("mirror_plugin", &plugin_dir()).unwrap();
+    assert_mirrors_strided_sub_view(&loader);
+}
+
+/// Python script used by the `python_plugin` tests.
+const PYTHON_SCRIPT: &str = r#"
+def invert_red(data, width, height, args):
+    for i in range(0, width * height * 4, 4):
+        data[i] = 255 - data[i]
+
+def fill(data, width, height, args):
+    return bytes([args["value"]]) * (width * height * 4)
 
-    ImageReader::open(output)
+def wrong_size(data, width, height, args):
+    return b"\x00"
+
+def broken(data, width, height, args):
+    raise ValueError("bad pixel " + args["which"])
+"#;
+
+/// Runs `function` from [`PYTHON_SCRIPT`] through
+/// `python_plugin` on a copy of `image`.
+fn run_python(
+    image: &RgbaImage,
+    function: &str,
+    args: &str,
+) -> Result<RgbaImage, AppError> {
+    // Scripts are read from the current directory by default.
+    let dir = TempDir::new_in(".").unwrap();
+    let script = dir.path().join("script.py");
+    fs::write(&script, PYTHON_SCRIPT).unwrap();
+    run_python_script(image, &script, function, args)
+}
+
+/// Runs `function` from `script` through `python_plugin` on a
+/// copy of `image`.
+fn run_python_script(
+    image: &RgbaImage,
+    script: &Path,
+    function: &str,
+    args: &str,
+) -> Result<RgbaImage, AppError> {
+    let params = serde_json::json!({
+        "script": script,
+        "function": function,
+        "input": "bytes",
+        "args": serde_json::from_str::<serde_json::Value>(args).unwrap(),
+    });
+
+    let plugin = PluginLoader::load("python_plugin", &plugin_dir()).unwrap();
+    let mut result = image.clone();
+    let (width, height) = result.dimensions();
+    plugin.process_image(width, height, &mut result, &params.to_string())?;
+    Ok(result)
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn python_plugin_runs_script_function() {
+    let image = noise_image(5, 3);
+
+    let inverted = run_python(&image, "invert_red", "{}").unwrap();
+    for (before, after) in image.pixels().zip(inverted.pixels()) {
+        assert_eq!(after.0, [255 - before[0], before[1], before[2], before[3]]);
+    }
+
+    let filled = run_python(&image, "fill", r#"{"value": 9}"#).unwrap();
+    assert!(filled.pixels().all(|p| p.0 == [9; 4]));
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn python_plugin_reports_script_errors_with_traceback() {
+    let image = noise_image(2, 2);
+
+    let err =
+        run_python(&image, "broken", r#"{"which": "(1, 1)"}"#).unwrap_err();
+    let AppError::PluginFailed { code: 8, message } = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(message.starts_with("Traceback"), "{message}");
+    assert!(message.contains("script.py"), "{message}");
+    assert!(message.contains("ValueError: bad pixel (1, 1)"), "{message}");
+
+    let err = run_python(&image, "wrong_size", "{}").unwrap_err();
+    assert!(err.to_string().contains("returned 1 bytes, expected 16"), "{err}");
+    let err = run_python(&image, "missing", "{}").unwrap_err();
+    assert!(err.to_string().contains("AttributeError"), "{err}");
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn python_plugin_only_runs_scripts_from_script_directory() {
+    let dir = TempDir::new().unwrap();
+    let script = dir.path().join("script.py");
+    fs::write(&script, PYTHON_SCRIPT).unwrap();
+
+    let image = noise_image(2, 2);
+    let err =
+        run_python_script(&image, &script, "invert_red", "{}").unwrap_err();
+    assert!(
+        err.to_string().contains("is outside the script directory"),
+        "{err}"
+    );
+    let err = run_python_script(&image, Path::new("../README.md"), "f", "{}")
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("is outside the script directory"),
+        "{err}"
+    );
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn script_plugin_evaluates_script_per_pixel() {
+    let plugin = PluginLoader::load("script_plugin", &plugin_dir()).unwrap();
+    let image = noise_image(5, 3);
+    let mut result = image.clone();
+
+    plugin
+        .process_image(
+            5,
+            3,
+            &mut result,
+            r#"{"script": "r = 255 - r; b = x;"}"#,
+        )
+        .unwrap();
+    for (x, y, pixel) in result.enumerate_pixels() {
+        let before = image.get_pixel(x, y);
+        let expected = [
+            255 - before[0],
+            before[1],
+            u8::try_from(x).unwrap(),
+            before[3],
+        ];
+        assert_eq!(pixel.0, expected);
+    }
+
+    let err = plugin
+        .process_image(5, 3, &mut result, r#"{"script": "r = undefined;"}"#)
+        .unwrap_err();
+    let AppError::PluginFailed { code: 8, message } = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(message.contains("at (0, 0)"), "{message}");
+}
+
+#[test]
+fn buffer_length_mismatch_is_rejected_before_calling_plugin() {
+    let plugin = Counting::default();
+    let mut data = vec![0u8; 4 * 4 * 4 - 1];
+
+    let result =
+        plugin.process_image(4, 4, &mut data, r#"{"horizontal": true}"#);
+    assert!(matches!(result, Err(AppError::InvalidImageView { .. })));
+    assert_eq!(plugin.calls.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn tiled_blur_matches_whole_image() {
+    let input = noise_image(23, 17);
+
+    for params in [
+        r#"{"radius": 2, "iterations": 2}"#,
+        // Halo (6) larger than the tile size.
+        r#"{"radius": 3, "iterations": 2}"#,
+        r#"{"radius": 1, "mode": {"type": "median"}}"#,
+        r#"{"mode": {"type": "bilateral", "sigma_spatial": 1.5}}"#,
+    ] {
+        let whole = run(
+            &input,
+            &format!(r#"[{{"plugin": "blur_plugin", "params": {params}}}]"#),
+            None,
+        )
+        .unwrap();
+        let tiled = run(
+            &input,
+            &format!(
+                r#"[{{"plugin": "blur_plugin", "params": {params},
+                      "tile_size": 4}}]"#
+            ),
+            None,
+        )
+        .unwrap();
+
+        assert!(tiled == whole, "tiled output differs for {params}");
+    }
+}
+
+#[test]
+fn tiling_plugin_that_is_not_tile_safe_returns_error() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true},
+             "tile_size": 2}]"#,
+        None,
+    );
+
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("not tile-safe"), "{err}");
+}
+
+#[test]
+fn region_limits_plugin_to_rectangle() {
+    let original = noise_image(6, 5);
+    let result = run(
+        &original,
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true},
+             "region": {"x": 1, "y": 1, "width": 3, "height": 2}}]"#,
+        None,
+    )
+    .unwrap();
+
+    for (x, y, pixel) in result.enumerate_pixels() {
+        let source = if (1..4).contains(&x) && (1..3).contains(&y) {
+            original.get_pixel(4 - x, y)
+        } else {
+            original.get_pixel(x, y)
+        };
+        assert_eq!(pixel, source, "pixel ({x}, {y})");
+    }
+}
+
+#[test]
+fn feathered_region_fades_into_original() {
+    // Mirror the top row: red moves from the left to the right half.
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true},
+             "region": {"x": 0, "y": 0, "width": 4, "height": 1},
+             "feather": 1}]"#,
+        None,
+    )
+    .unwrap();
+
+    // Only the bottom edge is inside the image, so the whole
+    // row is at half weight.
+    assert_eq!(result.get_pixel(0, 0), &Rgba([128, 0, 128, 255]));
+    assert_eq!(result.get_pixel(3, 0), &Rgba([128, 0, 128, 255]));
+    assert_eq!(result.get_pixel(0, 1), &Rgba([255, 0, 0, 255]));
+}
+
+const BLUR_REGION_STEP: &str = r#"{"plugin": "blur_plugin",
+    "params": {"radius": 2},
+    "region": {"x": 2, "y": 1, "width": 4, "height": 4},
+    "feather": 1}"#;
+
+#[test]
+fn pipeline_steps_run_in_order() {
+    let input = noise_image(8, 6);
+    let mirror = r#"{"plugin": "mirror_plugin",
+                     "params": {"horizontal": true}}"#;
+
+    let blurred = run(&input, &format!("[{BLUR_REGION_STEP}]"), None).unwrap();
+    let result =
+        run(&input, &format!("[{BLUR_REGION_STEP}, {mirror}]"), None).unwrap();
+
+    assert_eq!(result, image::imageops::flip_horizontal(&blurred));
+}
+
+#[test]
+fn region_outside_image_returns_error() {
+    let result = run(
+        &test_image(),
+        r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true},
+             "region": {"x": 2, "y": 2, "width": 3, "height": 1}}]"#,
+        None,
+    );
+
+    let err = result.unwrap_err();
+    assert!(matches!(err, AppError::InvalidRegion { .. }), "{err}");
+    assert!(err.to_string().contains("outside the 4x4 image"), "{err}");
+}
+
+#[test]
+fn process_bytes_runs_steps_in_memory() {
+    let image = noise_image(7, 5);
+    let steps = [
+        Step::new("mirror_plugin", serde_json::json!({"horizontal": true})),
+        Step::new("mirror_plugin", serde_json::json!({"vertical": true})),
+    ];
+
+    let output = process_bytes(&plugins(), &png(&image), &steps, None).unwrap();
+    assert_eq!(image::guess_format(&output).unwrap(), ImageFormat::Png);
+    let expected = image::imageops::flip_vertical(
+        &image::imageops::flip_horizontal(&image),
+    );
+    assert_eq!(
+        image::load_from_memory(&output).unwrap().into_rgba8(),
+        expected
+    );
+
+    let output =
+        process_bytes(&plugins(), &png(&image), &[], Some(ImageFormat::Jpeg))
+            .unwrap();
+    assert_eq!(image::guess_format(&output).unwrap(), ImageFormat::Jpeg);
+}
+
+#[test]
+fn process_rgba_matches_pipeline() {
+    let mut image = noise_image(8, 6);
+    let steps: Vec<Step> =
+        serde_json::from_str(&format!("[{BLUR_REGION_STEP}]")).unwrap();
+
+    process_rgba(&plugins(), &mut image, &steps, &[]).unwrap();
+
+    assert_eq!(
+        image,
+        run(&noise_image(8, 6), &format!("[{BLUR_REGION_STEP}]"), None)
+            .unwrap()
+    );
+}
+
+#[test]
+fn process_bytes_rejects_undecodable_input() {
+    let err =
+        process_bytes(&plugins(), b"not an image", &[], None).unwrap_err();
+    assert!(matches!(err, AppError::ImageDecode { .. }), "{err}");
+}
+
+/// Expected result of a [`cli`] run.
+#[derive(Clone, Copy)]
+enum Outcome {
+    Success,
+    Failure,
+}
+
+/// Helper: runs `image_processor` with the input/output set
+/// plus `args` and asserts the expected `outcome`.
+fn cli(input: &Path, output: &Path, args: &[&str], outcome: Outcome) -> Assert {
+    let assert = cargo_bin_cmd!("image_processor")
+        .arg("--input")
+        .arg(input)
+        .arg("--output")
+        .arg(output)
+        .args(args)
+        .assert();
+    match outcome {
+        Outcome::Success => assert.success(),
+        Outcome::Failure => assert.failure(),
+    }
+}
+
+/// Helper: runs `image_processor` like [`cli`], asserts success,
+/// and returns the output image.
+fn run_cli(input: &Path, output: &Path, args: &[&str]) -> RgbaImage {
+    cli(input, output, args, Outcome::Success);
+    read_image(output)
+}
+
+/// Decodes the image at `path`.
+fn read_image(path: &Path) -> RgbaImage {
+    ImageReader::open(path)
         .expect("failed to open output")
         .decode()
         .expect("failed to decode output")
         .into_rgba8()
 }
 
+/// Standard error of a finished CLI run.
+fn stderr_of(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+/// Runs `plugin` (e.g. `mirror_plugin@^1`) from `plugin_dir` with
+/// `{"horizontal": true}` on a 7x5 test image plus `args`; the
+/// output goes to `dir/out.png`.
+fn plugin_cli(
+    dir: &Path,
+    plugin_dir: &Path,
+    plugin: &str,
+    args: &[&str],
+    outcome: Outcome,
+) -> Assert {
+    let input = dir.join("input.png");
+    let params = dir.join("params.json");
+    noise_image(7, 5).save(&input).unwrap();
+    fs::write(&params, r#"{"horizontal": true}"#).unwrap();
+
+    let mut all_args = vec![
+        "--plugin",
+        plugin,
+        "--params",
+        params.to_str().unwrap(),
+        "--plugin-path",
+        plugin_dir.to_str().unwrap(),
+    ];
+    all_args.extend(args);
+    cli(&input, &dir.join("out.png"), &all_args, outcome)
+}
+
 #[test]
 #[ignore = "requires built plugin libraries (cargo build --workspace)"]
-fn mirror_horizontal_flips_pixels() {
+fn cli_runs_dynamic_plugins() {
     let dir = TempDir::new().unwrap();
     let input = dir.path().join("input.png");
-    let output = dir.path().join("output.png");
     let params = dir.path().join("params.json");
-
-    create_test_image(&input);
+    let pipeline = dir.path().join("pipeline.json");
+    noise_image(8, 6).save(&input).unwrap();
     fs::write(&params, r#"{"horizontal": true}"#).unwrap();
+    fs::write(&pipeline, format!(r#"{{"steps": [{BLUR_REGION_STEP}]}}"#))
+        .unwrap();
+    let plugin_path = plugin_dir();
+    let plugin_path = plugin_path.to_str().unwrap();
 
-    let result = run_and_load(&input, &output, "mirror_plugin", &params);
+    let mirrored = run_cli(
+        &input,
+        &dir.path().join("mirrored.png"),
+        &[
+            "--plugin",
+            "mirror_plugin",
+            "--params",
+            params.to_str().unwrap(),
+            "--plugin-path",
+            plugin_path,
+        ],
+    );
+    let blurred = run_cli(
+        &input,
+        &dir.path().join("blurred.png"),
+        &[
+            "--pipeline",
+            pipeline.to_str().unwrap(),
+            "--plugin-path",
+            plugin_path,
+        ],
+    );
 
-    // After horizontal flip of a 4x4 image:
-    // top-right quadrant should now be red (was top-left)
-    let top_right = result.get_pixel(3, 0);
-    assert_eq!(top_right, &Rgba([255, 0, 0, 255]));
+    let input = noise_image(8, 6);
+    let mirror = r#"[{"plugin": "mirror_plugin",
+                      "params": {"horizontal": true}}]"#;
+    assert_eq!(mirrored, run(&input, mirror, None).unwrap());
+    let steps = format!("[{BLUR_REGION_STEP}]");
+    assert_eq!(blurred, run(&input, &steps, None).unwrap());
+}
 
-    // top-left should now be blue (was top-right area)
-    let top_left = result.get_pixel(0, 0);
-    assert_eq!(top_left, &Rgba([0, 0, 255, 255]));
+#[test]
+#[cfg(all(feature = "builtin-mirror", feature = "builtin-blur"))]
+fn cli_uses_builtin_plugins_without_plugin_libraries() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.png");
+    let pipeline = dir.path().join("pipeline.json");
+    let output = dir.path().join("out.png");
+    noise_image(8, 6).save(&input).unwrap();
+    let steps = format!(
+        r#"[{BLUR_REGION_STEP},
+            {{"plugin": "mirror_plugin", "params": {{"horizontal": true}}}}]"#
+    );
+    fs::write(&pipeline, format!(r#"{{"steps": {steps}}}"#)).unwrap();
+
+    // Run from an empty directory, so the default plugin path
+    // has no libraries to load.
+    cargo_bin_cmd!("image_processor")
+        .current_dir(dir.path())
+        .arg("--input")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .arg("--pipeline")
+        .arg(&pipeline)
+        .assert()
+        .success();
+
+    let result = ImageReader::open(&output).unwrap().decode().unwrap();
+    let expected = run(&noise_image(8, 6), &steps, None).unwrap();
+    assert_eq!(result.into_rgba8(), expected);
+}
+
+/// Directory of the plugins built for `wasm32-unknown-unknown`.
+fn wasm_plugin_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../target/wasm32-unknown-unknown/debug")
+}
+
+/// Runs the binary with the built-in `plugin` and `params` on
+/// `input` plus `args`, and returns the output image.
+#[cfg(all(feature = "builtin-mirror", feature = "builtin-blur"))]
+fn run_builtin_cli(
+    input: &RgbaImage,
+    plugin: &str,
+    params: &str,
+    args: &[&str],
+) -> RgbaImage {
+    let dir = TempDir::new().unwrap();
+    let input_path = dir.path().join("input.png");
+    let params_path = dir.path().join("params.json");
+    input.save(&input_path).unwrap();
+    fs::write(&params_path, params).unwrap();
+
+    let mut all_args = vec![
+        "--plugin",
+        plugin,
+        "--params",
+        params_path.to_str().unwrap(),
+    ];
+    all_args.extend(args);
+    run_cli(&input_path, &dir.path().join("out.png"), &all_args)
 }
 
 #[test]
-#[ignore = "requires built plugin libraries (cargo build --workspace)"]
-fn mirror_vertical_flips_pixels() {
+#[cfg(all(feature = "builtin-mirror", feature = "builtin-blur"))]
+fn cli_blurs_only_inside_mask() {
+    let dir = TempDir::new().unwrap();
+    let mask_path = dir.path().join("mask.png");
+    let mask = RgbaImage::from_fn(8, 6, |x, _| {
+        let v = if x < 4 { 255 } else { 0 };
+        Rgba([v, v, v, 255])
+    });
+    mask.save(&mask_path).unwrap();
+    let input = noise_image(8, 6);
+    let params = r#"{"radius": 1, "mask": {}}"#;
+
+    let result = run_builtin_cli(
+        &input,
+        "blur_plugin",
+        params,
+        &["--mask", mask_path.to_str().unwrap()],
+    );
+
+    let steps = format!(r#"[{{"plugin": "blur_plugin", "params": {params}}}]"#);
+    assert_eq!(result, run(&input, &steps, Some(&mask)).unwrap());
+    assert_ne!(result, input);
+}
+
+#[test]
+#[cfg(all(feature = "builtin-mirror", feature = "builtin-blur"))]
+fn cli_limits_plugin_to_feathered_region() {
+    let input = noise_image(8, 6);
+    let result = run_builtin_cli(
+        &input,
+        "mirror_plugin",
+        r#"{"horizontal": true}"#,
+        &["--region", "1,1,5,3", "--feather", "1"],
+    );
+
+    let steps = r#"[{"plugin": "mirror_plugin", "params": {"horizontal": true},
+                     "region": {"x": 1, "y": 1, "width": 5, "height": 3},
+                     "feather": 1}]"#;
+    assert_eq!(result, run(&input, steps, None).unwrap());
+    assert_eq!(result.get_pixel(7, 5), input.get_pixel(7, 5));
+}
+
+#[test]
+#[cfg(all(feature = "builtin-mirror", feature = "builtin-blur"))]
+fn cli_tiled_blur_matches_whole_image() {
+    let input = noise_image(23, 17);
+    let params = r#"{"radius": 2, "iterations": 2}"#;
+
+    let tiled =
+        run_builtin_cli(&input, "blur_plugin", params, &["--tile-size", "4"]);
+
+    let steps = format!(r#"[{{"plugin": "blur_plugin", "params": {params}}}]"#);
+    assert_eq!(tiled, run(&input, &steps, None).unwrap());
+}
+
+#[test]
+#[ignore = "requires plugins built for wasm32-unknown-unknown \
+            (cargo build -p mirror_plugin -p blur_plugin -p panic_plugin \
+            --target wasm32-unknown-unknown)"]
+fn wasm_plugin_matches_builtin_plugin() {
     let dir = TempDir::new().unwrap();
     let input = dir.path().join("input.png");
-    let output = dir.path().join("output.png");
     let params = dir.path().join("params.json");
+    noise_image(7, 5).save(&input).unwrap();
+    fs::write(&params, r#"{"horizontal": true, "vertical": true}"#).unwrap();
 
-    create_test_image(&input);
-    fs::write(&params, r#"{"vertical": true}"#).unwrap();
+    let wasm_dir = wasm_plugin_dir();
+    let wasm = run_cli(
+        &input,
+        &dir.path().join("wasm.png"),
+        &[
+            "--plugin",
+            "mirror_plugin.wasm",
+            "--params",
+            params.to_str().unwrap(),
+            "--plugin-path",
+            wasm_dir.to_str().unwrap(),
+        ],
+    );
 
-    let result = run_and_load(&input, &output, "mirror_plugin", &params);
+    let builtin = run(
+        &noise_image(7, 5),
+        r#"[{"plugin": "mirror_plugin",
+             "params": {"horizontal": true, "vertical": true}}]"#,
+        None,
+    )
+    .unwrap();
+    assert_eq!(wasm, builtin);
+}
 
-    // After vertical flip:
-    // bottom-left quadrant should now be red (was top-left)
-    let bottom_left = result.get_pixel(0, 3);
-    assert_eq!(bottom_left, &Rgba([255, 0, 0, 255]));
+#[test]
+#[ignore = "requires plugins built for wasm32-unknown-unknown \
+            (cargo build -p mirror_plugin -p blur_plugin -p panic_plugin \
+            --target wasm32-unknown-unknown)"]
+fn wasm_sdk_plugins_report_results_errors_and_panics() {
+    let image = noise_image(9, 7);
+    let blur =
+        PluginLoader::load("blur_plugin.wasm", &wasm_plugin_dir()).unwrap();
+    let mut blurred = image.clone();
+    blur.process_image(9, 7, &mut blurred, r#"{"radius": 2}"#)
+        .unwrap();
+    let steps = r#"[{"plugin": "blur_plugin", "params": {"radius": 2}}]"#;
+    assert_eq!(blurred, run(&image, steps, None).unwrap());
 
-    // top-left should now be blue
-    let top_left = result.get_pixel(0, 0);
-    assert_eq!(top_left, &Rgba([0, 0, 255, 255]));
+    let err = blur
+        .process_image(9, 7, &mut blurred, r#"{"radius": "wide"}"#)
+        .unwrap_err();
+    assert!(matches!(err, AppError::PluginExec { code: 4 }), "{err}");
+
+    // Panics abort the module; the SDK's panic hook records the
+    // message for plugin_last_error before the trap.
+    let panicking =
+        PluginLoader::load("panic_plugin.wasm", &wasm_plugin_dir()).unwrap();
+    let err = panicking
+        .process_image(9, 7, &mut blurred, r#"{"message": "boom"}"#)
+        .unwrap_err();
+    assert!(
+        matches!(&err, AppError::PluginPanicked { message } if message == "boom"),
+        "{err}"
+    );
+}
+
+#[test]
+fn bundled_manifests_match_core_crate_versions() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    for (plugin, version) in [
+        ("mirror_plugin", mirror_core::VERSION),
+        ("blur_plugin", blur_core::VERSION),
+    ] {
+        let manifest =
+            PluginManifest::load(&root.join(plugin).join("plugin.toml"))
+                .unwrap();
+        assert_eq!(manifest.version, version, "{plugin}");
+    }
 }
 
 #[test]
 #[ignore = "requires built plugin libraries (cargo build --workspace)"]
-fn blur_modifies_image() {
+fn dynamic_plugins_report_core_crate_versions() {
+    for (plugin, version) in [
+        ("mirror_plugin", mirror_core::VERSION),
+        ("blur_plugin", blur_core::VERSION),
+    ] {
+        // Without a manifest, the requirement is checked against
+        // the library's descriptor.
+        PluginLoader::load(&format!("{plugin}@={version}"), &plugin_dir())
+            .unwrap();
+        let err =
+            PluginLoader::load(&format!("{plugin}@>{version}"), &plugin_dir())
+                .err()
+                .unwrap();
+        assert!(err.to_string().contains(version), "{err}");
+    }
+}
+
+/// Hex-encoded ed25519 secret key used to sign test plugins.
+const SIGNING_KEY: &str =
+    "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+/// Platform filename of the plugin library `name`.
+fn library_file(name: &str) -> String {
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+    format!("{DLL_PREFIX}{name}{DLL_SUFFIX}")
+}
+
+/// Runs `image_processor sign-plugin` for `plugin`, a library
+/// directly in its plugin directory, writing the key and trust
+/// store into `dir`; returns the trust store path.
+fn sign(dir: &Path, plugin: &Path) -> PathBuf {
+    let key = dir.join("signing.key");
+    let trust_store = dir.join("trust.json");
+    fs::write(&key, SIGNING_KEY).unwrap();
+
+    cargo_bin_cmd!("image_processor")
+        .arg("sign-plugin")
+        .arg(plugin)
+        .arg("--plugin-path")
+        .arg(plugin.parent().unwrap())
+        .arg("--key")
+        .arg(&key)
+        .arg("--trust-store")
+        .arg(&trust_store)
+        .assert()
+        .success();
+    trust_store
+}
+
+#[test]
+fn trust_store_is_checked_before_loading_library() {
+    let dir = TempDir::new().unwrap();
+    let plugins = dir.path().join("plugins");
+    fs::create_dir(&plugins).unwrap();
+    let plugin = plugins.join(library_file("mirror_plugin"));
+    fs::write(&plugin, b"not really a library").unwrap();
+    let trust_store = sign(dir.path(), &plugin);
+    let trust_store = trust_store.to_str().unwrap();
+
+    // Signed: passes verification, then fails to load as a library.
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin",
+        &["--trust-store", trust_store, "--require-signed"],
+        Outcome::Failure,
+    ));
+    assert!(stderr.contains("failed to load plugin"), "stderr: {stderr}");
+
+    // Modified after signing: rejected before loading.
+    fs::write(&plugin, b"tampered").unwrap();
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin",
+        &["--trust-store", trust_store],
+        Outcome::Failure,
+    ));
+    assert!(
+        stderr.contains("untrusted plugin") && stderr.contains("SHA-256"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn require_signed_needs_trust_store() {
+    let dir = TempDir::new().unwrap();
+    plugin_cli(
+        dir.path(),
+        dir.path(),
+        "mirror_plugin",
+        &["--require-signed"],
+        Outcome::Failure,
+    )
+    .code(2);
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn signed_dynamic_plugin_runs_with_require_signed() {
+    let dir = TempDir::new().unwrap();
+    let plugin = plugin_dir().join(library_file("mirror_plugin"));
+    let trust_store = sign(dir.path(), &plugin);
+    let trust_store = trust_store.to_str().unwrap();
+
+    plugin_cli(
+        dir.path(),
+        &plugin_dir(),
+        "mirror_plugin",
+        &["--trust-store", trust_store, "--require-signed"],
+        Outcome::Success,
+    );
+    assert_eq!(
+        read_image(&dir.path().join("out.png")),
+        image::imageops::flip_horizontal(&noise_image(7, 5))
+    );
+
+    // Plugins missing from the trust store are refused.
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugin_dir(),
+        "blur_plugin",
+        &["--trust-store", trust_store],
+        Outcome::Failure,
+    ));
+    assert!(stderr.contains("not listed in trust store"), "stderr: {stderr}");
+}
+
+/// Installs plugin `name` into `plugin_dir/name/`, or into
+/// `plugin_dir/name/<version>/` for a `version`, with the given
+/// manifest and library contents.
+fn install_plugin(
+    plugin_dir: &Path,
+    name: &str,
+    version: Option<&str>,
+    manifest: &str,
+    library: &[u8],
+) {
+    let mut dir = plugin_dir.join(name);
+    if let Some(version) = version {
+        dir.push(version);
+    }
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("plugin.toml"), manifest).unwrap();
+    fs::write(dir.join(library_file(name)), library).unwrap();
+}
+
+/// Manifest shipped with the `mirror_plugin` crate, with its
+/// version set to `version`.
+fn mirror_manifest(version: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../mirror_plugin/plugin.toml");
+    fs::read_to_string(path)
+        .unwrap()
+        .replace("version = \"0.1.0\"", &format!("version = \"{version}\""))
+}
+
+#[test]
+fn list_plugins_reads_manifests_without_loading_libraries() {
+    let dir = TempDir::new().unwrap();
+    let manifest = mirror_manifest("0.1.0");
+    install_plugin(dir.path(), "mirror_plugin", None, &manifest, b"");
+
+    let assert = cargo_bin_cmd!("image_processor")
+        .arg("list-plugins")
+        .arg("--plugin-path")
+        .arg(dir.path())
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("mirror_plugin 0.1.0\t"), "stdout: {stdout}");
+    if cfg!(feature = "builtin-blur") {
+        assert!(
+            stdout.contains("blur_plugin 0.1.0\t(built-in)"),
+            "stdout: {stdout}"
+        );
+    }
+}
+
+#[test]
+fn incompatible_manifest_is_rejected_before_loading_library() {
+    let dir = TempDir::new().unwrap();
+    let plugins = dir.path().join("plugins");
+    let manifest =
+        mirror_manifest("0.1.0").replace("abi_version = 1", "abi_version = 2");
+    install_plugin(
+        &plugins,
+        "mirror_plugin",
+        None,
+        &manifest,
+        b"not a library",
+    );
+
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin",
+        &[],
+        Outcome::Failure,
+    ));
+    assert!(
+        stderr.contains("not supported by this host")
+            && stderr.contains("ABI version 2"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn manifest_is_checked_against_library_descriptor() {
     let dir = TempDir::new().unwrap();
     let input = dir.path().join("input.png");
-    let output = dir.path().join("output.png");
     let params = dir.path().join("params.json");
+    let plugins = dir.path().join("plugins");
+    noise_image(7, 5).save(&input).unwrap();
+    fs::write(&params, r#"{"vertical": true}"#).unwrap();
+    let library =
+        fs::read(plugin_dir().join(library_file("mirror_plugin"))).unwrap();
 
-    create_test_image(&input);
-    fs::write(&params, r#"{"radius": 1, "iterations": 1}"#).unwrap();
+    let manifest = mirror_manifest("0.1.0");
+    install_plugin(&plugins, "mirror_plugin", None, &manifest, &library);
+    let result = run_cli(
+        &input,
+        &dir.path().join("out.png"),
+        &[
+            "--plugin",
+            "mirror_plugin",
+            "--params",
+            params.to_str().unwrap(),
+            "--plugin-path",
+            plugins.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(result, image::imageops::flip_vertical(&noise_image(7, 5)));
 
-    let original = ImageReader::open(&input)
-        .unwrap()
-        .decode()
-        .unwrap()
-        .into_rgba8();
+    let manifest = mirror_manifest("9.9.9");
+    install_plugin(&plugins, "mirror_plugin", None, &manifest, &library);
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin",
+        &[],
+        Outcome::Failure,
+    ));
+    assert!(
+        stderr.contains("does not match its manifest")
+            && stderr.contains("version: manifest says 9.9.9"),
+        "stderr: {stderr}"
+    );
+}
 
-    let result = run_and_load(&input, &output, "blur_plugin", &params);
+#[test]
+fn missing_plugin_version_lists_available_versions() {
+    let dir = TempDir::new().unwrap();
+    let plugins = dir.path().join("plugins");
+    let manifest = mirror_manifest("0.2.0");
+    install_plugin(
+        &plugins,
+        "mirror_plugin",
+        Some("0.2.0"),
+        &manifest,
+        b"not a library",
+    );
+    let manifest = mirror_manifest("1.0.0");
+    install_plugin(
+        &plugins,
+        "mirror_plugin",
+        Some("1.0.0"),
+        &manifest,
+        b"not a library",
+    );
 
-    // Blurred image should differ from original
-    // (boundary between red and blue areas gets mixed)
-    assert_ne!(original, result);
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin@>=3, <4",
+        &[],
+        Outcome::Failure,
+    ));
+    assert!(
+        stderr.contains("matches '>=3, <4' (available: 0.2.0, 1.0.0"),
+        "stderr: {stderr}"
+    );
+
+    // The built-in plugin is the only 0.1 version.
+    let builtin = cfg!(feature = "builtin-mirror");
+    plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin@~0.1",
+        &[],
+        if builtin {
+            Outcome::Success
+        } else {
+            Outcome::Failure
+        },
+    );
+    if builtin {
+        assert_eq!(
+            read_image(&dir.path().join("out.png")),
+            image::imageops::flip_horizontal(&noise_image(7, 5))
+        );
+    }
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn highest_matching_plugin_version_is_loaded() {
+    let dir = TempDir::new().unwrap();
+    let plugins = dir.path().join("plugins");
+    let library =
+        fs::read(plugin_dir().join(library_file("mirror_plugin"))).unwrap();
+    let manifest = mirror_manifest("0.1.0");
+    install_plugin(
+        &plugins,
+        "mirror_plugin",
+        Some("0.1.0"),
+        &manifest,
+        &library,
+    );
+    let manifest = mirror_manifest("0.2.0");
+    install_plugin(
+        &plugins,
+        "mirror_plugin",
+        Some("0.2.0"),
+        &manifest,
+        b"not a library",
+    );
+
+    // The highest version is chosen by default...
+    let stderr = stderr_of(&plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin",
+        &[],
+        Outcome::Failure,
+    ));
+    assert!(stderr.contains("0.2.0"), "stderr: {stderr}");
+
+    // ...and the requirement pins the working one.
+    plugin_cli(
+        dir.path(),
+        &plugins,
+        "mirror_plugin@<0.2",
+        &[],
+        Outcome::Success,
+    );
+}
+
+#[test]
+#[ignore = "requires built plugin libraries (cargo build --workspace)"]
+fn copied_library_is_reloaded_after_rebuild() {
+    let dir = TempDir::new().unwrap();
+    let library = dir.path().join(library_file("mirror_plugin"));
+    fs::copy(plugin_dir().join(library_file("mirror_plugin")), &library)
+        .unwrap();
+    let mut plugins = PluginRegistry::new(dir.path());
+    plugins.set_copy_libraries(true);
+    let mirror = plugins.get("mirror_plugin").unwrap();
+
+    // "Rebuild" the library in place while the old one is loaded.
+    fs::copy(plugin_dir().join(library_file("panic_plugin")), &library)
+        .unwrap();
+    let rebuilt = plugins.get("mirror_plugin").unwrap();
+
+    let mut image = noise_image(7, 5);
+    mirror
+        .process_image(7, 5, &mut image, r#"{"horizontal": true}"#)
+        .unwrap();
+    assert_eq!(image, image::imageops::flip_horizontal(&noise_image(7, 5)));
+    assert!(matches!(
+        rebuilt.process_image(7, 5, &mut image, "{}"),
+        Err(AppError::PluginPanicked { .. })
+    ));
+}
+
+#[test]
+#[cfg(feature = "builtin-mirror")]
+fn watch_mode_processes_again_when_params_change() {
+    use std::process::{Command, Stdio};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Waits up to 30 seconds for `path` to hold `expected`.
+    fn wait_for_image(path: &Path, expected: &RgbaImage) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while Instant::now() < deadline {
+            if let Ok(image) = image::open(path)
+                && image.into_rgba8() == *expected
+            {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.png");
+    let params = dir.path().join("params.json");
+    let output = dir.path().join("out.png");
+    noise_image(7, 5).save(&input).unwrap();
+    fs::write(&params, r#"{"horizontal": true}"#).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_image_processor"))
+        .current_dir(dir.path())
+        .arg("--input")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .arg("--plugin")
+        .arg("mirror_plugin")
+        .arg("--params")
+        .arg(&params)
+        .arg("--watch")
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let horizontal = wait_for_image(
+        &output,
+        &image::imageops::flip_horizontal(&noise_image(7, 5)),
+    );
+    fs::write(&params, r#"{"vertical": true}"#).unwrap();
+    let vertical = wait_for_image(
+        &output,
+        &image::imageops::flip_vertical(&noise_image(7, 5)),
+    );
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(horizontal, "first run did not finish");
+    assert!(vertical, "params change was not picked up");
 }
 
 #[test]
@@ -150,7 +1406,6 @@ fn missing_input_file_returns_error() {
 }
 
 #[test]
-#[ignore = "requires built plugin libraries (cargo build --workspace)"]
 fn missing_plugin_returns_error() {
     let dir = TempDir::new().unwrap();
     let input = dir.path().join("input.png");
@@ -195,3 +1450,305 @@ fn missing_params_file_returns_error() {
         .assert()
         .failure();
 }
+
+/// Encodes `image` as PNG.
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+/// Sends an HTTP request to `addr`, returning the response
+/// status and body.
+fn http(
+    addr: SocketAddr,
+    method: &str,
+    target: &str,
+    body: &[u8],
+) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{method} {target} HTTP/1.1\r\nHost: localhost\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let head_len = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .expect("incomplete response")
+        + 4;
+    let head = String::from_utf8_lossy(&response[..head_len]);
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, response[head_len..].to_vec())
+}
+
+/// Test double recording the most calls in progress at once.
+struct Overlapping {
+    thread_safe: bool,
+    active: Arc<AtomicUsize>,
+    max_active: Arc<AtomicUsize>,
+}
+
+impl ImagePlugin for Overlapping {
+    fn supports_aux_inputs(&self) -> bool {
+        false
+    }
+
+    fn thread_safe(&self) -> bool {
+        self.thread_safe
+    }
+
+    fn tile_halo(&self, _params: &str) -> Result<Option<u32>, AppError> {
+        Ok(None)
+    }
+
+    fn process_view(
+        &self,
+        _image: ImageViewMut<'_>,
+        _params: &str,
+        _aux: &[AuxInput<'_>],
+    ) -> Result<(), AppError> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_active.fetch_max(active, Ordering::SeqCst);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Serves `names` from `plugins` on a free localhost port while
+/// `test` runs.
+fn with_server(
+    plugins: &PluginRegistry,
+    names: &[&str],
+    test: impl FnOnce(SocketAddr),
+) {
+    let names: Vec<String> = names.iter().map(ToString::to_string).collect();
+    let server = Server::bind("127.0.0.1:0", plugins, &names).unwrap();
+    std::thread::scope(|scope| {
+        scope.spawn(|| server.run(NonZeroUsize::new(4).unwrap()));
+        // Stop the server even if `test` fails, or the scope
+        // would wait for it forever.
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                test(server.local_addr());
+            }));
+        server.stop();
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
+        }
+    });
+}
+
+#[test]
+fn server_processes_posted_images() {
+    let image = noise_image(7, 5);
+    with_server(&plugins(), &["mirror_plugin", "panicking"], |addr| {
+        let (status, body) = http(
+            addr,
+            "POST",
+            "/process/mirror_plugin?horizontal=true",
+            &png(&image),
+        );
+        assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+        let result = image::load_from_memory(&body).unwrap().into_rgba8();
+        assert_eq!(result, image::imageops::flip_horizontal(&image));
+
+        let (status, body) = http(
+            addr,
+            "POST",
+            "/process/mirror_plugin?params=%7B%22vertical%22%3Atrue%7D&format=bmp",
+            &png(&image),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(image::guess_format(&body).unwrap(), ImageFormat::Bmp);
+        let result = image::load_from_memory(&body).unwrap().into_rgba8();
+        assert_eq!(result, image::imageops::flip_vertical(&image));
+
+        let (status, body) = http(addr, "GET", "/plugins", b"");
+        assert_eq!(status, 200);
+        let list: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list["plugins"][0]["name"], "mirror_plugin");
+    });
+}
+
+#[test]
+fn server_serves_plugins_by_name() {
+    let image = noise_image(7, 5);
+    with_server(&plugins(), &["mirror_plugin@^0.1"], |addr| {
+        let (status, body) = http(
+            addr,
+            "POST",
+            "/process/mirror_plugin?horizontal=true",
+            &png(&image),
+        );
+        assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+
+        let (_, body) = http(addr, "GET", "/plugins", b"");
+        let list: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list["plugins"][0]["name"], "mirror_plugin");
+    });
+
+    let names = ["mirror_plugin".to_owned(), "mirror_plugin@^0.1".to_owned()];
+    let err = Server::bind("127.0.0.1:0", &plugins(), &names)
+        .err()
+        .unwrap();
+    assert!(matches!(err, AppError::InvalidPluginSpec { .. }), "{err}");
+    assert!(err.to_string().contains("already served"), "{err}");
+}
+
+#[test]
+fn server_maps_errors_to_status_codes() {
+    let image = png(&noise_image(7, 5));
+    with_server(&plugins(), &["mirror_plugin", "panicking"], |addr| {
+        for (method, target, body, expected) in [
+            (
+                "POST",
+                "/process/mirror_plugin?horizontal=%22yes%22",
+                &image[..],
+                400,
+            ),
+            ("POST", "/process/mirror_plugin", b"not an image", 400),
+            ("POST", "/process/mirror_plugin?format=nope", &image[..], 415),
+            ("POST", "/process/blur_plugin", &image[..], 404),
+            ("GET", "/process/mirror_plugin", b"", 405),
+            ("GET", "/nothing", b"", 404),
+            ("POST", "/process/panicking?message=boom", &image[..], 500),
+        ] {
+            let (status, body) = http(addr, method, target, body);
+            assert_eq!(status, expected, "{method} {target}");
+            let error: serde_json::Value =
+                serde_json::from_slice(&body).unwrap();
+            assert!(error["error"].is_string(), "{method} {target}");
+        }
+    });
+}
+
+#[test]
+fn server_serializes_plugins_that_are_not_thread_safe() {
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+    let mut plugins = PluginRegistry::new(plugin_dir());
+    plugins.register(
+        "serial",
+        Overlapping {
+            thread_safe: false,
+            active: Arc::clone(&active),
+            max_active: Arc::clone(&max_active),
+        },
+    );
+
+    let image = png(&noise_image(4, 4));
+    with_server(&plugins, &["serial"], |addr| {
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(
+                        http(addr, "POST", "/process/serial", &image).0,
+                        200
+                    );
+                });
+            }
+        });
+    });
+    assert_eq!(max_active.load(Ordering::SeqCst), 1);
+}
+
+/// Runs `image_processor serve` on a free port in `dir` with
+/// the extra `args`, calls `test` with its address and stops
+/// it.
+fn with_serve_command(
+    dir: &Path,
+    args: &[&str],
+    test: impl FnOnce(SocketAddr),
+) {
+    use std::io::BufRead as _;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_image_processor"))
+        .current_dir(dir)
+        .args(["serve", "--bind", "127.0.0.1:0"])
+        .args(args)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut line = String::new();
+    std::io::BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on http://")
+        .map(|addr| addr.parse().unwrap());
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        test(addr.unwrap_or_else(|| panic!("unexpected output: {line}")));
+    }));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+#[test]
+#[cfg(feature = "builtin-mirror")]
+fn serve_command_listens_on_localhost() {
+    let dir = TempDir::new().unwrap();
+    let args = [
+        "--plugin",
+        "mirror_plugin",
+        "--wasm-memory-mib",
+        "16",
+        "--wasm-fuel",
+        "1000000",
+    ];
+    with_serve_command(dir.path(), &args, |addr| {
+        let (status, body) = http(
+            addr,
+            "POST",
+            "/process/mirror_plugin?vertical=true",
+            &png(&noise_image(7, 5)),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(
+            image::load_from_memory(&body).unwrap().into_rgba8(),
+            image::imageops::flip_vertical(&noise_image(7, 5))
+        );
+    });
+}
+
+#[test]
+fn serve_command_only_serves_builtin_plugins_by_default() {
+    let dir = TempDir::new().unwrap();
+    let manifest = mirror_manifest("0.1.0").replace("mirror_plugin", "other");
+    install_plugin(dir.path(), "other", None, &manifest, b"not a library");
+
+    let plugin_path = dir.path().to_str().unwrap();
+    with_serve_command(dir.path(), &["--plugin-path", plugin_path], |addr| {
+        let (status, body) = http(addr, "GET", "/plugins", b"");
+        assert_eq!(status, 200);
+        let list: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let names: Vec<&str> = list["plugins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|plugin| plugin["name"].as_str().unwrap())
+            .collect();
+        let mut builtins = Vec::new();
+        if cfg!(feature = "builtin-blur") {
+            builtins.push("blur_plugin");
+        }
+        if cfg!(feature = "builtin-mirror") {
+            builtins.push("mirror_plugin");
+        }
+        assert_eq!(names, builtins);
+    });
+}
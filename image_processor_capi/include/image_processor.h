@@ -0,0 +1,200 @@
+#ifndef IMAGE_PROCESSOR_H
+#define IMAGE_PROCESSOR_H
+
+/* Generated by cbindgen from image_processor_capi/src/lib.rs; do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The call succeeded.
+ */
+#define IP_OK 0
+
+/**
+ * A required pointer argument was null.
+ */
+#define IP_ERROR_NULL_POINTER 1
+
+/**
+ * A string argument was not valid UTF-8, or an output format
+ * was not recognized.
+ */
+#define IP_ERROR_INVALID_ARGUMENT 2
+
+/**
+ * Loading a plugin or processing the image failed; see
+ * [`ip_last_error`].
+ */
+#define IP_ERROR_FAILED 3
+
+/**
+ * The host (or a built-in plugin) panicked.
+ */
+#define IP_ERROR_PANICKED 99
+
+/**
+ * Loaded plugin, kept alive until [`ip_plugin_free`].
+ */
+typedef struct IpPlugin IpPlugin;
+
+/**
+ * Plugin registry: resolves plugin names to plugins loaded
+ * from a directory, falling back to the built-in plugins.
+ *
+ * A registry may be shared between threads as long as none of
+ * them changes it (e.g. with [`ip_registry_set_trust_store`]).
+ */
+typedef struct IpRegistry IpRegistry;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a registry loading plugins from `plugin_dir`, with
+ * the built-in plugins registered. Returns null on failure.
+ *
+ * # Safety
+ *
+ * `plugin_dir` must be null or a NUL-terminated string.
+ */
+IpRegistry *ip_registry_new(const char *plugin_dir);
+
+/**
+ * Frees a registry. Plugins loaded from it stay usable.
+ *
+ * # Safety
+ *
+ * `registry` must be null or a pointer returned by
+ * [`ip_registry_new`] that has not been freed.
+ */
+void ip_registry_free(IpRegistry *registry);
+
+/**
+ * Only loads plugin files listed in the trust store at
+ * `path` from now on; with `require_signed`, only those
+ * signed by one of its keys.
+ *
+ * # Safety
+ *
+ * `registry` must be a live registry and `path` a
+ * NUL-terminated string.
+ */
+int ip_registry_set_trust_store(IpRegistry *registry, const char *path, bool require_signed);
+
+/**
+ * Loads and validates the plugin `name` (e.g. `blur_plugin`
+ * or `blur_plugin@^1.2`) and stores it in `*plugin`.
+ *
+ * # Safety
+ *
+ * `registry` must be a live registry, `name` a NUL-terminated
+ * string and `plugin` valid for writes.
+ */
+int ip_plugin_load(const IpRegistry *registry, const char *name, IpPlugin **plugin);
+
+/**
+ * Frees a plugin handle.
+ *
+ * # Safety
+ *
+ * `plugin` must be null or a pointer stored by
+ * [`ip_plugin_load`] that has not been freed.
+ */
+void ip_plugin_free(IpPlugin *plugin);
+
+/**
+ * Returns `true` if the plugin may process several images at
+ * once from different threads.
+ *
+ * # Safety
+ *
+ * `plugin` must be null or a live plugin handle.
+ */
+bool ip_plugin_thread_safe(const IpPlugin *plugin);
+
+/**
+ * Runs a plugin in place on the `width x height` RGBA image
+ * at `rgba_data`, whose rows start `stride_bytes` apart
+ * (`width * 4` for a packed image) within `len` bytes.
+ *
+ * # Safety
+ *
+ * `plugin` must be a live plugin handle, `rgba_data` valid for
+ * reads and writes of `len` bytes and `params` a
+ * NUL-terminated string.
+ */
+int ip_plugin_process(const IpPlugin *plugin,
+                      uint8_t *rgba_data,
+                      size_t len,
+                      uint32_t width,
+                      uint32_t height,
+                      size_t stride_bytes,
+                      const char *params);
+
+/**
+ * Runs a JSON pipeline (`{"steps": [...]}`, as in pipeline
+ * files) in place on the packed `width x height` RGBA image at
+ * `rgba_data`, which must be `width * height * 4` bytes.
+ *
+ * # Safety
+ *
+ * `registry` must be a live registry, `pipeline_json` a
+ * NUL-terminated string and `rgba_data` valid for reads and
+ * writes of `len` bytes.
+ */
+int ip_pipeline_run(const IpRegistry *registry,
+                    const char *pipeline_json,
+                    uint8_t *rgba_data,
+                    size_t len,
+                    uint32_t width,
+                    uint32_t height);
+
+/**
+ * Decodes the encoded image (PNG, JPEG, ...) at `input`, runs
+ * a JSON pipeline on it and encodes the result in `format`
+ * (a file extension such as `"png"`, or null for the input
+ * format).
+ *
+ * The result is stored in `*output` and `*output_len` and
+ * must be freed with [`ip_buffer_free`].
+ *
+ * # Safety
+ *
+ * `registry` must be a live registry, `input` valid for reads
+ * of `input_len` bytes, `pipeline_json` a NUL-terminated
+ * string, `format` null or a NUL-terminated string, and
+ * `output` and `output_len` valid for writes.
+ */
+int ip_process_bytes(const IpRegistry *registry,
+                     const uint8_t *input,
+                     size_t input_len,
+                     const char *pipeline_json,
+                     const char *format,
+                     uint8_t **output,
+                     size_t *output_len);
+
+/**
+ * Frees a buffer returned by [`ip_process_bytes`].
+ *
+ * # Safety
+ *
+ * `data` must be null or a buffer returned by
+ * [`ip_process_bytes`] with its length `len`, not yet freed.
+ */
+void ip_buffer_free(uint8_t *data, size_t len);
+
+/**
+ * Returns the message of the last failed call on the calling
+ * thread, or null if there is none. The string stays valid
+ * until the next `ip_*` call on that thread.
+ */
+const char *ip_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* IMAGE_PROCESSOR_H */
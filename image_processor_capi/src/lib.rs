@@ -0,0 +1,419 @@
+//! C API for the image processor host: plugin discovery,
+//! validation and pipelines for applications not written in
+//! Rust. `include/image_processor.h` declares it.
+//!
+//! Functions return [`IP_OK`] or an `IP_ERROR_*` code; the
+//! message of the last failure on the calling thread is
+//! available from [`ip_last_error`]. Panics are caught at the
+//! boundary and reported as [`IP_ERROR_PANICKED`].
+
+use std::ffi::{CStr, c_char, c_int};
+use std::path::Path;
+use std::sync::Arc;
+
+use image::{ImageBuffer, ImageFormat};
+use image_processor::builtin::register_builtins;
+use image_processor::error::AppError;
+use image_processor::image_view::ImageViewMut;
+use image_processor::pipeline::Pipeline;
+use image_processor::plugin::ImagePlugin;
+use image_processor::registry::PluginRegistry;
+use image_processor::trust::TrustStore;
+use image_processor::{process_bytes, process_rgba};
+use plugin_sdk::ffi::{CallError, last_error};
+
+/// The call succeeded.
+pub const IP_OK: c_int = 0;
+
+/// A required pointer argument was null.
+pub const IP_ERROR_NULL_POINTER: c_int = 1;
+
+/// A string argument was not valid UTF-8, or an output format
+/// was not recognized.
+pub const IP_ERROR_INVALID_ARGUMENT: c_int = 2;
+
+/// Loading a plugin or processing the image failed; see
+/// [`ip_last_error`].
+pub const IP_ERROR_FAILED: c_int = 3;
+
+/// The host (or a built-in plugin) panicked.
+pub const IP_ERROR_PANICKED: c_int = 99;
+
+/// Plugin registry: resolves plugin names to plugins loaded
+/// from a directory, falling back to the built-in plugins.
+///
+/// A registry may be shared between threads as long as none of
+/// them changes it (e.g. with [`ip_registry_set_trust_store`]).
+pub struct IpRegistry {
+    plugins: PluginRegistry,
+}
+
+/// Loaded plugin, kept alive until [`ip_plugin_free`].
+pub struct IpPlugin {
+    plugin: Arc<dyn ImagePlugin>,
+}
+
+/// Error reported by a C API call.
+struct Failure {
+    code: c_int,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: c_int, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl CallError for Failure {
+    const PANICKED: c_int = IP_ERROR_PANICKED;
+
+    fn code(&self) -> c_int {
+        self.code
+    }
+
+    fn into_message(self) -> Option<String> {
+        Some(self.message)
+    }
+}
+
+impl From<AppError> for Failure {
+    fn from(err: AppError) -> Self {
+        Self::new(IP_ERROR_FAILED, err.to_string())
+    }
+}
+
+/// Creates a registry loading plugins from `plugin_dir`, with
+/// the built-in plugins registered. Returns null on failure.
+///
+/// # Safety
+///
+/// `plugin_dir` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_registry_new(
+    plugin_dir: *const c_char,
+) -> *mut IpRegistry {
+    let mut registry = std::ptr::null_mut();
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let plugin_dir = unsafe { str_arg(plugin_dir, "plugin_dir") }?;
+        let mut plugins = PluginRegistry::new(plugin_dir);
+        register_builtins(&mut plugins);
+        registry = Box::into_raw(Box::new(IpRegistry { plugins }));
+        Ok(())
+    });
+    registry
+}
+
+/// Frees a registry. Plugins loaded from it stay usable.
+///
+/// # Safety
+///
+/// `registry` must be null or a pointer returned by
+/// [`ip_registry_new`] that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_registry_free(registry: *mut IpRegistry) {
+    if !registry.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe { Box::from_raw(registry) });
+    }
+}
+
+/// Only loads plugin files listed in the trust store at
+/// `path` from now on; with `require_signed`, only those
+/// signed by one of its keys.
+///
+/// # Safety
+///
+/// `registry` must be a live registry and `path` a
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_registry_set_trust_store(
+    registry: *mut IpRegistry,
+    path: *const c_char,
+    require_signed: bool,
+) -> c_int {
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let registry = unsafe { registry.as_mut() }
+            .ok_or_else(|| null_pointer("registry"))?;
+        // SAFETY: guaranteed by the caller.
+        let path = unsafe { str_arg(path, "path") }?;
+        let mut trust_store = TrustStore::load(Path::new(path))?;
+        trust_store.set_require_signed(require_signed);
+        registry.plugins.set_trust_store(trust_store);
+        Ok(())
+    })
+}
+
+/// Loads and validates the plugin `name` (e.g. `blur_plugin`
+/// or `blur_plugin@^1.2`) and stores it in `*plugin`.
+///
+/// # Safety
+///
+/// `registry` must be a live registry, `name` a NUL-terminated
+/// string and `plugin` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_plugin_load(
+    registry: *const IpRegistry,
+    name: *const c_char,
+    plugin: *mut *mut IpPlugin,
+) -> c_int {
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let registry = unsafe { registry.as_ref() }
+            .ok_or_else(|| null_pointer("registry"))?;
+        // SAFETY: guaranteed by the caller.
+        let name = unsafe { str_arg(name, "name") }?;
+        if plugin.is_null() {
+            return Err(null_pointer("plugin"));
+        }
+        let loaded = registry.plugins.get(name)?;
+        // SAFETY: checked for null above, valid per the caller.
+        unsafe {
+            *plugin = Box::into_raw(Box::new(IpPlugin { plugin: loaded }));
+        }
+        Ok(())
+    })
+}
+
+/// Frees a plugin handle.
+///
+/// # Safety
+///
+/// `plugin` must be null or a pointer stored by
+/// [`ip_plugin_load`] that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_plugin_free(plugin: *mut IpPlugin) {
+    if !plugin.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe { Box::from_raw(plugin) });
+    }
+}
+
+/// Returns `true` if the plugin may process several images at
+/// once from different threads.
+///
+/// # Safety
+///
+/// `plugin` must be null or a live plugin handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_plugin_thread_safe(
+    plugin: *const IpPlugin,
+) -> bool {
+    // SAFETY: guaranteed by the caller.
+    unsafe { plugin.as_ref() }.is_some_and(|p| p.plugin.thread_safe())
+}
+
+/// Runs a plugin in place on the `width x height` RGBA image
+/// at `rgba_data`, whose rows start `stride_bytes` apart
+/// (`width * 4` for a packed image) within `len` bytes.
+///
+/// # Safety
+///
+/// `plugin` must be a live plugin handle, `rgba_data` valid for
+/// reads and writes of `len` bytes and `params` a
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_plugin_process(
+    plugin: *const IpPlugin,
+    rgba_data: *mut u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    stride_bytes: usize,
+    params: *const c_char,
+) -> c_int {
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let plugin =
+            unsafe { plugin.as_ref() }.ok_or_else(|| null_pointer("plugin"))?;
+        // SAFETY: guaranteed by the caller.
+        let params = unsafe { str_arg(params, "params") }?;
+        // SAFETY: guaranteed by the caller.
+        let data = unsafe { buffer_arg(rgba_data, len, "rgba_data") }?;
+        let image =
+            ImageViewMut::with_stride(data, width, height, stride_bytes)?;
+        plugin.plugin.process_view(image, params, &[])?;
+        Ok(())
+    })
+}
+
+/// Runs a JSON pipeline (`{"steps": [...]}`, as in pipeline
+/// files) in place on the packed `width x height` RGBA image at
+/// `rgba_data`, which must be `width * height * 4` bytes.
+///
+/// # Safety
+///
+/// `registry` must be a live registry, `pipeline_json` a
+/// NUL-terminated string and `rgba_data` valid for reads and
+/// writes of `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_pipeline_run(
+    registry: *const IpRegistry,
+    pipeline_json: *const c_char,
+    rgba_data: *mut u8,
+    len: usize,
+    width: u32,
+    height: u32,
+) -> c_int {
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let registry = unsafe { registry.as_ref() }
+            .ok_or_else(|| null_pointer("registry"))?;
+        // SAFETY: guaranteed by the caller.
+        let pipeline = unsafe { str_arg(pipeline_json, "pipeline_json") }?;
+        let pipeline = Pipeline::from_json(pipeline)?;
+        // SAFETY: guaranteed by the caller.
+        let data = unsafe { buffer_arg(rgba_data, len, "rgba_data") }?;
+        // Checks the buffer even if no step would.
+        ImageViewMut::new(data, width, height)?;
+        let Some(mut image) = ImageBuffer::from_raw(width, height, data) else {
+            unreachable!("buffer size checked above");
+        };
+        process_rgba(&registry.plugins, &mut image, &pipeline.steps, &[])?;
+        Ok(())
+    })
+}
+
+/// Decodes the encoded image (PNG, JPEG, ...) at `input`, runs
+/// a JSON pipeline on it and encodes the result in `format`
+/// (a file extension such as `"png"`, or null for the input
+/// format).
+///
+/// The result is stored in `*output` and `*output_len` and
+/// must be freed with [`ip_buffer_free`].
+///
+/// # Safety
+///
+/// `registry` must be a live registry, `input` valid for reads
+/// of `input_len` bytes, `pipeline_json` a NUL-terminated
+/// string, `format` null or a NUL-terminated string, and
+/// `output` and `output_len` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_process_bytes(
+    registry: *const IpRegistry,
+    input: *const u8,
+    input_len: usize,
+    pipeline_json: *const c_char,
+    format: *const c_char,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> c_int {
+    guard(|| {
+        // SAFETY: guaranteed by the caller.
+        let registry = unsafe { registry.as_ref() }
+            .ok_or_else(|| null_pointer("registry"))?;
+        if input.is_null() {
+            return Err(null_pointer("input"));
+        }
+        // SAFETY: checked for null above, valid per the caller.
+        let input = unsafe { std::slice::from_raw_parts(input, input_len) };
+        // SAFETY: guaranteed by the caller.
+        let pipeline = unsafe { str_arg(pipeline_json, "pipeline_json") }?;
+        let pipeline = Pipeline::from_json(pipeline)?;
+        let format = if format.is_null() {
+            None
+        } else {
+            // SAFETY: guaranteed by the caller.
+            let format = unsafe { str_arg(format, "format") }?;
+            Some(ImageFormat::from_extension(format).ok_or_else(|| {
+                Failure::new(
+                    IP_ERROR_INVALID_ARGUMENT,
+                    format!("unsupported image format '{format}'"),
+                )
+            })?)
+        };
+        if output.is_null() || output_len.is_null() {
+            return Err(null_pointer("output"));
+        }
+
+        let encoded =
+            process_bytes(&registry.plugins, input, &pipeline.steps, format)?
+                .into_boxed_slice();
+        // SAFETY: checked for null above, valid per the caller.
+        unsafe {
+            *output_len = encoded.len();
+            *output = Box::into_raw(encoded).cast();
+        }
+        Ok(())
+    })
+}
+
+/// Frees a buffer returned by [`ip_process_bytes`].
+///
+/// # Safety
+///
+/// `data` must be null or a buffer returned by
+/// [`ip_process_bytes`] with its length `len`, not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ip_buffer_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe {
+            Box::from_raw(std::ptr::slice_from_raw_parts_mut(data, len))
+        });
+    }
+}
+
+/// Returns the message of the last failed call on the calling
+/// thread, or null if there is none. The string stays valid
+/// until the next `ip_*` call on that thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn ip_last_error() -> *const c_char {
+    last_error()
+}
+
+/// Runs `body` through the SDK's [`plugin_sdk::ffi::guard`],
+/// which catches panics, records the error message and maps
+/// the outcome to a return code.
+fn guard(body: impl FnOnce() -> Result<(), Failure>) -> c_int {
+    plugin_sdk::ffi::guard(body)
+}
+
+fn null_pointer(name: &str) -> Failure {
+    Failure::new(IP_ERROR_NULL_POINTER, format!("'{name}' is null"))
+}
+
+/// Borrows a NUL-terminated UTF-8 string argument.
+///
+/// # Safety
+///
+/// `ptr` must be null or a NUL-terminated string outliving the
+/// call.
+unsafe fn str_arg<'a>(
+    ptr: *const c_char,
+    name: &str,
+) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err(null_pointer(name));
+    }
+    // SAFETY: guaranteed by the caller.
+    unsafe { CStr::from_ptr(ptr) }.to_str().map_err(|_| {
+        Failure::new(
+            IP_ERROR_INVALID_ARGUMENT,
+            format!("'{name}' is not valid UTF-8"),
+        )
+    })
+}
+
+/// Borrows a mutable buffer argument.
+///
+/// # Safety
+///
+/// `ptr` must be null or valid for reads and writes of `len`
+/// bytes for the duration of the call.
+unsafe fn buffer_arg<'a>(
+    ptr: *mut u8,
+    len: usize,
+    name: &str,
+) -> Result<&'a mut [u8], Failure> {
+    if ptr.is_null() {
+        return Err(null_pointer(name));
+    }
+    // SAFETY: guaranteed by the caller.
+    Ok(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+}
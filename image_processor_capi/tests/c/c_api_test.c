@@ -0,0 +1,138 @@
+/* Exercises the C API through the generated header; run by
+ * tests/c_api.rs with an empty plugin directory, so only the
+ * built-in plugins are available. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "image_processor.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            const char *error = ip_last_error();                      \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n", \
+                    __FILE__, __LINE__, #cond, error ? error : "none"); \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+/* Fills a packed RGBA image so that pixel i has red = i. */
+static void fill(uint8_t *rgba, size_t pixels) {
+    for (size_t i = 0; i < pixels; i++) {
+        rgba[i * 4 + 0] = (uint8_t)i;
+        rgba[i * 4 + 1] = 0;
+        rgba[i * 4 + 2] = 0;
+        rgba[i * 4 + 3] = 255;
+    }
+}
+
+/* Writes a 2x1 32-bit BMP, the simplest format to build by hand. */
+static size_t write_bmp(uint8_t *out) {
+    static const uint8_t bmp[] = {
+        'B', 'M', 62, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0, /* file header */
+        40, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0,           /* 2x1 */
+        1, 0, 32, 0, 0, 0, 0, 0, 8, 0, 0, 0,           /* 32 bpp, BI_RGB */
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 255, 0, 255, 0, 0, 0,                    /* red, blue (BGRx) */
+    };
+    memcpy(out, bmp, sizeof bmp);
+    return sizeof bmp;
+}
+
+static void test_registry_and_plugins(const char *plugin_dir) {
+    CHECK(ip_registry_new(NULL) == NULL);
+    CHECK(ip_last_error() != NULL);
+
+    IpRegistry *registry = ip_registry_new(plugin_dir);
+    CHECK(registry != NULL);
+    CHECK(ip_last_error() == NULL);
+
+    IpPlugin *missing = NULL;
+    CHECK(ip_plugin_load(registry, "no_such_plugin", &missing) == IP_ERROR_FAILED);
+    CHECK(missing == NULL);
+    CHECK(ip_last_error() != NULL);
+    CHECK(ip_plugin_load(registry, "mirror_plugin", NULL) == IP_ERROR_NULL_POINTER);
+
+    IpPlugin *mirror = NULL;
+    CHECK(ip_plugin_load(registry, "mirror_plugin", &mirror) == IP_OK);
+    CHECK(mirror != NULL);
+    /* Plugins outlive the registry they were loaded from. */
+    ip_registry_free(registry);
+
+    uint8_t rgba[3 * 2 * 4];
+    fill(rgba, 6);
+    CHECK(ip_plugin_process(mirror, rgba, sizeof rgba, 3, 2, 12,
+                            "{\"horizontal\": true}") == IP_OK);
+    CHECK(rgba[0] == 2 && rgba[4] == 1 && rgba[8] == 0);
+    CHECK(rgba[12] == 5 && rgba[20] == 3);
+
+    /* The left 2x2 pixels of the 3x2 image, through the stride. */
+    fill(rgba, 6);
+    CHECK(ip_plugin_process(mirror, rgba, sizeof rgba, 2, 2, 12,
+                            "{\"horizontal\": true}") == IP_OK);
+    CHECK(rgba[0] == 1 && rgba[4] == 0 && rgba[8] == 2);
+
+    CHECK(ip_plugin_process(mirror, rgba, sizeof rgba, 3, 2, 12,
+                            "not json") == IP_ERROR_FAILED);
+    CHECK(ip_plugin_process(mirror, rgba, sizeof rgba - 1, 3, 2, 12,
+                            "{}") == IP_ERROR_FAILED);
+    ip_plugin_free(mirror);
+}
+
+static void test_pipelines(const char *plugin_dir) {
+    IpRegistry *registry = ip_registry_new(plugin_dir);
+    uint8_t rgba[3 * 2 * 4];
+    fill(rgba, 6);
+
+    const char *pipeline =
+        "{\"steps\": ["
+        "{\"plugin\": \"mirror_plugin\", \"params\": {\"horizontal\": true}},"
+        "{\"plugin\": \"mirror_plugin\", \"params\": {\"vertical\": true}}]}";
+    CHECK(ip_pipeline_run(registry, pipeline, rgba, sizeof rgba, 3, 2) == IP_OK);
+    CHECK(rgba[0] == 5 && rgba[4] == 4 && rgba[20] == 0);
+
+    CHECK(ip_pipeline_run(registry, "{\"steps\": 1}", rgba, sizeof rgba, 3, 2)
+          == IP_ERROR_FAILED);
+    CHECK(strstr(ip_last_error(), "invalid pipeline") != NULL);
+    CHECK(ip_pipeline_run(registry, "{\"steps\": []}", rgba, 4, 3, 2)
+          == IP_ERROR_FAILED);
+
+    uint8_t bmp[64];
+    size_t bmp_len = write_bmp(bmp);
+    uint8_t *output = NULL;
+    size_t output_len = 0;
+    CHECK(ip_process_bytes(registry, bmp, bmp_len, pipeline, "png",
+                           &output, &output_len) == IP_OK);
+    CHECK(output_len > 8 && memcmp(output, "\x89PNG", 4) == 0);
+    ip_buffer_free(output, output_len);
+
+    CHECK(ip_process_bytes(registry, bmp, bmp_len, "{\"steps\": []}", NULL,
+                           &output, &output_len) == IP_OK);
+    CHECK(output_len > 2 && memcmp(output, "BM", 2) == 0);
+    ip_buffer_free(output, output_len);
+
+    CHECK(ip_process_bytes(registry, bmp, bmp_len, pipeline, "nope",
+                           &output, &output_len) == IP_ERROR_INVALID_ARGUMENT);
+    CHECK(ip_process_bytes(registry, (const uint8_t *)"junk", 4, pipeline,
+                           NULL, &output, &output_len) == IP_ERROR_FAILED);
+    ip_registry_free(registry);
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s PLUGIN_DIR\n", argv[0]);
+        return 2;
+    }
+    test_registry_and_plugins(argv[1]);
+    test_pipelines(argv[1]);
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+    printf("ok\n");
+    return 0;
+}
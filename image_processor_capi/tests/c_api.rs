@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Directory holding the built `image_processor_capi` library
+/// (`target/<profile>`, next to this test's `deps` directory).
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+fn generate_header() -> String {
+    let config =
+        cbindgen::Config::from_file(crate_dir().join("cbindgen.toml")).unwrap();
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir().join("src/lib.rs"))
+        .generate()
+        .unwrap()
+        .write(&mut header);
+    String::from_utf8(header).unwrap()
+}
+
+/// The checked-in header must match the Rust declarations;
+/// run with `UPDATE_C_HEADER=1` to regenerate it.
+#[test]
+fn header_is_up_to_date() {
+    let path = crate_dir().join("include/image_processor.h");
+    let generated = generate_header();
+    if env::var_os("UPDATE_C_HEADER").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        generated,
+        "include/image_processor.h is stale; rerun with UPDATE_C_HEADER=1"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn c_program_drives_plugins() {
+    let dir = TempDir::new().unwrap();
+    let exe = dir.path().join("c_api_test");
+    let lib_dir = library_dir();
+
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .arg(crate_dir().join("tests/c/c_api_test.c"))
+        .arg("-I")
+        .arg(crate_dir().join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .args(["-limage_processor_capi", "-o"])
+        .arg(&exe)
+        .status()
+        .unwrap();
+    assert!(compiled.success(), "failed to compile the C test program");
+
+    let output = Command::new(&exe)
+        .arg(dir.path())
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stdout: {}\nstderr: {}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,213 @@
+use image_processor::error::AppError;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    image_processor,
+    ImageProcessorError,
+    PyException,
+    "Base class of the errors raised by image_processor."
+);
+create_exception!(
+    image_processor,
+    PluginLoadError,
+    ImageProcessorError,
+    "A plugin could not be found, loaded or validated."
+);
+create_exception!(
+    image_processor,
+    PluginVersionError,
+    PluginLoadError,
+    "No installed version of a plugin matches the requirement, \
+     or a version is installed twice."
+);
+create_exception!(
+    image_processor,
+    UntrustedPluginError,
+    PluginLoadError,
+    "A plugin file is not allowed by the trust store."
+);
+create_exception!(
+    image_processor,
+    PluginExecError,
+    ImageProcessorError,
+    "A plugin rejected the image or its parameters; `code` holds \
+     the plugin's error code, if it returned one."
+);
+create_exception!(
+    image_processor,
+    PluginPanickedError,
+    PluginExecError,
+    "A plugin panicked."
+);
+create_exception!(
+    image_processor,
+    ImageFormatError,
+    ImageProcessorError,
+    "An image could not be decoded or encoded."
+);
+create_exception!(
+    image_processor,
+    InvalidInputError,
+    ImageProcessorError,
+    "An image buffer, region, auxiliary input or pipeline is \
+     invalid."
+);
+
+/// Converts an [`AppError`] to the matching Python exception.
+pub fn to_py_err(py: Python<'_>, err: &AppError) -> PyErr {
+    let message = err.to_string();
+    let code = match err {
+        AppError::PluginExec { code } | AppError::PluginFailed { code, .. } => {
+            Some(*code)
+        }
+        _ => None,
+    };
+    let py_err = match err {
+        AppError::PluginLoad { .. }
+        | AppError::SymbolLoad(_)
+        | AppError::WasmLoad { .. }
+        | AppError::InvalidManifest { .. }
+        | AppError::ManifestMismatch { .. }
+        | AppError::IncompatiblePlugin { .. }
+        | AppError::InvalidPluginSpec { .. } => {
+            PluginLoadError::new_err(message)
+        }
+        AppError::PluginVersionNotFound { .. }
+        | AppError::PluginVersionConflict { .. } => {
+            PluginVersionError::new_err(message)
+        }
+        AppError::UntrustedPlugin { .. } => {
+            UntrustedPluginError::new_err(message)
+        }
+        AppError::PluginExec { .. }
+        | AppError::PluginFailed { .. }
+        | AppError::Wasm { .. }
+        | AppError::AuxInputsUnsupported
+        | AppError::NotTileSafe => PluginExecError::new_err(message),
+        AppError::PluginPanicked { .. } => {
+            PluginPanickedError::new_err(message)
+        }
+        AppError::ImageLoad { .. }
+        | AppError::ImageSave { .. }
+        | AppError::ImageDecode { .. }
+        | AppError::ImageEncode { .. }
+        | AppError::UnsupportedFormat { .. } => {
+            ImageFormatError::new_err(message)
+        }
+        AppError::InvalidImageView { .. }
+        | AppError::InvalidAuxInput { .. }
+        | AppError::InvalidRegion { .. }
+        | AppError::InvalidPipeline { .. }
+        | AppError::InvalidPipelineJson { .. } => {
+            InvalidInputError::new_err(message)
+        }
+        _ => ImageProcessorError::new_err(message),
+    };
+    if py_err.is_instance_of::<PluginExecError>(py) {
+        // Best effort: the message already holds the code.
+        let _ = py_err.value(py).setattr("code", code);
+    }
+    py_err
+}
+
+/// Adds the exception classes to the module.
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("ImageProcessorError", py.get_type::<ImageProcessorError>())?;
+    m.add("PluginLoadError", py.get_type::<PluginLoadError>())?;
+    m.add("PluginVersionError", py.get_type::<PluginVersionError>())?;
+    m.add("UntrustedPluginError", py.get_type::<UntrustedPluginError>())?;
+    m.add("PluginExecError", py.get_type::<PluginExecError>())?;
+    m.add("PluginPanickedError", py.get_type::<PluginPanickedError>())?;
+    m.add("ImageFormatError", py.get_type::<ImageFormatError>())?;
+    m.add("InvalidInputError", py.get_type::<InvalidInputError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts `err` and returns the exception with its `code`
+    /// attribute, if it has one.
+    fn convert(py: Python<'_>, err: &AppError) -> (PyErr, Option<i32>) {
+        let py_err = to_py_err(py, err);
+        let code = py_err
+            .value(py)
+            .getattr("code")
+            .ok()
+            .and_then(|code| code.extract().ok());
+        (py_err, code)
+    }
+
+    #[test]
+    fn errors_map_to_exception_classes() {
+        Python::initialize();
+        Python::attach(|py| {
+            let (err, code) = convert(py, &AppError::PluginExec { code: 4 });
+            assert!(err.is_instance_of::<PluginExecError>(py));
+            assert_eq!(code, Some(4));
+
+            let (err, code) = convert(
+                py,
+                &AppError::PluginFailed {
+                    code: 8,
+                    message: "bad radius".into(),
+                },
+            );
+            assert!(err.is_instance_of::<PluginExecError>(py));
+            assert!(err.to_string().contains("bad radius"), "{err}");
+            assert_eq!(code, Some(8));
+
+            let (err, code) = convert(
+                py,
+                &AppError::PluginPanicked {
+                    message: "boom".into(),
+                },
+            );
+            assert!(err.is_instance_of::<PluginPanickedError>(py));
+            assert!(err.is_instance_of::<PluginExecError>(py));
+            assert_eq!(code, None);
+
+            let err = to_py_err(
+                py,
+                &AppError::PluginVersionNotFound {
+                    name: "blur_plugin".into(),
+                    requirement: "^2".into(),
+                    available: vec![],
+                },
+            );
+            assert!(err.is_instance_of::<PluginVersionError>(py));
+            assert!(err.is_instance_of::<PluginLoadError>(py));
+
+            let err = to_py_err(
+                py,
+                &AppError::UnsupportedFormat {
+                    format: "nope".into(),
+                },
+            );
+            assert!(err.is_instance_of::<ImageFormatError>(py));
+
+            let err = to_py_err(
+                py,
+                &AppError::InvalidPipeline {
+                    path: "pipeline.json".into(),
+                    message: "no steps".into(),
+                },
+            );
+            assert!(err.is_instance_of::<InvalidInputError>(py));
+            assert!(!err.is_instance_of::<PluginLoadError>(py));
+
+            let err = to_py_err(
+                py,
+                &AppError::Serve {
+                    message: "busy".into(),
+                },
+            );
+            assert!(err.is_instance_of::<ImageProcessorError>(py));
+            assert!(!err.is_instance_of::<PluginExecError>(py));
+        });
+    }
+}
@@ -0,0 +1,332 @@
+//! Python bindings for the image processor host: load plugins
+//! and run them or whole pipelines on `H x W x 4` `uint8`
+//! `numpy` arrays, in place.
+//!
+//! Errors are raised as the exceptions in [`errors`], derived
+//! from `ImageProcessorError`. The GIL is released while plugins
+//! run.
+
+mod errors;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use image::{ImageBuffer, ImageFormat};
+use image_processor::builtin::register_builtins;
+use image_processor::error::AppError;
+use image_processor::pipeline::Pipeline;
+use image_processor::plugin::ImagePlugin;
+use image_processor::registry::PluginRegistry;
+use image_processor::trust::TrustStore;
+use image_processor::{process_bytes, process_rgba};
+use numpy::PyReadwriteArray3;
+use numpy::ndarray::ArrayView3;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyList, PyString};
+
+use crate::errors::to_py_err;
+
+/// Resolves plugin names to plugins loaded from `plugin_dir`,
+/// falling back to the built-in plugins.
+#[pyclass(module = "image_processor")]
+struct Registry {
+    plugins: PluginRegistry,
+}
+
+#[pymethods]
+impl Registry {
+    #[new]
+    #[pyo3(signature = (plugin_dir = PathBuf::from("target/debug")))]
+    fn new(plugin_dir: PathBuf) -> Self {
+        let mut plugins = PluginRegistry::new(plugin_dir);
+        register_builtins(&mut plugins);
+        Self { plugins }
+    }
+
+    /// Only loads plugin files listed in the trust store at
+    /// `path` from now on; with `require_signed`, only those
+    /// signed by one of its keys.
+    #[pyo3(signature = (path, require_signed = false))]
+    #[allow(clippy::needless_pass_by_value)] // extracted by value
+    fn set_trust_store(
+        &mut self,
+        py: Python<'_>,
+        path: PathBuf,
+        require_signed: bool,
+    ) -> PyResult<()> {
+        let mut trust_store =
+            TrustStore::load(&path).map_err(|e| to_py_err(py, &e))?;
+        trust_store.set_require_signed(require_signed);
+        self.plugins.set_trust_store(trust_store);
+        Ok(())
+    }
+
+    /// Loads and validates the plugin `name`, e.g. `blur_plugin`
+    /// or `blur_plugin@^1.2`.
+    fn load(&self, py: Python<'_>, name: &str) -> PyResult<Plugin> {
+        let plugin = py
+            .detach(|| self.plugins.get(name))
+            .map_err(|e| to_py_err(py, &e))?;
+        Ok(Plugin::new(name.to_owned(), plugin))
+    }
+
+    /// Runs pipeline `steps` (a list of step dicts, a
+    /// `{"steps": [...]}` dict or its JSON text) on `image` in
+    /// place.
+    fn run_pipeline(
+        &self,
+        py: Python<'_>,
+        mut image: PyReadwriteArray3<'_, u8>,
+        steps: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let pipeline = pipeline(steps)?;
+        with_pixels(py, &mut image, |rgba_data, width, height| {
+            let Some(mut image) =
+                ImageBuffer::from_raw(width, height, rgba_data)
+            else {
+                unreachable!("the pixels have the array's shape");
+            };
+            process_rgba(&self.plugins, &mut image, &pipeline.steps, &[])
+        })
+    }
+
+    /// Decodes an encoded image (PNG, JPEG, ...), runs pipeline
+    /// `steps` on it and returns it encoded in `format` (a file
+    /// extension such as `"png"`), or in the input format.
+    #[pyo3(signature = (data, steps, format = None))]
+    fn process_bytes<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        steps: &Bound<'_, PyAny>,
+        format: Option<&str>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let pipeline = pipeline(steps)?;
+        let format = format
+            .map(|format| {
+                ImageFormat::from_extension(format).ok_or_else(|| {
+                    AppError::UnsupportedFormat {
+                        format: format.to_owned(),
+                    }
+                })
+            })
+            .transpose()
+            .map_err(|e| to_py_err(py, &e))?;
+
+        let encoded = py
+            .detach(|| {
+                process_bytes(&self.plugins, data, &pipeline.steps, format)
+            })
+            .map_err(|e| to_py_err(py, &e))?;
+        Ok(PyBytes::new(py, &encoded))
+    }
+}
+
+/// A loaded plugin.
+#[pyclass(module = "image_processor", frozen)]
+struct Plugin {
+    #[pyo3(get)]
+    name: String,
+    inner: Arc<dyn ImagePlugin>,
+    /// Serializes calls to plugins that are not thread-safe.
+    lock: Option<Mutex<()>>,
+}
+
+impl Plugin {
+    fn new(name: String, plugin: Arc<dyn ImagePlugin>) -> Self {
+        let lock = (!plugin.thread_safe()).then(|| Mutex::new(()));
+        Self {
+            name,
+            inner: plugin,
+            lock,
+        }
+    }
+
+    /// Runs the plugin, one call at a time unless it is
+    /// thread-safe.
+    fn process_image(
+        &self,
+        width: u32,
+        height: u32,
+        rgba_data: &mut [u8],
+        params: &str,
+    ) -> Result<(), AppError> {
+        let _guard = self
+            .lock
+            .as_ref()
+            .map(|lock| lock.lock().unwrap_or_else(PoisonError::into_inner));
+        self.inner.process_image(width, height, rgba_data, params)
+    }
+}
+
+#[pymethods]
+impl Plugin {
+    /// Whether the plugin may process several images at once
+    /// from different threads.
+    #[getter]
+    fn thread_safe(&self) -> bool {
+        self.inner.thread_safe()
+    }
+
+    /// Runs the plugin on `image` in place. `params` is a dict
+    /// (passed as JSON), a string passed verbatim, or `None`
+    /// for `{}`. Calls from several threads run concurrently
+    /// only if the plugin is thread-safe.
+    #[pyo3(signature = (image, params = None))]
+    fn process(
+        &self,
+        py: Python<'_>,
+        mut image: PyReadwriteArray3<'_, u8>,
+        params: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let params = match params {
+            None => "{}".to_owned(),
+            Some(params) => to_json(params)?,
+        };
+        with_pixels(py, &mut image, |rgba_data, width, height| {
+            self.process_image(width, height, rgba_data, &params)
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Plugin {}>", self.name)
+    }
+}
+
+/// Serializes a Python value as JSON; strings are taken as
+/// they are.
+fn to_json(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    if let Ok(text) = value.cast::<PyString>() {
+        return Ok(text.to_str()?.to_owned());
+    }
+    let json = value.py().import("json")?;
+    json.call_method1("dumps", (value,))?.extract()
+}
+
+/// Parses pipeline steps given as a list, a dict or JSON text.
+fn pipeline(steps: &Bound<'_, PyAny>) -> PyResult<Pipeline> {
+    let text = if steps.is_instance_of::<PyList>() {
+        let pipeline = pyo3::types::PyDict::new(steps.py());
+        pipeline.set_item("steps", steps)?;
+        to_json(pipeline.as_any())?
+    } else {
+        to_json(steps)?
+    };
+    Pipeline::from_json(&text).map_err(|e| to_py_err(steps.py(), &e))
+}
+
+/// Runs `f` on the packed RGBA pixels of an `H x W x 4` array
+/// with the GIL released: directly on the array's memory if it
+/// is contiguous, otherwise on a copy written back afterwards.
+fn with_pixels(
+    py: Python<'_>,
+    image: &mut PyReadwriteArray3<'_, u8>,
+    f: impl FnOnce(&mut [u8], u32, u32) -> Result<(), AppError> + Send,
+) -> PyResult<()> {
+    let (height, width, channels) = image.as_array().dim();
+    let dimension = |n: usize| {
+        u32::try_from(n).map_err(|_| {
+            PyValueError::new_err("image dimensions are too large")
+        })
+    };
+    if channels != 4 {
+        return Err(PyValueError::new_err(format!(
+            "expected an HxWx4 RGBA array, got shape \
+             ({height}, {width}, {channels})"
+        )));
+    }
+    let (w, h) = (dimension(width)?, dimension(height)?);
+
+    if let Ok(rgba_data) = image.as_slice_mut() {
+        return py
+            .detach(|| f(rgba_data, w, h))
+            .map_err(|e| to_py_err(py, &e));
+    }
+
+    let mut packed: Vec<u8> = image.as_array().iter().copied().collect();
+    py.detach(|| f(&mut packed, w, h))
+        .map_err(|e| to_py_err(py, &e))?;
+    let packed = ArrayView3::from_shape((height, width, channels), &packed)
+        .expect("the copy has the array's shape");
+    image.as_array_mut().assign(&packed);
+    Ok(())
+}
+
+#[pymodule(name = "image_processor")]
+fn image_processor_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Registry>()?;
+    m.add_class::<Plugin>()?;
+    errors::register(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use image_processor::image_view::ImageViewMut;
+    use image_processor::plugin_loader::AuxInput;
+
+    use super::*;
+
+    /// Test double recording the most calls in progress at once.
+    struct Overlapping {
+        thread_safe: bool,
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    impl ImagePlugin for Overlapping {
+        fn supports_aux_inputs(&self) -> bool {
+            false
+        }
+
+        fn thread_safe(&self) -> bool {
+            self.thread_safe
+        }
+
+        fn tile_halo(&self, _params: &str) -> Result<Option<u32>, AppError> {
+            Ok(None)
+        }
+
+        fn process_view(
+            &self,
+            _image: ImageViewMut<'_>,
+            _params: &str,
+            _aux: &[AuxInput<'_>],
+        ) -> Result<(), AppError> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Most calls of a plugin in progress at once when four
+    /// threads process images through one `Plugin`.
+    fn max_overlap(thread_safe: bool) -> usize {
+        let double = Arc::new(Overlapping {
+            thread_safe,
+            active: AtomicUsize::new(0),
+            max_active: AtomicUsize::new(0),
+        });
+        let plugin = Plugin::new("double".into(), double.clone());
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    plugin.process_image(1, 1, &mut [0; 4], "{}").unwrap();
+                });
+            }
+        });
+        double.max_active.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn plugins_that_are_not_thread_safe_run_one_call_at_a_time() {
+        assert_eq!(max_overlap(false), 1);
+        assert!(max_overlap(true) > 1);
+    }
+}
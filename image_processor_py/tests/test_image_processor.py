@@ -0,0 +1,166 @@
+"""Tests for the Python bindings; they use the built-in plugins,
+so only need the extension module (``maturin develop``)."""
+
+import json
+import struct
+from concurrent.futures import ThreadPoolExecutor
+
+import numpy as np
+import pytest
+
+import image_processor as ip
+
+
+@pytest.fixture
+def registry(tmp_path):
+    # An empty plugin directory: only built-in plugins resolve.
+    return ip.Registry(str(tmp_path))
+
+
+def noise(height, width):
+    rng = np.random.default_rng(7)
+    image = rng.integers(0, 256, size=(height, width, 4), dtype=np.uint8)
+    image[..., 3] = 255
+    return image
+
+
+def bmp_2x1():
+    """A 2x1 32-bit BMP (red, blue), the simplest format to build by hand."""
+    header = b"BM" + struct.pack("<IHHI", 62, 0, 0, 54)
+    info = struct.pack("<IiiHHIIiiII", 40, 2, 1, 1, 32, 0, 8, 0, 0, 0, 0)
+    return header + info + bytes([0, 0, 255, 0, 255, 0, 0, 0])
+
+
+def test_plugin_processes_array_in_place(registry):
+    mirror = registry.load("mirror_plugin")
+    image = noise(5, 7)
+    expected = image[:, ::-1].copy()
+
+    assert mirror.process(image, {"horizontal": True}) is None
+
+    np.testing.assert_array_equal(image, expected)
+    assert mirror.name == "mirror_plugin"
+    assert mirror.thread_safe
+
+
+def test_params_may_be_json_text(registry):
+    image = noise(4, 3)
+    expected = image[::-1].copy()
+
+    registry.load("mirror_plugin").process(image, '{"vertical": true}')
+
+    np.testing.assert_array_equal(image, expected)
+
+
+def test_contiguous_slices_are_processed_in_place(registry):
+    image = noise(6, 4)
+    rows = image[2:4]
+    expected = image.copy()
+    expected[2:4] = expected[2:4, ::-1]
+
+    registry.load("mirror_plugin").process(rows, {"horizontal": True})
+
+    np.testing.assert_array_equal(image, expected)
+
+
+def test_strided_arrays_are_copied_and_written_back(registry):
+    image = noise(4, 6)
+    columns = image[:, 1:5]
+    assert not columns.flags.c_contiguous
+    expected = image.copy()
+    expected[:, 1:5] = expected[:, 1:5][:, ::-1]
+
+    registry.load("mirror_plugin").process(columns, {"horizontal": True})
+
+    np.testing.assert_array_equal(image, expected)
+
+
+def test_plugin_may_be_called_from_several_threads(registry):
+    # Plugins that are not thread-safe are serialized by the
+    # bindings (see the Rust unit tests); either way, concurrent
+    # calls each see only their own image.
+    mirror = registry.load("mirror_plugin")
+    images = [noise(16, 32 + n) for n in range(8)]
+    expected = [image[:, ::-1].copy() for image in images]
+
+    def flip(image):
+        mirror.process(image, {"horizontal": True})
+
+    with ThreadPoolExecutor(max_workers=4) as pool:
+        list(pool.map(flip, images))
+
+    for image, want in zip(images, expected):
+        np.testing.assert_array_equal(image, want)
+
+
+def test_arrays_must_be_hxwx4_uint8(registry):
+    mirror = registry.load("mirror_plugin")
+    with pytest.raises(ValueError):
+        mirror.process(np.zeros((2, 2, 3), dtype=np.uint8))
+    with pytest.raises(TypeError):
+        mirror.process(np.zeros((2, 2, 4), dtype=np.float32))
+    readonly = np.zeros((2, 2, 4), dtype=np.uint8)
+    readonly.flags.writeable = False
+    with pytest.raises(Exception):
+        mirror.process(readonly)
+
+
+@pytest.mark.parametrize(
+    "steps",
+    [
+        [
+            {"plugin": "mirror_plugin", "params": {"horizontal": True}},
+            {"plugin": "mirror_plugin", "params": {"vertical": True}},
+        ],
+        {
+            "steps": [
+                {"plugin": "mirror_plugin", "params": {"horizontal": True}},
+                {"plugin": "mirror_plugin", "params": {"vertical": True}},
+            ]
+        },
+        json.dumps(
+            {
+                "steps": [
+                    {"plugin": "mirror_plugin", "params": {"horizontal": True}},
+                    {"plugin": "mirror_plugin", "params": {"vertical": True}},
+                ]
+            }
+        ),
+    ],
+)
+def test_pipelines_run_in_order(registry, steps):
+    image = noise(5, 7)
+    expected = image[::-1, ::-1].copy()
+
+    registry.run_pipeline(image, steps)
+
+    np.testing.assert_array_equal(image, expected)
+
+
+def test_process_bytes_converts_formats(registry):
+    steps = [{"plugin": "mirror_plugin", "params": {"horizontal": True}}]
+
+    png = registry.process_bytes(bmp_2x1(), steps, format="png")
+    assert png.startswith(b"\x89PNG")
+    assert registry.process_bytes(bmp_2x1(), []).startswith(b"BM")
+
+
+def test_errors_map_to_exception_classes(registry):
+    image = noise(2, 2)
+
+    with pytest.raises(ip.PluginLoadError):
+        registry.load("no_such_plugin")
+    with pytest.raises(ip.PluginVersionError):
+        registry.load("mirror_plugin@^99")
+    with pytest.raises(ip.PluginExecError) as err:
+        registry.load("mirror_plugin").process(image, {"horizontal": "yes"})
+    assert err.value.code == 4
+    with pytest.raises(ip.InvalidInputError):
+        registry.run_pipeline(image, {"steps": 1})
+    with pytest.raises(ip.ImageFormatError):
+        registry.process_bytes(b"not an image", [])
+    with pytest.raises(ip.ImageFormatError):
+        registry.process_bytes(bmp_2x1(), [], format="nope")
+
+    assert issubclass(ip.PluginPanickedError, ip.PluginExecError)
+    assert issubclass(ip.PluginExecError, ip.ImageProcessorError)
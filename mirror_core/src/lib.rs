@@ -0,0 +1,219 @@
+//! Mirror image plugin, built on `plugin_sdk`.
+//!
+//! The `mirror_plugin` crate exports it as a dynamic library; the
+//! host can also link [`MirrorPlugin`] in directly.
+
+use plugin_sdk::{BYTES_PER_PIXEL, ImageViewMut, Plugin, PluginError};
+use serde::Deserialize;
+
+/// Plugin version, reported by the plugin built into the host
+/// and by the dynamic library.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Mirror plugin parameters.
+#[derive(Deserialize)]
+pub struct MirrorParams {
+    /// Flip horizontally (left to right).
+    #[serde(default)]
+    horizontal: bool,
+    /// Flip vertically (top to bottom).
+    #[serde(default)]
+    vertical: bool,
+}
+
+/// Flips images horizontally and/or vertically.
+#[derive(Default)]
+pub struct MirrorPlugin;
+
+impl Plugin for MirrorPlugin {
+    type Params = MirrorParams;
+
+    fn process(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: MirrorParams,
+    ) -> Result<(), PluginError> {
+        if params.horizontal {
+            flip_horizontal(&mut image);
+        }
+        if params.vertical {
+            flip_vertical(&mut image);
+        }
+
+        Ok(())
+    }
+}
+
+/// Flips the image horizontally — reverses the pixels of each
+/// row (left <-> right).
+fn flip_horizontal(image: &mut ImageViewMut<'_>) {
+    for row in image.rows_mut() {
+        row.as_chunks_mut::<BYTES_PER_PIXEL>().0.reverse();
+    }
+}
+
+/// Flips the image vertically — swaps rows
+/// (top <-> bottom).
+fn flip_vertical(image: &mut ImageViewMut<'_>) {
+    let half = image.height() / 2;
+    let mut rows: Vec<&mut [u8]> = image.rows_mut().collect();
+    let (top, bottom) = rows.split_at_mut(half);
+    for (upper, lower) in top.iter_mut().zip(bottom.iter_mut().rev()) {
+        upper.swap_with_slice(lower);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps a packed test image.
+    fn view(data: &mut [u8], width: usize, height: usize) -> ImageViewMut<'_> {
+        ImageViewMut::new(data, width, height).unwrap()
+    }
+
+    /// Creates a 2x2 test image with unique colors per pixel.
+    /// Format: 2x2, each pixel = 4 bytes RGBA.
+    fn make_2x2() -> Vec<u8> {
+        vec![
+            255, 0, 0, 255, // top-left — red
+            0, 255, 0, 255, // top-right — green
+            0, 0, 255, 255, // bottom-left — blue
+            255, 255, 255, 255, // bottom-right — white
+        ]
+    }
+
+    #[test]
+    fn horizontal_flip_2x2() {
+        let mut data = make_2x2();
+        flip_horizontal(&mut view(&mut data, 2, 2));
+
+        // After horizontal flip:
+        // green, red
+        // white, blue
+        assert_eq!(
+            data,
+            vec![
+                0, 255, 0, 255, // green
+                255, 0, 0, 255, // red
+                255, 255, 255, 255, // white
+                0, 0, 255, 255, // blue
+            ]
+        );
+    }
+
+    #[test]
+    fn vertical_flip_2x2() {
+        let mut data = make_2x2();
+        flip_vertical(&mut view(&mut data, 2, 2));
+
+        // After vertical flip:
+        // blue, white
+        // red, green
+        assert_eq!(
+            data,
+            vec![
+                0, 0, 255, 255, // blue
+                255, 255, 255, 255, // white
+                255, 0, 0, 255, // red
+                0, 255, 0, 255, // green
+            ]
+        );
+    }
+
+    #[test]
+    fn both_flips_2x2() {
+        let mut data = make_2x2();
+        flip_horizontal(&mut view(&mut data, 2, 2));
+        flip_vertical(&mut view(&mut data, 2, 2));
+
+        // Horizontal + vertical = 180° rotation:
+        // white, blue
+        // green, red
+        assert_eq!(
+            data,
+            vec![
+                255, 255, 255, 255, // white
+                0, 0, 255, 255, // blue
+                0, 255, 0, 255, // green
+                255, 0, 0, 255, // red
+            ]
+        );
+    }
+
+    #[test]
+    fn horizontal_flip_single_column() {
+        // 1x3 image — horizontal flip changes nothing
+        let mut data = vec![
+            1, 2, 3, 4, //
+            5, 6, 7, 8, //
+            9, 10, 11, 12, //
+        ];
+        let original = data.clone();
+        flip_horizontal(&mut view(&mut data, 1, 3));
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn vertical_flip_single_row() {
+        // 3x1 image — vertical flip changes nothing
+        let mut data = vec![
+            1, 2, 3, 4, //
+            5, 6, 7, 8, //
+            9, 10, 11, 12, //
+        ];
+        let original = data.clone();
+        flip_vertical(&mut view(&mut data, 3, 1));
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn flips_strided_view_in_place() {
+        // 2x2 view into a 3x2 image of make_2x2-style pixels;
+        // the third column must stay untouched.
+        let mut data: Vec<u8> = (0..6u8).flat_map(|i| [i; 4]).collect();
+        let mut image =
+            ImageViewMut::with_stride(&mut data, 2, 2, 3 * 4).unwrap();
+        flip_horizontal(&mut image);
+        flip_vertical(&mut image);
+
+        let pixels: Vec<u8> = data.chunks(4).map(|p| p[0]).collect();
+        assert_eq!(pixels, [4, 3, 2, 1, 0, 5]);
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Generates a random RGBA image with dimensions
+        /// in range [1, 64] and random pixel data.
+        fn arbitrary_image() -> impl Strategy<Value = (usize, usize, Vec<u8>)> {
+            (1..=64usize, 1..=64usize).prop_flat_map(|(w, h)| {
+                let len = w * h * BYTES_PER_PIXEL;
+                (Just(w), Just(h), proptest::collection::vec(any::<u8>(), len))
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn double_horizontal_flip_is_identity(
+                (w, h, mut data) in arbitrary_image()
+            ) {
+                let original = data.clone();
+                flip_horizontal(&mut view(&mut data, w, h));
+                flip_horizontal(&mut view(&mut data, w, h));
+                prop_assert_eq!(data, original);
+            }
+
+            #[test]
+            fn double_vertical_flip_is_identity(
+                (w, h, mut data) in arbitrary_image()
+            ) {
+                let original = data.clone();
+                flip_vertical(&mut view(&mut data, w, h));
+                flip_vertical(&mut view(&mut data, w, h));
+                prop_assert_eq!(data, original);
+            }
+        }
+    }
+}
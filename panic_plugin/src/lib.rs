@@ -0,0 +1,41 @@
+//! Test plugin that always panics.
+//!
+//! Used by the integration tests to check that a panic inside a
+//! plugin is reported to the host as an error instead of
+//! aborting it.
+
+use plugin_sdk::{ImageViewMut, Plugin, PluginError, declare_plugin};
+use serde::Deserialize;
+
+/// Panic plugin parameters.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct PanicParams {
+    /// Message to panic with.
+    message: String,
+}
+
+impl Default for PanicParams {
+    fn default() -> Self {
+        Self {
+            message: "deliberate panic".to_owned(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct PanicPlugin;
+
+impl Plugin for PanicPlugin {
+    type Params = PanicParams;
+
+    fn process(
+        &self,
+        _image: ImageViewMut<'_>,
+        params: PanicParams,
+    ) -> Result<(), PluginError> {
+        panic!("{}", params.message);
+    }
+}
+
+declare_plugin!(PanicPlugin);
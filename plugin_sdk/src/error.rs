@@ -0,0 +1,69 @@
+use std::ffi::c_int;
+
+/// Errors a plugin can report to the host.
+///
+/// Each variant maps to a stable return code of the
+/// `process_image` C ABI (see [`PluginError::code`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginError {
+    /// A required pointer argument was null (code 1).
+    NullPointer,
+    /// Width or height is zero or not representable, or the
+    /// stride or buffer length does not fit them (code 2).
+    InvalidDimensions,
+    /// The buffer size overflows `usize` (code 3).
+    SizeOverflow,
+    /// Parameters could not be parsed or are out of range
+    /// (code 4).
+    InvalidParams,
+    /// An auxiliary input is missing, has the wrong size, or
+    /// the plugin does not accept auxiliary inputs (code 5).
+    AuxInput,
+    /// The image descriptor's pixel format is not supported
+    /// (code 7).
+    UnsupportedFormat,
+    /// Processing failed for a reason described with
+    /// [`report_error`](crate::report_error), e.g. a script
+    /// error (code 8).
+    Failed,
+    /// The plugin panicked (code 99).
+    Panicked,
+    /// Plugin-specific error code. Codes 1–5, 7, 8 and 99 are
+    /// reserved for the variants above.
+    Custom(c_int),
+}
+
+impl PluginError {
+    /// Return code reported across the C ABI.
+    pub const fn code(self) -> c_int {
+        match self {
+            Self::NullPointer => 1,
+            Self::InvalidDimensions => 2,
+            Self::SizeOverflow => 3,
+            Self::InvalidParams => 4,
+            Self::AuxInput => 5,
+            Self::UnsupportedFormat => 7,
+            Self::Failed => 8,
+            Self::Panicked => 99,
+            Self::Custom(code) => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        assert_eq!(PluginError::NullPointer.code(), 1);
+        assert_eq!(PluginError::InvalidDimensions.code(), 2);
+        assert_eq!(PluginError::SizeOverflow.code(), 3);
+        assert_eq!(PluginError::InvalidParams.code(), 4);
+        assert_eq!(PluginError::AuxInput.code(), 5);
+        assert_eq!(PluginError::UnsupportedFormat.code(), 7);
+        assert_eq!(PluginError::Failed.code(), 8);
+        assert_eq!(PluginError::Panicked.code(), 99);
+        assert_eq!(PluginError::Custom(42).code(), 42);
+    }
+}
@@ -0,0 +1,1071 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+use serde::de::DeserializeOwned;
+
+use crate::Plugin;
+use crate::error::PluginError;
+use crate::image::{
+    AuxInputs, BYTES_PER_PIXEL, ImageView, ImageViewMut, expected_len,
+};
+
+/// [`ImageDesc::format`] value for 8-bit RGBA pixels (4 bytes
+/// per pixel, in R, G, B, A order).
+pub const PIXEL_FORMAT_RGBA8: u32 = 0;
+
+/// Version of the plugin C ABI, reported in
+/// [`PluginDescriptor::abi_version`].
+pub const ABI_VERSION: u32 = 1;
+
+/// [`PluginDescriptor::pixel_formats`] bit for
+/// [`PIXEL_FORMAT_RGBA8`].
+pub const PIXEL_FORMATS_RGBA8: u32 = 1 << PIXEL_FORMAT_RGBA8;
+
+/// [`PluginDescriptor::flags`] bit: the plugin may be called
+/// from several threads at once.
+pub const PLUGIN_FLAG_THREAD_SAFE: u32 = 1 << 0;
+
+/// [`PluginDescriptor::flags`] bit: the plugin changes the
+/// image dimensions. The in-place ABI cannot express this, so
+/// SDK plugins never set it.
+pub const PLUGIN_FLAG_CHANGES_DIMENSIONS: u32 = 1 << 1;
+
+/// Static description of a plugin returned by
+/// `plugin_descriptor` (C layout of the `PluginDescriptor` ABI
+/// struct), which hosts compare against the plugin's manifest.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginDescriptor {
+    /// ABI version the plugin was built for.
+    pub abi_version: u32,
+    /// Null-terminated plugin name.
+    pub name: *const c_char,
+    /// Null-terminated plugin version.
+    pub version: *const c_char,
+    /// Bit set of supported pixel formats
+    /// (`1 << PIXEL_FORMAT_*`).
+    pub pixel_formats: u32,
+    /// `PLUGIN_FLAG_*` bits.
+    pub flags: u32,
+}
+
+// SAFETY: descriptors only point to immutable static strings.
+unsafe impl Sync for PluginDescriptor {}
+
+impl PluginDescriptor {
+    /// Describes plugin `P` named `name` at `version`; both
+    /// strings must end with a NUL byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics (at compile time when used in a `static`) if
+    /// `name` or `version` is not NUL-terminated.
+    pub const fn new<P: Plugin>(
+        name: &'static str,
+        version: &'static str,
+    ) -> Self {
+        assert!(
+            name.as_bytes()[name.len() - 1] == 0
+                && version.as_bytes()[version.len() - 1] == 0,
+            "descriptor strings must be NUL-terminated"
+        );
+        Self {
+            abi_version: ABI_VERSION,
+            name: name.as_ptr().cast(),
+            version: version.as_ptr().cast(),
+            pixel_formats: PIXEL_FORMATS_RGBA8,
+            flags: if P::THREAD_SAFE {
+                PLUGIN_FLAG_THREAD_SAFE
+            } else {
+                0
+            },
+        }
+    }
+}
+
+/// Copies `s` into an array with a trailing NUL byte, for
+/// descriptor strings built from constants by
+/// [`declare_plugin!`](crate::declare_plugin).
+///
+/// # Panics
+///
+/// Panics (at compile time when used in a constant) if `N` is
+/// not `s.len() + 1` or `s` contains a NUL byte.
+#[doc(hidden)]
+pub const fn nul_terminated<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    assert!(N == bytes.len() + 1, "array must fit the string and a NUL");
+    let mut out = [0; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i] != 0, "string must not contain NUL bytes");
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+/// Describes an image buffer passed across the ABI (C layout
+/// of the `ImageDesc` ABI struct).
+///
+/// Rows start `stride_bytes` apart, so the descriptor can
+/// refer to a sub-image of a larger buffer or to padded rows.
+/// `len` is the number of bytes readable (and, for the main
+/// image, writable) from `data`; it must cover
+/// `stride_bytes * (height - 1) + width * 4` bytes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDesc {
+    /// First byte of the top-left pixel.
+    pub data: *mut u8,
+    /// Number of bytes accessible from `data`.
+    pub len: usize,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Distance in bytes between the starts of two rows.
+    pub stride_bytes: usize,
+    /// Pixel format; only [`PIXEL_FORMAT_RGBA8`] is defined.
+    pub format: u32,
+}
+
+/// Named auxiliary image passed to `process_image_desc`
+/// (C layout of the `AuxImage` ABI struct). Its buffer is
+/// read-only.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AuxImage {
+    /// Null-terminated input name.
+    pub name: *const c_char,
+    /// Image buffer description.
+    pub image: ImageDesc,
+}
+
+/// Auxiliary input image passed by the host alongside the
+/// main buffer (C layout of the `AuxInput` ABI struct used by
+/// `process_image_with_aux`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AuxInput {
+    /// Null-terminated input name.
+    pub name: *const c_char,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// RGBA buffer of `width * height * 4` bytes.
+    pub rgba_data: *const u8,
+}
+
+/// Tiling support reported by `plugin_tile_info` (C layout of
+/// the `TileInfo` ABI struct).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileInfo {
+    /// Whether the image may be processed in tiles.
+    pub tile_safe: bool,
+    /// Pixels of context needed around each tile.
+    pub halo: u32,
+}
+
+thread_local! {
+    /// Message describing the last failed call on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Implements the `process_image_with_aux` C ABI on top of a
+/// [`Plugin`]: validates the raw arguments, parses parameters,
+/// runs the plugin and maps the outcome to a return code.
+///
+/// Panics raised while constructing or running the plugin are
+/// caught and reported as [`PluginError::Panicked`] instead of
+/// unwinding into the host; the panic message is then available
+/// through [`last_error`].
+///
+/// This is what [`declare_plugin!`](crate::declare_plugin)
+/// exports; plugins normally do not call it directly.
+///
+/// # Safety
+///
+/// - `rgba_data` must point to a valid buffer of size
+///   `width * height * 4` bytes.
+/// - `params` must be a valid pointer to a null-terminated
+///   C string.
+/// - `aux` must point to `aux_count` valid `AuxInput` entries
+///   (or be null when `aux_count` is 0), each with a
+///   null-terminated name and a buffer matching its size.
+pub unsafe fn process_image<P: Plugin>(
+    make_plugin: impl FnOnce() -> P,
+    width: u32,
+    height: u32,
+    rgba_data: *mut u8,
+    params: *const c_char,
+    aux: *const AuxInput,
+    aux_count: usize,
+) -> c_int {
+    guard(|| {
+        if rgba_data.is_null()
+            || params.is_null()
+            || (aux.is_null() && aux_count > 0)
+        {
+            return Err(PluginError::NullPointer);
+        }
+
+        let image = packed_desc(rgba_data, width, height)?;
+
+        // SAFETY: aux is non-null when aux_count > 0, and the
+        // caller guarantees it points to aux_count entries.
+        let raw_aux = unsafe { slice_or_empty(aux, aux_count) };
+        let aux = raw_aux
+            .iter()
+            .map(|input| {
+                if input.name.is_null() || input.rgba_data.is_null() {
+                    return Err(PluginError::NullPointer);
+                }
+                Ok(AuxImage {
+                    name: input.name,
+                    image: packed_desc(
+                        input.rgba_data.cast_mut(),
+                        input.width,
+                        input.height,
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // SAFETY: the descriptors describe the caller's buffers,
+        // whose sizes the caller guarantees.
+        unsafe { run(make_plugin, &image, params, &aux) }
+    })
+}
+
+/// Implements the `process_image_desc` C ABI on top of a
+/// [`Plugin`].
+///
+/// Behaves like [`process_image`], but takes the main image
+/// and auxiliary inputs as [`ImageDesc`] descriptors, so the
+/// buffer length is bounds-checked and strided sub-images are
+/// accepted. The main image is handed to the plugin in place,
+/// stride included; strided auxiliary inputs are packed into
+/// temporary buffers.
+///
+/// # Safety
+///
+/// - `image` must point to a valid [`ImageDesc`] whose `data`
+///   is valid for reads and writes of `len` bytes.
+/// - `params` must be a valid pointer to a null-terminated
+///   C string.
+/// - `aux` must point to `aux_count` valid [`AuxImage`] entries
+///   (or be null when `aux_count` is 0), each with a
+///   null-terminated name and a buffer valid for reads of its
+///   `len` bytes.
+pub unsafe fn process_image_desc<P: Plugin>(
+    make_plugin: impl FnOnce() -> P,
+    image: *const ImageDesc,
+    params: *const c_char,
+    aux: *const AuxImage,
+    aux_count: usize,
+) -> c_int {
+    guard(|| {
+        if image.is_null()
+            || params.is_null()
+            || (aux.is_null() && aux_count > 0)
+        {
+            return Err(PluginError::NullPointer);
+        }
+
+        // SAFETY: pointers are non-null; validity is guaranteed
+        // by the caller.
+        unsafe {
+            let aux = slice_or_empty(aux, aux_count);
+            run(make_plugin, &*image, params, aux)
+        }
+    })
+}
+
+/// Implements the `plugin_tile_info` C ABI on top of
+/// [`Plugin::tile_halo`].
+///
+/// # Safety
+///
+/// - `params` must be a valid pointer to a null-terminated
+///   C string.
+/// - `info` must be valid for writing a [`TileInfo`].
+pub unsafe fn tile_info<P: Plugin>(
+    make_plugin: impl FnOnce() -> P,
+    params: *const c_char,
+    info: *mut TileInfo,
+) -> c_int {
+    guard(|| {
+        if params.is_null() || info.is_null() {
+            return Err(PluginError::NullPointer);
+        }
+
+        // SAFETY: params is non-null; the caller guarantees it is
+        // a valid C string.
+        let params = unsafe { parse_params::<P::Params>(params) }?;
+        let halo = make_plugin().tile_halo(&params)?;
+
+        // SAFETY: info is non-null and valid for writes per contract.
+        unsafe {
+            info.write(TileInfo {
+                tile_safe: halo.is_some(),
+                halo: halo.unwrap_or(0),
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Describes why the current call fails, e.g. with a script
+/// traceback.
+///
+/// The host reads the message through `plugin_last_error` and
+/// includes it in the error it reports. Call this before
+/// returning an error (typically [`PluginError::Failed`]); each
+/// call into the plugin starts without a message.
+pub fn report_error(message: &str) {
+    set_last_error(Some(message.to_owned()));
+}
+
+/// Implements the `plugin_last_error` C ABI: returns the message
+/// of the last failed call on the current thread (set for
+/// panics and by [`report_error`]), or null if there is none.
+///
+/// The pointer stays valid until the next plugin call on the
+/// same thread.
+pub fn last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|message| {
+        message.as_ref().map_or(std::ptr::null(), |m| m.as_ptr())
+    })
+}
+
+/// Implements the `plugin_alloc` C ABI exported by WebAssembly
+/// plugins: allocates `len` zeroed bytes in linear memory for
+/// the host to copy images and parameters into.
+///
+/// The buffer is released with [`dealloc`]; a host that drops
+/// the whole instance after the call need not free it.
+pub fn alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()).cast()
+}
+
+/// Implements the `plugin_dealloc` C ABI exported by
+/// WebAssembly plugins.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`alloc`] with the same
+/// `len` and not freed since.
+pub unsafe fn dealloc(ptr: *mut u8, len: usize) {
+    // SAFETY: the caller guarantees ptr/len come from alloc,
+    // which leaked a boxed slice of exactly len bytes.
+    drop(unsafe {
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len))
+    });
+}
+
+/// Error of a C ABI call run through [`guard`].
+pub trait CallError {
+    /// Return code of calls that panicked.
+    const PANICKED: c_int;
+
+    /// Return code of the error.
+    fn code(&self) -> c_int;
+
+    /// Message to record as the last error, or `None` to keep
+    /// the one reported during the call (see [`report_error`]).
+    fn into_message(self) -> Option<String>;
+}
+
+impl CallError for PluginError {
+    const PANICKED: c_int = Self::Panicked.code();
+
+    fn code(&self) -> c_int {
+        Self::code(*self)
+    }
+
+    fn into_message(self) -> Option<String> {
+        None
+    }
+}
+
+/// Runs `body`, catching panics, and maps its outcome to a
+/// return code: 0 on success, otherwise the error's code, with
+/// its message or the panic message as the last error (see
+/// [`last_error`]).
+///
+/// Hosts exposing their own C API use this to keep panics from
+/// unwinding into C callers.
+pub fn guard<E: CallError>(body: impl FnOnce() -> Result<(), E>) -> c_int {
+    #[cfg(target_arch = "wasm32")]
+    record_panics();
+    set_last_error(None);
+
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(err)) => {
+            let code = err.code();
+            if let Some(message) = err.into_message() {
+                set_last_error(Some(message));
+            }
+            code
+        }
+        Err(payload) => {
+            set_last_error(Some(panic_message(payload.as_ref())));
+            E::PANICKED
+        }
+    }
+}
+
+/// Installs a panic hook that stores the panic message as the
+/// last error.
+///
+/// Panics abort on `wasm32-unknown-unknown`, so they cannot be
+/// caught; the host sees a trap instead and reads the message
+/// through `plugin_last_error`.
+#[cfg(target_arch = "wasm32")]
+fn record_panics() {
+    static HOOK: std::sync::Once = std::sync::Once::new();
+    HOOK.call_once(|| {
+        panic::set_hook(Box::new(|info| {
+            set_last_error(Some(panic_message(info.payload())));
+        }));
+    });
+}
+
+/// Replaces the current thread's last error message.
+fn set_last_error(message: Option<String>) {
+    let message =
+        message.map(|m| CString::new(m.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.set(message);
+}
+
+/// Extracts the message from a panic payload.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| (*s).to_owned())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_owned())
+}
+
+/// Describes a contiguous `width * height * 4` byte buffer.
+fn packed_desc(
+    data: *mut u8,
+    width: u32,
+    height: u32,
+) -> Result<ImageDesc, PluginError> {
+    let (w, h) = dimensions(width, height)?;
+    let len = expected_len(w, h).ok_or(PluginError::SizeOverflow)?;
+    Ok(ImageDesc {
+        data,
+        len,
+        width,
+        height,
+        stride_bytes: w * BYTES_PER_PIXEL,
+        format: PIXEL_FORMAT_RGBA8,
+    })
+}
+
+/// Builds a slice from a pointer that may be null when `count`
+/// is 0.
+///
+/// # Safety
+///
+/// If `count > 0`, `ptr` must point to `count` valid entries.
+const unsafe fn slice_or_empty<'a, T>(ptr: *const T, count: usize) -> &'a [T] {
+    if count == 0 {
+        &[]
+    } else {
+        // SAFETY: guaranteed by the caller.
+        unsafe { std::slice::from_raw_parts(ptr, count) }
+    }
+}
+
+/// Validates descriptors, parses parameters and runs the
+/// plugin.
+///
+/// # Safety
+///
+/// Every descriptor's `data` must be valid for `len` bytes
+/// (writable for `image`), and `params` and the aux names must
+/// be valid null-terminated C strings.
+unsafe fn run<P: Plugin>(
+    make_plugin: impl FnOnce() -> P,
+    image: &ImageDesc,
+    params: *const c_char,
+    aux: &[AuxImage],
+) -> Result<(), PluginError> {
+    let layout = Layout::of(image)?;
+
+    // SAFETY: the caller guarantees data is valid for len bytes
+    // and the layout check ensured span <= len.
+    let data =
+        unsafe { std::slice::from_raw_parts_mut(image.data, layout.span) };
+
+    // SAFETY: forwarded caller guarantee for params.
+    let params = unsafe { parse_params::<P::Params>(params) }?;
+
+    // SAFETY: forwarded caller guarantees for aux entries.
+    let aux_buffers = unsafe { aux_buffers(aux) }?;
+    let mut aux = AuxInputs::new();
+    for input in &aux_buffers {
+        let (width, height) = (input.layout.width, input.layout.height);
+        let view = ImageView::new(&input.data, width, height)
+            .ok_or(PluginError::InvalidDimensions)?;
+        aux = aux.with(input.name, view);
+    }
+
+    let view = ImageViewMut::with_stride(
+        data,
+        layout.width,
+        layout.height,
+        layout.stride,
+    )
+    .ok_or(PluginError::InvalidDimensions)?;
+    make_plugin().process_with_aux(view, params, &aux)
+}
+
+/// Parses JSON plugin parameters from a C string.
+///
+/// # Safety
+///
+/// `params` must be a valid pointer to a null-terminated C
+/// string.
+unsafe fn parse_params<T: DeserializeOwned>(
+    params: *const c_char,
+) -> Result<T, PluginError> {
+    // SAFETY: the caller guarantees params points to a valid
+    // null-terminated C string.
+    let params_str = unsafe { CStr::from_ptr(params) }
+        .to_str()
+        .map_err(|_| PluginError::InvalidParams)?;
+    serde_json::from_str(params_str).map_err(|_| PluginError::InvalidParams)
+}
+
+/// Validated auxiliary input with contiguous pixel data.
+struct AuxBuffer<'a> {
+    name: &'a str,
+    layout: Layout,
+    data: Cow<'a, [u8]>,
+}
+
+/// Validates auxiliary image descriptors and returns their
+/// contiguous pixel data.
+///
+/// # Safety
+///
+/// Every entry must hold a null-terminated name and a buffer
+/// valid for reads of its `len` bytes.
+unsafe fn aux_buffers(
+    raw: &[AuxImage],
+) -> Result<Vec<AuxBuffer<'_>>, PluginError> {
+    raw.iter()
+        .map(|input| {
+            if input.name.is_null() {
+                return Err(PluginError::NullPointer);
+            }
+
+            // SAFETY: name is non-null and null-terminated per contract.
+            let name = unsafe { CStr::from_ptr(input.name) }
+                .to_str()
+                .map_err(|_| PluginError::AuxInput)?;
+            let layout = Layout::of(&input.image)?;
+
+            // SAFETY: data is valid for len bytes per contract and
+            // span <= len.
+            let data = unsafe {
+                std::slice::from_raw_parts(input.image.data, layout.span)
+            };
+            Ok(AuxBuffer {
+                name,
+                layout,
+                data: layout.pack(data),
+            })
+        })
+        .collect()
+}
+
+/// Converts C dimensions to non-zero `usize` values.
+fn dimensions(width: u32, height: u32) -> Result<(usize, usize), PluginError> {
+    let to_usize = |v: u32| {
+        usize::try_from(v)
+            .ok()
+            .filter(|&v| v > 0)
+            .ok_or(PluginError::InvalidDimensions)
+    };
+    Ok((to_usize(width)?, to_usize(height)?))
+}
+
+/// Validated memory layout of an [`ImageDesc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    width: usize,
+    height: usize,
+    stride: usize,
+    /// Bytes from the first pixel to the end of the last row.
+    span: usize,
+}
+
+impl Layout {
+    /// Checks the descriptor's pointer, format, dimensions,
+    /// stride and length.
+    fn of(desc: &ImageDesc) -> Result<Self, PluginError> {
+        if desc.data.is_null() {
+            return Err(PluginError::NullPointer);
+        }
+        if desc.format != PIXEL_FORMAT_RGBA8 {
+            return Err(PluginError::UnsupportedFormat);
+        }
+
+        let (width, height) = dimensions(desc.width, desc.height)?;
+        let row_len = width
+            .checked_mul(BYTES_PER_PIXEL)
+            .ok_or(PluginError::SizeOverflow)?;
+        if desc.stride_bytes < row_len {
+            return Err(PluginError::InvalidDimensions);
+        }
+        let span = desc
+            .stride_bytes
+            .checked_mul(height - 1)
+            .and_then(|v| v.checked_add(row_len))
+            .ok_or(PluginError::SizeOverflow)?;
+        if desc.len < span {
+            return Err(PluginError::InvalidDimensions);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            stride: desc.stride_bytes,
+            span,
+        })
+    }
+
+    /// Bytes of pixel data per row.
+    const fn row_len(&self) -> usize {
+        self.width * BYTES_PER_PIXEL
+    }
+
+    /// Returns `true` if rows have no padding between them.
+    const fn is_packed(&self) -> bool {
+        self.stride == self.row_len()
+    }
+
+    /// Returns the pixels of `data` without row padding,
+    /// borrowing when the layout is already packed.
+    fn pack<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.is_packed() {
+            return Cow::Borrowed(data);
+        }
+        let row_len = self.row_len();
+        Cow::Owned(
+            data.chunks(self.stride)
+                .flat_map(|row| &row[..row_len])
+                .copied()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// Test plugin that fills the image with a value.
+    #[derive(Default)]
+    struct Fill;
+
+    #[derive(Deserialize)]
+    struct FillParams {
+        value: u8,
+    }
+
+    impl Plugin for Fill {
+        type Params = FillParams;
+
+        fn process(
+            &self,
+            mut image: ImageViewMut<'_>,
+            params: FillParams,
+        ) -> Result<(), PluginError> {
+            assert!(params.value != 13, "unlucky value");
+            if params.value == 42 {
+                report_error("value too meaningful");
+                return Err(PluginError::Failed);
+            }
+            image.rows_mut().for_each(|row| row.fill(params.value));
+            Ok(())
+        }
+
+        fn tile_halo(
+            &self,
+            params: &FillParams,
+        ) -> Result<Option<u32>, PluginError> {
+            Ok((params.value > 0).then_some(u32::from(params.value)))
+        }
+    }
+
+    /// Calls the generic entry point for `Fill` without
+    /// auxiliary inputs.
+    fn call(width: u32, height: u32, data: &mut [u8], params: &CStr) -> c_int {
+        // SAFETY: data has the size the callers declare and params
+        // is a valid C string.
+        unsafe {
+            process_image(
+                Fill::default,
+                width,
+                height,
+                data.as_mut_ptr(),
+                params.as_ptr(),
+                std::ptr::null(),
+                0,
+            )
+        }
+    }
+
+    #[test]
+    fn runs_plugin_on_valid_input() {
+        let mut data = [0u8; 8];
+        assert_eq!(call(2, 1, &mut data, cr#"{"value": 7}"#), 0);
+        assert_eq!(data, [7; 8]);
+    }
+
+    #[test]
+    fn null_pointers_are_rejected() {
+        // SAFETY: null pointers are checked before any access.
+        let code = unsafe {
+            process_image(
+                Fill::default,
+                1,
+                1,
+                std::ptr::null_mut(),
+                c"{}".as_ptr(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn zero_dimensions_are_rejected() {
+        let mut data = [0u8; 4];
+        assert_eq!(call(0, 1, &mut data, cr#"{"value": 1}"#), 2);
+        assert_eq!(call(1, 0, &mut data, cr#"{"value": 1}"#), 2);
+    }
+
+    #[test]
+    fn overflowing_size_is_rejected() {
+        let mut data = [0u8; 4];
+        assert_eq!(call(u32::MAX, u32::MAX, &mut data, c"{}"), 3);
+    }
+
+    #[test]
+    fn bad_params_are_rejected() {
+        let mut data = [0u8; 4];
+        assert_eq!(call(1, 1, &mut data, c"not json"), 4);
+        assert_eq!(call(1, 1, &mut data, c"{}"), 4);
+    }
+
+    #[test]
+    fn aux_inputs_without_support_are_rejected() {
+        let mut data = [0u8; 4];
+        let mask = [255u8; 4];
+        let aux = [AuxInput {
+            name: c"mask".as_ptr(),
+            width: 1,
+            height: 1,
+            rgba_data: mask.as_ptr(),
+        }];
+        // SAFETY: all pointers reference live buffers of the
+        // declared sizes.
+        let code = unsafe {
+            process_image(
+                Fill::default,
+                1,
+                1,
+                data.as_mut_ptr(),
+                cr#"{"value": 1}"#.as_ptr(),
+                aux.as_ptr(),
+                aux.len(),
+            )
+        };
+        assert_eq!(code, 5);
+    }
+
+    /// Describes a `width x height` sub-image of `data` starting
+    /// at byte `offset`, with rows `stride` bytes apart.
+    fn desc(
+        data: &mut [u8],
+        offset: usize,
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> ImageDesc {
+        ImageDesc {
+            data: data[offset..].as_mut_ptr(),
+            len: data.len() - offset,
+            width,
+            height,
+            stride_bytes: stride,
+            format: PIXEL_FORMAT_RGBA8,
+        }
+    }
+
+    /// Calls the descriptor entry point for `Fill`.
+    fn call_desc(image: &ImageDesc, params: &CStr, aux: &[AuxImage]) -> c_int {
+        // SAFETY: the descriptors built by the tests reference live
+        // buffers of at least `len` bytes.
+        unsafe {
+            process_image_desc(
+                Fill::default,
+                image,
+                params.as_ptr(),
+                aux.as_ptr(),
+                aux.len(),
+            )
+        }
+    }
+
+    #[test]
+    fn strided_sub_image_is_processed_in_place() {
+        // 3x3 image; process the 2x2 block at (1, 1).
+        let mut data = [0u8; 36];
+        let image = desc(&mut data, 16, 2, 2, 12);
+        assert_eq!(call_desc(&image, cr#"{"value": 9}"#, &[]), 0);
+
+        for (i, pixel) in data.as_chunks::<4>().0.iter().enumerate() {
+            let inside = i % 3 >= 1 && i / 3 >= 1;
+            assert_eq!(*pixel, [u8::from(inside) * 9; 4], "pixel {i}");
+        }
+    }
+
+    /// Test plugin that fails with its view's stride as the
+    /// error code.
+    #[derive(Default)]
+    struct Stride;
+
+    impl Plugin for Stride {
+        type Params = serde_json::Value;
+
+        fn process(
+            &self,
+            image: ImageViewMut<'_>,
+            _params: serde_json::Value,
+        ) -> Result<(), PluginError> {
+            let stride = c_int::try_from(image.stride()).unwrap();
+            Err(PluginError::Custom(stride))
+        }
+    }
+
+    #[test]
+    fn strided_images_are_not_packed_for_the_plugin() {
+        let mut data = [0u8; 36];
+        let image = desc(&mut data, 16, 2, 2, 12);
+        // SAFETY: the descriptor covers data and params is a
+        // valid C string.
+        let code = unsafe {
+            process_image_desc(
+                Stride::default,
+                &raw const image,
+                c"{}".as_ptr(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(code, 12);
+    }
+
+    #[test]
+    fn descriptor_length_is_bounds_checked() {
+        let mut data = [0u8; 36];
+        // Needs 12 + 8 = 20 bytes from offset 20; only 16 remain.
+        let image = desc(&mut data, 20, 2, 2, 12);
+        assert_eq!(call_desc(&image, cr#"{"value": 9}"#, &[]), 2);
+        assert_eq!(data, [0; 36]);
+    }
+
+    #[test]
+    fn invalid_descriptors_are_rejected() {
+        let mut data = [0u8; 16];
+        let narrow = desc(&mut data, 0, 2, 2, 4);
+        assert_eq!(call_desc(&narrow, cr#"{"value": 1}"#, &[]), 2);
+
+        let other_format = ImageDesc {
+            format: 1,
+            ..desc(&mut data, 0, 2, 2, 8)
+        };
+        assert_eq!(call_desc(&other_format, cr#"{"value": 1}"#, &[]), 7);
+
+        let null = ImageDesc {
+            data: std::ptr::null_mut(),
+            ..desc(&mut data, 0, 2, 2, 8)
+        };
+        assert_eq!(call_desc(&null, cr#"{"value": 1}"#, &[]), 1);
+    }
+
+    #[test]
+    fn strided_aux_inputs_are_packed() {
+        let mut data = [0u8; 4];
+        let image = desc(&mut data, 0, 1, 1, 4);
+        let mut mask = [7u8; 16];
+        let aux = [AuxImage {
+            name: c"mask".as_ptr(),
+            image: desc(&mut mask, 4, 1, 2, 8),
+        }];
+        // Fill rejects aux inputs after they validate.
+        assert_eq!(call_desc(&image, cr#"{"value": 1}"#, &aux), 5);
+
+        let short = [AuxImage {
+            name: c"mask".as_ptr(),
+            image: desc(&mut mask, 12, 1, 2, 8),
+        }];
+        assert_eq!(call_desc(&image, cr#"{"value": 1}"#, &short), 2);
+    }
+
+    #[test]
+    fn nul_terminated_appends_nul() {
+        const VERSION: [u8; 6] = nul_terminated("1.2.3");
+        assert_eq!(&VERSION, b"1.2.3\0");
+    }
+
+    #[test]
+    fn layout_packs_padded_rows() {
+        let layout = Layout {
+            width: 1,
+            height: 2,
+            stride: 6,
+            span: 10,
+        };
+        let data = [1, 2, 3, 4, 0, 0, 5, 6, 7, 8];
+        let packed = layout.pack(&data).into_owned();
+        assert_eq!(packed, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    /// Calls the tile info entry point for `Fill`.
+    fn call_tile_info(params: &CStr) -> (c_int, TileInfo) {
+        let mut info = TileInfo::default();
+        // SAFETY: params is a valid C string and info is writable.
+        let code =
+            unsafe { tile_info(Fill::default, params.as_ptr(), &raw mut info) };
+        (code, info)
+    }
+
+    #[test]
+    fn tile_info_reports_plugin_halo() {
+        let expected = TileInfo {
+            tile_safe: true,
+            halo: 3,
+        };
+        assert_eq!(call_tile_info(cr#"{"value": 3}"#), (0, expected));
+        assert_eq!(
+            call_tile_info(cr#"{"value": 0}"#),
+            (0, TileInfo::default())
+        );
+        assert_eq!(call_tile_info(c"{}").0, 4);
+    }
+
+    /// Reads the current thread's last error message.
+    fn last_error_message() -> Option<String> {
+        let ptr = last_error();
+        // SAFETY: a non-null pointer refers to the thread-local
+        // CString, which is not modified until the next call.
+        (!ptr.is_null()).then(|| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    #[test]
+    fn panics_are_caught() {
+        let mut data = [0u8; 4];
+        assert_eq!(call(1, 1, &mut data, cr#"{"value": 13}"#), 99);
+        assert_eq!(last_error_message().as_deref(), Some("unlucky value"));
+    }
+
+    #[test]
+    fn reported_errors_are_returned_as_last_error() {
+        let mut data = [0u8; 4];
+        assert_eq!(call(1, 1, &mut data, cr#"{"value": 42}"#), 8);
+        assert_eq!(
+            last_error_message().as_deref(),
+            Some("value too meaningful")
+        );
+        assert_eq!(data, [0; 4]);
+    }
+
+    #[test]
+    fn last_error_is_cleared_by_next_call() {
+        let mut data = [0u8; 4];
+        call(1, 1, &mut data, cr#"{"value": 13}"#);
+        assert!(last_error_message().is_some());
+        assert_eq!(call(1, 1, &mut data, cr#"{"value": 1}"#), 0);
+        assert!(last_error_message().is_none());
+    }
+
+    #[test]
+    fn alloc_returns_zeroed_buffer() {
+        let ptr = alloc(8);
+        // SAFETY: alloc returned 8 initialized bytes.
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, 8) };
+        assert_eq!(bytes, [0; 8]);
+        // SAFETY: ptr was returned by alloc(8).
+        unsafe { dealloc(ptr, 8) };
+
+        let empty = alloc(0);
+        assert!(!empty.is_null());
+        // SAFETY: empty was returned by alloc(0).
+        unsafe { dealloc(empty, 0) };
+    }
+
+    #[test]
+    fn panic_message_handles_payload_types() {
+        assert_eq!(panic_message(&"static"), "static");
+        assert_eq!(panic_message(&String::from("owned")), "owned");
+        assert_eq!(panic_message(&42_i32), "unknown panic");
+    }
+
+    #[test]
+    fn descriptor_reports_abi_and_thread_safety() {
+        /// Plugin that opts out of concurrent calls.
+        #[derive(Default)]
+        struct Serial;
+
+        impl Plugin for Serial {
+            type Params = serde_json::Value;
+            const THREAD_SAFE: bool = false;
+
+            fn process(
+                &self,
+                _image: ImageViewMut<'_>,
+                _params: Self::Params,
+            ) -> Result<(), PluginError> {
+                Ok(())
+            }
+        }
+
+        static FILL: PluginDescriptor =
+            PluginDescriptor::new::<Fill>("fill\0", "1.2.3\0");
+        let serial = PluginDescriptor::new::<Serial>("serial\0", "0.1.0\0");
+
+        assert_eq!(FILL.abi_version, ABI_VERSION);
+        assert_eq!(FILL.pixel_formats, PIXEL_FORMATS_RGBA8);
+        assert_eq!(FILL.flags, PLUGIN_FLAG_THREAD_SAFE);
+        assert_eq!(serial.flags, 0);
+        // SAFETY: the descriptor strings are static and
+        // NUL-terminated.
+        let (name, version) = unsafe {
+            (CStr::from_ptr(FILL.name), CStr::from_ptr(FILL.version))
+        };
+        assert_eq!(name, c"fill");
+        assert_eq!(version, c"1.2.3");
+    }
+}
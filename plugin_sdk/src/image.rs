@@ -0,0 +1,308 @@
+/// Number of bytes per RGBA8 pixel.
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// Mutable view of an RGBA8 image whose rows may be padded or
+/// belong to a larger image.
+///
+/// Row `y` starts at byte `y * stride` of the underlying
+/// buffer; only the first `width * 4` bytes of each row are
+/// pixel data. Plugins that need contiguous pixels use
+/// [`ImageViewMut::with_packed`].
+#[derive(Debug)]
+pub struct ImageViewMut<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a> ImageViewMut<'a> {
+    /// Wraps `data` as a tightly packed `width` x `height`
+    /// RGBA8 image.
+    ///
+    /// Returns `None` if either dimension is zero or
+    /// `data.len() != width * height * 4`.
+    pub fn new(
+        data: &'a mut [u8],
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        if expected_len(width, height) != Some(data.len()) {
+            return None;
+        }
+        Self::with_stride(data, width, height, width * BYTES_PER_PIXEL)
+    }
+
+    /// Wraps `data` as a `width` x `height` RGBA8 image whose
+    /// rows start `stride` bytes apart. `data` may extend past
+    /// the last row.
+    ///
+    /// Returns `None` if either dimension is zero, `stride` is
+    /// shorter than a row or `data` does not cover all rows.
+    pub fn with_stride(
+        data: &'a mut [u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Option<Self> {
+        let row_len = width.checked_mul(BYTES_PER_PIXEL)?;
+        let span = stride.checked_mul(height.checked_sub(1)?)?;
+        let span = span.checked_add(row_len)?;
+        (width > 0 && stride >= row_len && span <= data.len()).then(|| Self {
+            data: &mut data[..span],
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Width in pixels.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Distance in bytes between the starts of two rows.
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns `true` if rows have no padding between them.
+    pub const fn is_packed(&self) -> bool {
+        self.stride == self.row_len()
+    }
+
+    /// Pixel bytes of row `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y >= height`.
+    pub fn row(&self, y: usize) -> &[u8] {
+        assert!(y < self.height, "row {y} out of bounds");
+        let start = y * self.stride;
+        &self.data[start..start + self.row_len()]
+    }
+
+    /// Mutable pixel bytes of row `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y >= height`.
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        assert!(y < self.height, "row {y} out of bounds");
+        let start = y * self.stride;
+        let row_len = self.row_len();
+        &mut self.data[start..start + row_len]
+    }
+
+    /// Pixel bytes of each row, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        let row_len = self.row_len();
+        self.data
+            .chunks(self.stride)
+            .map(move |row| &row[..row_len])
+    }
+
+    /// Mutable pixel bytes of each row, top to bottom.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let row_len = self.row_len();
+        self.data
+            .chunks_mut(self.stride)
+            .map(move |row| &mut row[..row_len])
+    }
+
+    /// Runs `f` on the pixels packed row by row: directly on the
+    /// buffer if rows have no padding, otherwise on a copy that
+    /// is written back afterwards.
+    pub fn with_packed<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        if self.is_packed() {
+            return f(self.data);
+        }
+        let mut packed = self.to_packed();
+        let result = f(&mut packed);
+        self.copy_from_packed(&packed);
+        result
+    }
+
+    /// Copies the pixels into a new buffer packed row by row.
+    pub fn to_packed(&self) -> Vec<u8> {
+        self.rows().flatten().copied().collect()
+    }
+
+    /// Overwrites the pixels with `packed`, packed row by row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `packed.len() != width * height * 4`.
+    pub fn copy_from_packed(&mut self, packed: &[u8]) {
+        let row_len = self.row_len();
+        assert_eq!(
+            Some(packed.len()),
+            expected_len(self.width, self.height),
+            "packed buffer does not match the image size"
+        );
+        for (row, src) in self.rows_mut().zip(packed.chunks_exact(row_len)) {
+            row.copy_from_slice(src);
+        }
+    }
+
+    /// Bytes of pixel data per row.
+    const fn row_len(&self) -> usize {
+        self.width * BYTES_PER_PIXEL
+    }
+}
+
+/// Read-only view of a tightly packed RGBA8 image.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageView<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> ImageView<'a> {
+    /// Wraps `data` as a `width` x `height` RGBA8 image.
+    ///
+    /// Returns `None` if either dimension is zero or
+    /// `data.len() != width * height * 4`.
+    pub fn new(data: &'a [u8], width: usize, height: usize) -> Option<Self> {
+        (width > 0
+            && height > 0
+            && expected_len(width, height) == Some(data.len()))
+        .then_some(Self {
+            data,
+            width,
+            height,
+        })
+    }
+
+    /// Width in pixels.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Pixel bytes, row by row.
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// Named auxiliary images supplied by the host alongside the
+/// main buffer, e.g. masks.
+#[derive(Debug, Clone, Default)]
+pub struct AuxInputs<'a> {
+    inputs: Vec<(&'a str, ImageView<'a>)>,
+}
+
+impl<'a> AuxInputs<'a> {
+    /// Creates an empty set of inputs.
+    pub const fn new() -> Self {
+        Self { inputs: Vec::new() }
+    }
+
+    /// Adds a named input.
+    #[must_use]
+    pub fn with(mut self, name: &'a str, image: ImageView<'a>) -> Self {
+        self.inputs.push((name, image));
+        self
+    }
+
+    /// Looks up an input by name.
+    pub fn get(&self, name: &str) -> Option<ImageView<'a>> {
+        self.inputs
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, image)| image)
+    }
+
+    /// Returns `true` if no inputs were supplied.
+    pub const fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
+
+/// Byte length of a `width` x `height` RGBA8 buffer, or `None`
+/// on overflow.
+pub fn expected_len(width: usize, height: usize) -> Option<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|v| v.checked_mul(BYTES_PER_PIXEL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_requires_matching_length() {
+        let mut data = vec![0u8; 2 * 3 * BYTES_PER_PIXEL];
+        assert!(ImageViewMut::new(&mut data, 2, 3).is_some());
+        assert!(ImageViewMut::new(&mut data, 3, 3).is_none());
+        assert!(ImageViewMut::new(&mut data, 0, 3).is_none());
+        assert!(ImageView::new(&data, 6, 1).is_some());
+        assert!(ImageView::new(&data, 6, 2).is_none());
+    }
+
+    #[test]
+    fn with_stride_checks_row_and_buffer_size() {
+        let mut data = vec![0u8; 20];
+        assert!(ImageViewMut::with_stride(&mut data, 2, 2, 12).is_some());
+        assert!(ImageViewMut::with_stride(&mut data, 2, 2, 4).is_none());
+        assert!(ImageViewMut::with_stride(&mut data[..19], 2, 2, 12).is_none());
+        assert!(ImageViewMut::with_stride(&mut data, 2, 0, 12).is_none());
+    }
+
+    #[test]
+    fn strided_rows_skip_padding() {
+        // 2x2 view with stride 12 (one pixel of padding per row);
+        // every byte holds its buffer index.
+        let mut data: Vec<u8> = (0..20).collect();
+        let mut image = ImageViewMut::with_stride(&mut data, 2, 2, 12).unwrap();
+        assert!(!image.is_packed());
+        assert_eq!(image.row(1), &[12, 13, 14, 15, 16, 17, 18, 19]);
+        assert_eq!(image.to_packed()[..8], [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        image.rows_mut().for_each(|row| row.fill(0));
+        assert_eq!(data[8..12], [8, 9, 10, 11]);
+        assert!(data[..8].iter().chain(&data[12..]).all(|&v| v == 0));
+    }
+
+    #[test]
+    fn with_packed_writes_back_strided_pixels() {
+        let mut data = vec![0u8; 20];
+        let mut image = ImageViewMut::with_stride(&mut data, 2, 2, 12).unwrap();
+        image.with_packed(|packed| {
+            assert_eq!(packed.len(), 16);
+            packed.fill(7);
+        });
+        assert_eq!(data[..8], [7; 8]);
+        assert_eq!(data[8..12], [0; 4]);
+        assert_eq!(data[12..], [7; 8]);
+    }
+
+    #[test]
+    fn aux_inputs_lookup_by_name() {
+        let data = [1u8, 2, 3, 4];
+        let view = ImageView::new(&data, 1, 1).unwrap();
+        let aux = AuxInputs::new().with("mask", view);
+        assert!(!aux.is_empty());
+        assert_eq!(aux.get("mask").unwrap().data(), &data);
+        assert!(aux.get("other").is_none());
+        assert!(AuxInputs::new().is_empty());
+    }
+
+    #[test]
+    fn expected_len_detects_overflow() {
+        assert_eq!(expected_len(2, 3), Some(24));
+        assert_eq!(expected_len(usize::MAX, 2), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Safe SDK for writing image processing plugins.
+//!
+//! A plugin implements [`Plugin`] and exports the C ABI with
+//! [`declare_plugin!`]:
+//!
+//! ```
+//! use plugin_sdk::{ImageViewMut, Plugin, PluginError, declare_plugin};
+//!
+//! #[derive(Default)]
+//! struct Invert;
+//!
+//! impl Plugin for Invert {
+//!     type Params = serde_json::Value;
+//!
+//!     fn process(
+//!         &self,
+//!         mut image: ImageViewMut<'_>,
+//!         _params: Self::Params,
+//!     ) -> Result<(), PluginError> {
+//!         for row in image.rows_mut() {
+//!             for pixel in row.chunks_exact_mut(4) {
+//!                 pixel[..3].iter_mut().for_each(|v| *v = 255 - *v);
+//!             }
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! declare_plugin!(Invert);
+//! ```
+
+pub mod error;
+pub mod ffi;
+pub mod image;
+
+use serde::de::DeserializeOwned;
+
+pub use crate::error::PluginError;
+pub use crate::ffi::report_error;
+pub use crate::image::{AuxInputs, BYTES_PER_PIXEL, ImageView, ImageViewMut};
+
+/// An in-place RGBA8 image processing plugin.
+pub trait Plugin {
+    /// Parameters, deserialized from the JSON string passed by
+    /// the host.
+    type Params: DeserializeOwned;
+
+    /// Whether the plugin may be called from several threads at
+    /// once, reported to the host in the plugin descriptor.
+    ///
+    /// Every call gets a fresh plugin value, so this only needs
+    /// to be `false` for plugins sharing global state.
+    const THREAD_SAFE: bool = true;
+
+    /// Processes `image` in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PluginError`] whose code is reported to the
+    /// host.
+    fn process(
+        &self,
+        image: ImageViewMut<'_>,
+        params: Self::Params,
+    ) -> Result<(), PluginError>;
+
+    /// Processes `image` in place with auxiliary inputs (e.g. a
+    /// mask) supplied by the host.
+    ///
+    /// The default implementation forwards to
+    /// [`Plugin::process`] and rejects non-empty `aux`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PluginError::AuxInput`] if `aux` is non-empty
+    /// and the plugin does not override this method, or any
+    /// error returned by [`Plugin::process`].
+    fn process_with_aux(
+        &self,
+        image: ImageViewMut<'_>,
+        params: Self::Params,
+        aux: &AuxInputs<'_>,
+    ) -> Result<(), PluginError> {
+        if aux.is_empty() {
+            self.process(image, params)
+        } else {
+            Err(PluginError::AuxInput)
+        }
+    }
+
+    /// Declares whether the plugin is tile-safe for `params`,
+    /// and if so how many pixels of surrounding context (halo)
+    /// each tile needs.
+    ///
+    /// Returning `Some(halo)` promises that processing a tile
+    /// extended by `halo` pixels on every side (clipped to the
+    /// image) yields exactly the whole-image result inside the
+    /// tile. Auxiliary inputs are cropped like the image. The
+    /// default is `None` (not tile-safe).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PluginError`] if `params` are invalid.
+    fn tile_halo(
+        &self,
+        params: &Self::Params,
+    ) -> Result<Option<u32>, PluginError> {
+        let _ = params;
+        Ok(None)
+    }
+}
+
+/// Exports the plugin C ABI (`process_image`,
+/// `process_image_with_aux`, `process_image_desc`,
+/// `plugin_tile_info`, `plugin_last_error` and
+/// `plugin_descriptor`) for a [`Plugin`] type that implements
+/// [`Default`].
+///
+/// The descriptor names the plugin after the crate invoking the
+/// macro (`CARGO_PKG_NAME`). Its version is that crate's
+/// `CARGO_PKG_VERSION` unless given as a `&str` constant with
+/// `declare_plugin!(Plugin, version = core_crate::VERSION)`, so
+/// a plugin wrapping a core crate that is also linked into the
+/// host reports the same version both ways.
+///
+/// When building for `wasm32`, `plugin_alloc` and
+/// `plugin_dealloc` are exported as well, so the host can place
+/// buffers in the module's linear memory.
+///
+/// A fresh plugin value is created for every call. Pointer
+/// validation, parameter parsing, panic catching and error code
+/// mapping are handled by [`ffi::process_image`].
+#[macro_export]
+macro_rules! declare_plugin {
+    ($plugin:ty) => {
+        $crate::declare_plugin!($plugin, version = env!("CARGO_PKG_VERSION"));
+    };
+    ($plugin:ty, version = $version:expr) => {
+        /// Plugin entry point — exported with C-compatible ABI.
+        ///
+        /// Returns 0 on success, non-zero on error.
+        ///
+        /// # Safety
+        ///
+        /// - `rgba_data` must point to a valid buffer of size
+        ///   `width * height * 4` bytes.
+        /// - `params` must be a valid pointer to a null-terminated
+        ///   C string.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn process_image(
+            width: u32,
+            height: u32,
+            rgba_data: *mut u8,
+            params: *const ::std::ffi::c_char,
+        ) -> ::std::ffi::c_int {
+            // SAFETY: forwarded caller guarantees; no auxiliary inputs.
+            unsafe {
+                $crate::ffi::process_image(
+                    <$plugin as ::std::default::Default>::default,
+                    width,
+                    height,
+                    rgba_data,
+                    params,
+                    ::std::ptr::null(),
+                    0,
+                )
+            }
+        }
+
+        /// Plugin entry point with auxiliary inputs — exported
+        /// with C-compatible ABI.
+        ///
+        /// Returns 0 on success, non-zero on error.
+        ///
+        /// # Safety
+        ///
+        /// - `rgba_data` must point to a valid buffer of size
+        ///   `width * height * 4` bytes.
+        /// - `params` must be a valid pointer to a null-terminated
+        ///   C string.
+        /// - `aux` must point to `aux_count` valid `AuxInput`
+        ///   entries (or be null when `aux_count` is 0).
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn process_image_with_aux(
+            width: u32,
+            height: u32,
+            rgba_data: *mut u8,
+            params: *const ::std::ffi::c_char,
+            aux: *const $crate::ffi::AuxInput,
+            aux_count: usize,
+        ) -> ::std::ffi::c_int {
+            // SAFETY: forwarded caller guarantees.
+            unsafe {
+                $crate::ffi::process_image(
+                    <$plugin as ::std::default::Default>::default,
+                    width,
+                    height,
+                    rgba_data,
+                    params,
+                    aux,
+                    aux_count,
+                )
+            }
+        }
+
+        /// Plugin entry point taking image descriptors (with
+        /// explicit length and stride) — exported with
+        /// C-compatible ABI.
+        ///
+        /// Returns 0 on success, non-zero on error.
+        ///
+        /// # Safety
+        ///
+        /// - `image` must point to a valid `ImageDesc` whose
+        ///   buffer is valid for reads and writes of `len` bytes.
+        /// - `params` must be a valid pointer to a null-terminated
+        ///   C string.
+        /// - `aux` must point to `aux_count` valid `AuxImage`
+        ///   entries (or be null when `aux_count` is 0).
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn process_image_desc(
+            image: *const $crate::ffi::ImageDesc,
+            params: *const ::std::ffi::c_char,
+            aux: *const $crate::ffi::AuxImage,
+            aux_count: usize,
+        ) -> ::std::ffi::c_int {
+            // SAFETY: forwarded caller guarantees.
+            unsafe {
+                $crate::ffi::process_image_desc(
+                    <$plugin as ::std::default::Default>::default,
+                    image,
+                    params,
+                    aux,
+                    aux_count,
+                )
+            }
+        }
+
+        /// Reports whether the plugin is tile-safe for `params`
+        /// and the halo it needs — exported with C-compatible ABI.
+        ///
+        /// Returns 0 on success, non-zero on error.
+        ///
+        /// # Safety
+        ///
+        /// - `params` must be a valid pointer to a null-terminated
+        ///   C string.
+        /// - `info` must be valid for writing a `TileInfo`.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn plugin_tile_info(
+            params: *const ::std::ffi::c_char,
+            info: *mut $crate::ffi::TileInfo,
+        ) -> ::std::ffi::c_int {
+            // SAFETY: forwarded caller guarantees.
+            unsafe {
+                $crate::ffi::tile_info(
+                    <$plugin as ::std::default::Default>::default,
+                    params,
+                    info,
+                )
+            }
+        }
+
+        /// Returns the message of the last failed call on the
+        /// current thread (e.g. a panic message), or null.
+        ///
+        /// The string stays valid until the next plugin call on
+        /// the same thread.
+        #[unsafe(no_mangle)]
+        pub extern "C" fn plugin_last_error() -> *const ::std::ffi::c_char {
+            $crate::ffi::last_error()
+        }
+
+        /// Returns the plugin's static descriptor — exported with
+        /// C-compatible ABI.
+        #[unsafe(no_mangle)]
+        pub extern "C" fn plugin_descriptor()
+        -> *const $crate::ffi::PluginDescriptor {
+            const VERSION: &str = $version;
+            const VERSION_C: [u8; VERSION.len() + 1] =
+                $crate::ffi::nul_terminated(VERSION);
+            static DESCRIPTOR: $crate::ffi::PluginDescriptor =
+                $crate::ffi::PluginDescriptor::new::<$plugin>(
+                    concat!(env!("CARGO_PKG_NAME"), "\0"),
+                    match ::std::str::from_utf8(&VERSION_C) {
+                        Ok(version) => version,
+                        Err(_) => panic!("version is not UTF-8"),
+                    },
+                );
+            &raw const DESCRIPTOR
+        }
+
+        /// Allocates `len` zeroed bytes of linear memory for the
+        /// host — exported with C-compatible ABI.
+        #[cfg(target_arch = "wasm32")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn plugin_alloc(len: usize) -> *mut u8 {
+            $crate::ffi::alloc(len)
+        }
+
+        /// Frees a buffer returned by `plugin_alloc` — exported
+        /// with C-compatible ABI.
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must come from `plugin_alloc(len)` and not have
+        /// been freed since.
+        #[cfg(target_arch = "wasm32")]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn plugin_dealloc(ptr: *mut u8, len: usize) {
+            // SAFETY: forwarded caller guarantees.
+            unsafe { $crate::ffi::dealloc(ptr, len) }
+        }
+    };
+}
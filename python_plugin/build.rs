@@ -0,0 +1,12 @@
+//! Lets the plugin find the Python shared library it embeds
+//! when that is installed outside the loader's default search
+//! path (e.g. by pyenv).
+
+fn main() {
+    let windows =
+        std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows");
+    if let (Some(lib_dir), false) = (&pyo3_build_config::get().lib_dir, windows)
+    {
+        println!("cargo:rustc-link-arg-cdylib=-Wl,-rpath,{lib_dir}");
+    }
+}
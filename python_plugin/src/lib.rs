@@ -0,0 +1,211 @@
+//! Bridge plugin: runs a function from a Python script on the
+//! image through an embedded interpreter, so quick pixel
+//! experiments need no Rust plugin.
+//!
+//! Parameters name the script and function:
+//!
+//! ```json
+//! {"script": "tweaks/invert.py", "function": "process",
+//!  "input": "numpy", "args": {"amount": 0.5}}
+//! ```
+//!
+//! Scripts are only read from the directory named by the
+//! [`SCRIPT_DIR_VAR`] environment variable, or the current
+//! directory if it is unset: `script` is resolved against it,
+//! and paths leading outside of it (through `..`, absolute
+//! paths or symbolic links) are rejected, so parameters from an
+//! untrusted client cannot run arbitrary files.
+//!
+//! The script is read and executed afresh on every call. With
+//! `"input": "numpy"` (the default) the function is called as
+//! `function(image, args)` with an `H x W x 4` `uint8` array;
+//! with `"input": "bytes"` as `function(data, width, height,
+//! args)` with a `bytearray` of packed RGBA pixels. It either
+//! modifies its input in place and returns `None`, or returns
+//! new pixels of the same size. Python exceptions are reported
+//! to the host with their traceback.
+
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use plugin_sdk::{
+    BYTES_PER_PIXEL, ImageViewMut, Plugin, PluginError, report_error,
+};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyDict};
+use serde::Deserialize;
+
+/// Environment variable naming the directory scripts are read
+/// from.
+pub const SCRIPT_DIR_VAR: &str = "PYTHON_PLUGIN_SCRIPT_DIR";
+
+/// How the image is passed to the Python function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Input {
+    /// An `H x W x 4` `numpy` array.
+    #[default]
+    Numpy,
+    /// A `bytearray` of packed RGBA pixels, with the width and
+    /// height as separate arguments.
+    Bytes,
+}
+
+/// Parameters of [`PythonPlugin`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PythonParams {
+    /// Script defining the function, relative to the script
+    /// directory.
+    pub script: PathBuf,
+    /// Function to call.
+    #[serde(default = "default_function")]
+    pub function: String,
+    /// How the image is passed.
+    #[serde(default)]
+    pub input: Input,
+    /// JSON value passed to the function as its last argument.
+    #[serde(default = "empty_args")]
+    pub args: serde_json::Value,
+}
+
+fn default_function() -> String {
+    "process".to_owned()
+}
+
+fn empty_args() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+/// Runs a Python function on the image; see the crate
+/// documentation.
+#[derive(Default)]
+pub struct PythonPlugin;
+
+impl Plugin for PythonPlugin {
+    type Params = PythonParams;
+
+    fn process(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: PythonParams,
+    ) -> Result<(), PluginError> {
+        Python::initialize();
+        Python::attach(|py| {
+            run_script(py, &mut image, &params)
+                .map_err(|err| describe(py, &err))
+        })
+        .map_err(|message| {
+            report_error(&message);
+            PluginError::Failed
+        })
+    }
+}
+
+/// Executes the script and calls its function on `image`,
+/// copying the result back.
+fn run_script(
+    py: Python<'_>,
+    image: &mut ImageViewMut<'_>,
+    params: &PythonParams,
+) -> PyResult<()> {
+    let path = script_path(&params.script).map_err(PyValueError::new_err)?;
+    let code = fs::read_to_string(&path).map_err(|e| {
+        PyValueError::new_err(format!(
+            "cannot read script '{}': {e}",
+            params.script.display()
+        ))
+    })?;
+    let file_name = CString::new(params.script.to_string_lossy().as_ref())?;
+    let module = PyModule::from_code(
+        py,
+        &CString::new(code)?,
+        &file_name,
+        c"python_plugin_script",
+    )?;
+    let function = module.getattr(params.function.as_str())?;
+    let args = py
+        .import("json")?
+        .call_method1("loads", (params.args.to_string(),))?;
+
+    let (width, height) = (image.width(), image.height());
+    let data = PyByteArray::new(py, &image.to_packed());
+    let numpy = match params.input {
+        Input::Numpy => Some(py.import("numpy")?),
+        Input::Bytes => None,
+    };
+    let returned = if let Some(numpy) = &numpy {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("dtype", "uint8")?;
+        let array = numpy
+            .call_method("frombuffer", (&data,), Some(&kwargs))?
+            .call_method1("reshape", ((height, width, 4),))?;
+        function.call1((array, args))?
+    } else {
+        function.call1((&data, width, height, args))?
+    };
+
+    let pixels = if returned.is_none() {
+        data.into_any()
+    } else if let Some(numpy) = &numpy {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("dtype", "uint8")?;
+        numpy
+            .call_method("ascontiguousarray", (returned,), Some(&kwargs))?
+            .call_method0("tobytes")?
+    } else {
+        py.get_type::<PyBytes>().call1((returned,))?
+    };
+    let pixels = pixels.extract::<Vec<u8>>()?;
+    let expected = width * height * BYTES_PER_PIXEL;
+    if pixels.len() != expected {
+        return Err(PyValueError::new_err(format!(
+            "'{}' returned {} bytes, expected {expected} ({width}x{height} \
+             RGBA)",
+            params.function,
+            pixels.len(),
+        )));
+    }
+    image.copy_from_packed(&pixels);
+    Ok(())
+}
+
+/// Resolves `script` against the script directory, rejecting
+/// paths that lead outside of it.
+fn script_path(script: &Path) -> Result<PathBuf, String> {
+    let dir =
+        env::var_os(SCRIPT_DIR_VAR).map_or_else(|| ".".into(), PathBuf::from);
+    let dir = dir.canonicalize().map_err(|e| {
+        format!("cannot open script directory '{}': {e}", dir.display())
+    })?;
+    let path = dir.join(script).canonicalize().map_err(|e| {
+        format!("cannot read script '{}': {e}", script.display())
+    })?;
+    if !path.starts_with(&dir) {
+        return Err(format!(
+            "script '{}' is outside the script directory '{}' \
+             (set {SCRIPT_DIR_VAR} to change it)",
+            script.display(),
+            dir.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Formats a Python exception with its traceback.
+fn describe(py: Python<'_>, err: &PyErr) -> String {
+    let formatted = py.import("traceback").and_then(|traceback| {
+        traceback
+            .call_method1(
+                "format_exception",
+                (err.get_type(py), err.value(py), err.traceback(py)),
+            )?
+            .extract::<Vec<String>>()
+    });
+    formatted.map_or_else(|_| err.to_string(), |lines| lines.concat())
+}
+
+plugin_sdk::declare_plugin!(PythonPlugin);
@@ -0,0 +1,224 @@
+//! Scripting plugin: evaluates a small [Rhai](https://rhai.rs)
+//! script on every pixel, so simple effects need no Rust plugin.
+//!
+//! ```json
+//! {"script": "r = 255 - r; g = 255 - g; b = 255 - b;"}
+//! ```
+//!
+//! The script is compiled once per call and run for each pixel
+//! with `x`, `y`, `width` and `height` as constants and the
+//! channels `r`, `g`, `b` and `a` as variables. Whatever it
+//! leaves in the channels is written back, rounded and clamped
+//! to `0..=255`; they may be set to integers or floats. The
+//! results are collected in a scratch buffer and only written
+//! to the image once every pixel succeeded, so a failing
+//! script leaves the image unchanged. Compilation and
+//! evaluation errors are reported to the host with the failing
+//! pixel.
+
+use plugin_sdk::{
+    BYTES_PER_PIXEL, ImageViewMut, Plugin, PluginError, report_error,
+};
+use rhai::{AST, Dynamic, Engine, FLOAT, INT, Scope};
+use serde::Deserialize;
+
+/// Upper bound on the operations a script may run per pixel, so
+/// an endless loop fails instead of hanging the host.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Channel variables, in pixel byte order.
+const CHANNELS: [&str; BYTES_PER_PIXEL] = ["r", "g", "b", "a"];
+
+/// Parameters of [`ScriptPlugin`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptParams {
+    /// Rhai script run for every pixel.
+    pub script: String,
+}
+
+/// Evaluates a Rhai script on every pixel; see the crate
+/// documentation.
+#[derive(Default)]
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    type Params = ScriptParams;
+
+    fn process(
+        &self,
+        mut image: ImageViewMut<'_>,
+        params: ScriptParams,
+    ) -> Result<(), PluginError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine
+            .compile(&params.script)
+            .map_err(|e| format!("cannot compile script: {e}"))
+            .and_then(|ast| run_script(&engine, &ast, &mut image))
+            .map_err(|message| {
+                report_error(&message);
+                PluginError::Failed
+            })
+    }
+}
+
+/// Runs the compiled script on each pixel of `image`, writing
+/// the results back only if it succeeds on all of them.
+fn run_script(
+    engine: &Engine,
+    ast: &AST,
+    image: &mut ImageViewMut<'_>,
+) -> Result<(), String> {
+    let (width, height) = (image.width(), image.height());
+    let mut scope = Scope::new();
+    scope.push_constant("width", to_int(width));
+    scope.push_constant("height", to_int(height));
+    let base = scope.len();
+
+    let mut result = image.to_packed();
+    let (pixels, _) = result.as_chunks_mut::<BYTES_PER_PIXEL>();
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+        // Also drops variables the script declared for the last
+        // pixel.
+        scope.rewind(base);
+        scope.push_constant("x", to_int(x));
+        scope.push_constant("y", to_int(y));
+        for (channel, &value) in CHANNELS.iter().zip(pixel.iter()) {
+            scope.push(*channel, INT::from(value));
+        }
+
+        engine
+            .run_ast_with_scope(&mut scope, ast)
+            .map_err(|e| format!("script failed at ({x}, {y}): {e}"))?;
+
+        for (channel, value) in CHANNELS.iter().zip(pixel.iter_mut()) {
+            let result = scope.get(channel).expect("channels are in scope");
+            *value = to_channel(result).ok_or_else(|| {
+                format!(
+                    "script set '{channel}' to a {} at ({x}, {y}), \
+                     expected a number",
+                    result.type_name()
+                )
+            })?;
+        }
+    }
+    image.copy_from_packed(&result);
+    Ok(())
+}
+
+/// Converts a coordinate or dimension to a script integer.
+fn to_int(n: usize) -> INT {
+    INT::try_from(n).unwrap_or(INT::MAX)
+}
+
+/// Rounds and clamps a script number to a channel value.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_channel(value: &Dynamic) -> Option<u8> {
+    if let Some(int) = value.clone().try_cast::<INT>() {
+        return Some(int.clamp(0, 255) as u8);
+    }
+    // NaN saturates to 0.
+    let float = value.clone().try_cast::<FLOAT>()?;
+    Some(float.round().clamp(0.0, 255.0) as u8)
+}
+
+plugin_sdk::declare_plugin!(ScriptPlugin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `script` on a packed RGBA image of `width` x
+    /// `height`.
+    fn run(
+        data: &mut [u8],
+        width: usize,
+        height: usize,
+        script: &str,
+    ) -> Result<(), PluginError> {
+        let image = ImageViewMut::new(data, width, height).unwrap();
+        ScriptPlugin.process(
+            image,
+            ScriptParams {
+                script: script.to_owned(),
+            },
+        )
+    }
+
+    #[test]
+    fn scripts_update_channels() {
+        let mut data = vec![10, 20, 30, 40, 200, 100, 0, 255];
+        run(&mut data, 2, 1, "r = 255 - r; a = 255;").unwrap();
+        assert_eq!(data, vec![245, 20, 30, 255, 55, 100, 0, 255]);
+    }
+
+    #[test]
+    fn scripts_see_coordinates_and_dimensions() {
+        let mut data = vec![0; 3 * 2 * 4];
+        run(&mut data, 3, 2, "r = x; g = y; b = width; a = height;").unwrap();
+        let pixels: Vec<_> = data.chunks(4).collect();
+        assert_eq!(pixels[0], [0, 0, 3, 2]);
+        assert_eq!(pixels[2], [2, 0, 3, 2]);
+        assert_eq!(pixels[4], [1, 1, 3, 2]);
+    }
+
+    #[test]
+    fn results_are_rounded_and_clamped() {
+        let mut data = vec![100, 100, 100, 100];
+        run(&mut data, 1, 1, "r = r * 3; g = -1; b = 2.6; a = a / 3.0;")
+            .unwrap();
+        assert_eq!(data, vec![255, 0, 3, 33]);
+    }
+
+    #[test]
+    fn integers_and_floats_mix() {
+        let mut data = vec![200, 0, 100, 255, 200, 0, 100, 255];
+        run(
+            &mut data,
+            2,
+            1,
+            "let t = x / (width - 1.0); r = r * (1.0 - t); b = b * t;",
+        )
+        .unwrap();
+        assert_eq!(data, vec![200, 0, 0, 255, 0, 0, 100, 255]);
+    }
+
+    #[test]
+    fn declarations_do_not_leak_between_pixels() {
+        let mut data = vec![0; 2 * 4];
+        run(&mut data, 2, 1, "if x == 1 { r = seen; } let seen = 7;")
+            .unwrap_err();
+    }
+
+    #[test]
+    fn failing_scripts_leave_image_unchanged() {
+        let mut data = vec![10, 20, 30, 40, 50, 60, 70, 80];
+        let err = run(&mut data, 2, 1, "r = 0; if x == 1 { throw \"stop\"; }");
+        assert_eq!(err, Err(PluginError::Failed));
+        assert_eq!(data, vec![10, 20, 30, 40, 50, 60, 70, 80]);
+
+        let err =
+            run(&mut data, 2, 1, "g = 0; if y == 0 && x == 1 { b = (); }");
+        assert_eq!(err, Err(PluginError::Failed));
+        assert_eq!(data, vec![10, 20, 30, 40, 50, 60, 70, 80]);
+    }
+
+    #[test]
+    fn coordinates_are_read_only() {
+        let mut data = vec![0; 4];
+        assert_eq!(run(&mut data, 1, 1, "x = 1;"), Err(PluginError::Failed));
+    }
+
+    #[test]
+    fn bad_scripts_fail() {
+        let mut data = vec![0; 4];
+        assert_eq!(run(&mut data, 1, 1, "r = ("), Err(PluginError::Failed));
+        assert_eq!(
+            run(&mut data, 1, 1, "r = \"red\";"),
+            Err(PluginError::Failed)
+        );
+        assert_eq!(run(&mut data, 1, 1, "loop {}"), Err(PluginError::Failed));
+    }
+}